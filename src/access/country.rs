@@ -0,0 +1,208 @@
+//! Default access tables for countries whose laws differ from the worldwide
+//! defaults, keyed by
+//! [ISO 3166-1 alpha-2](https://en.wikipedia.org/wiki/ISO_3166-1_alpha-2)
+//! code. Each country table starts from the worldwide defaults and overrides
+//! only the highway types and modes that differ.
+//!
+//! Additional or corrected tables can be loaded from JSON mapping country
+//! code to highway type to mode to access value:
+//!
+//! ```json
+//! { "CH": { "trunk": { "bicycle": "no", "foot": "no" } } }
+//! ```
+//!
+//! https://wiki.openstreetmap.org/wiki/OSM_tags_for_routing/Access_restrictions
+use super::DefaultAccess;
+use crate::tag::{access::AccessLevel, highway::Highway, travel_by::Mode};
+use hashbrown::HashMap;
+use std::{collections, error, fmt};
+
+/// Highway type to mode to access value, as loaded from JSON
+type Overrides =
+    collections::HashMap<String, collections::HashMap<String, String>>;
+
+#[derive(Debug)]
+pub enum LoadError {
+    Json(serde_json::Error),
+    /// A key in the table isn't a transport mode
+    UnknownMode(String),
+}
+
+impl fmt::Display for LoadError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            LoadError::Json(e) => write!(f, "invalid access table: {}", e),
+            LoadError::UnknownMode(key) => {
+                write!(f, "unknown transport mode {} in access table", key)
+            }
+        }
+    }
+}
+
+impl error::Error for LoadError {}
+
+impl From<serde_json::Error> for LoadError {
+    fn from(e: serde_json::Error) -> Self {
+        LoadError::Json(e)
+    }
+}
+
+/// Default access tables by country with the worldwide table as fallback
+#[derive(Clone, Debug)]
+pub struct CountryDefaults {
+    world: DefaultAccess,
+    countries: HashMap<String, DefaultAccess>,
+}
+
+impl Default for CountryDefaults {
+    fn default() -> Self {
+        CountryDefaults {
+            world: DefaultAccess::world().clone(),
+            countries: HashMap::new(),
+        }
+    }
+}
+
+impl CountryDefaults {
+    /// Worldwide defaults with the built-in country tables
+    pub fn builtin() -> Self {
+        use AccessLevel::{No, Yes};
+        use Mode::{Bicycle, Foot, Horse, Moped};
+
+        let mut defaults = CountryDefaults::default();
+        let no_slow_traffic =
+            [(Foot, No), (Bicycle, No), (Horse, No), (Moped, No)];
+
+        // trunk roads signed as motorroads (Autostrasse, autoweg) are closed
+        // to slow traffic
+        for country in &["AT", "CH", "NL"] {
+            for highway in &[Highway::Trunk, Highway::TrunkLink] {
+                defaults.set_all(country, highway, &no_slow_traffic);
+            }
+        }
+
+        // public bridleways also carry walkers and cyclists
+        defaults.set_all(
+            "GB",
+            &Highway::Bridleway,
+            &[(Foot, Yes), (Bicycle, Yes)],
+        );
+        defaults.set_all("GB", &Highway::Cycleway, &[(Foot, Yes)]);
+
+        defaults.set_all("US", &Highway::Cycleway, &[(Foot, Yes)]);
+        defaults.set_all("US", &Highway::Pedestrian, &[(Bicycle, Yes)]);
+
+        defaults.set_all("DE", &Highway::Bridleway, &[(Foot, Yes)]);
+
+        defaults
+    }
+
+    /// Defaults for the country code or the worldwide defaults if the
+    /// country has no table
+    pub fn get(&self, country: &str) -> &DefaultAccess {
+        self.countries
+            .get(&country.to_ascii_uppercase())
+            .unwrap_or(&self.world)
+    }
+
+    /// Replace the table for a country
+    pub fn insert(&mut self, country: &str, defaults: DefaultAccess) {
+        self.countries
+            .insert(country.to_ascii_uppercase(), defaults);
+    }
+
+    /// Apply country tables from JSON on top of the existing tables. Nothing
+    /// is changed if the JSON is invalid.
+    pub fn load_json(&mut self, json: &str) -> Result<(), LoadError> {
+        let tables: collections::HashMap<String, Overrides> =
+            serde_json::from_str(json)?;
+        let mut parsed = Vec::new();
+
+        for (country, highways) in tables {
+            for (highway, modes) in highways {
+                let highway: Highway = highway.parse().unwrap();
+                let mut levels = Vec::new();
+                for (mode, level) in modes {
+                    let mode: Mode = mode
+                        .parse()
+                        .map_err(|_| LoadError::UnknownMode(mode))?;
+                    levels.push((mode, level.parse().unwrap()));
+                }
+                parsed.push((country.clone(), highway, levels));
+            }
+        }
+        for (country, highway, levels) in parsed {
+            self.set_all(&country, &highway, &levels);
+        }
+        Ok(())
+    }
+
+    fn set_all(
+        &mut self,
+        country: &str,
+        highway: &Highway,
+        levels: &[(Mode, AccessLevel)],
+    ) {
+        let world = &self.world;
+        let table = self
+            .countries
+            .entry(country.to_ascii_uppercase())
+            .or_insert_with(|| world.clone());
+
+        for (mode, level) in levels {
+            table.set(highway.clone(), *mode, level.clone());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{access::resolve, way::Way, TagMap};
+
+    #[test]
+    fn country_overrides_world_defaults() {
+        let mut tags = TagMap::new();
+        tags.insert("highway", "trunk");
+        let trunk = Way {
+            tags: Some(tags),
+            ..Default::default()
+        };
+        let defaults = CountryDefaults::builtin();
+
+        assert_eq!(
+            resolve(&trunk, Mode::Bicycle, defaults.get("ch")),
+            AccessLevel::No
+        );
+        assert_eq!(
+            resolve(&trunk, Mode::Bicycle, defaults.get("US")),
+            AccessLevel::Yes
+        );
+        assert_eq!(
+            resolve(&trunk, Mode::Bicycle, defaults.get("ZZ")),
+            AccessLevel::Yes
+        );
+    }
+
+    #[test]
+    fn load_json_tables() {
+        let mut defaults = CountryDefaults::builtin();
+        defaults
+            .load_json(r#"{ "BE": { "footway": { "bicycle": "yes" } } }"#)
+            .unwrap();
+        let footway = Highway::Footway;
+
+        assert_eq!(
+            defaults.get("BE").get(&footway, Mode::Bicycle),
+            Some(&AccessLevel::Yes)
+        );
+        assert_eq!(
+            defaults.get("BE").get(&footway, Mode::Foot),
+            Some(&AccessLevel::Designated)
+        );
+        assert!(matches!(
+            defaults.load_json(r#"{ "BE": { "path": { "rocket": "no" } } }"#),
+            Err(LoadError::UnknownMode(_))
+        ));
+    }
+}
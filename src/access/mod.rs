@@ -0,0 +1,192 @@
+//! Resolve whether a mode of transport may use an element by walking the
+//! [access hierarchy](https://wiki.openstreetmap.org/wiki/Key:access#Transport_mode_restrictions)
+//! from the most specific mode to `access`.
+//!
+//! Explicit tags always win: the first of the mode's own key or an ancestor
+//! key found on the element decides. Without any explicit tag, the default
+//! restrictions for the element's `highway` type apply, as listed at
+//! https://wiki.openstreetmap.org/wiki/OSM_tags_for_routing/Access_restrictions.
+//! Those defaults vary by country; see `country::CountryDefaults`.
+pub mod country;
+pub mod oneway;
+
+use crate::tag::{
+    access::AccessLevel, highway::Highway, travel_by::Mode, Tagged,
+};
+use hashbrown::HashMap;
+use std::sync::OnceLock;
+
+/// Access granted to each mode on each highway type when the way has no
+/// access tags of its own
+#[derive(Clone, Debug, Default)]
+pub struct DefaultAccess {
+    highways: HashMap<Highway, HashMap<Mode, AccessLevel>>,
+}
+
+impl DefaultAccess {
+    /// Worldwide defaults
+    pub fn world() -> &'static DefaultAccess {
+        static WORLD: OnceLock<DefaultAccess> = OnceLock::new();
+
+        WORLD.get_or_init(|| {
+            use AccessLevel::*;
+            use Mode::*;
+
+            let mut defaults = DefaultAccess::default();
+            let motorway = [
+                (Access, No),
+                (MotorVehicle, Yes),
+                (Moped, No),
+                (Mode::Agricultural, No),
+            ];
+            let foot_only = [(Access, No), (Foot, Designated)];
+
+            for highway in &[Highway::Motorway, Highway::MotorwayLink] {
+                defaults.set_all(highway.clone(), &motorway);
+            }
+            for highway in &[
+                Highway::Trunk,
+                Highway::TrunkLink,
+                Highway::Primary,
+                Highway::PrimaryLink,
+                Highway::Secondary,
+                Highway::SecondaryLink,
+                Highway::Tertiary,
+                Highway::TertiaryLink,
+                Highway::Unclassified,
+                Highway::Residential,
+                Highway::LivingStreet,
+                Highway::Road,
+                Highway::Service,
+                Highway::Track,
+            ] {
+                defaults.set(highway.clone(), Access, Yes);
+            }
+            defaults.set_all(Highway::Pedestrian, &[(Access, No), (Foot, Yes)]);
+            defaults.set_all(
+                Highway::Path,
+                &[(Access, No), (Foot, Yes), (Horse, Yes), (Bicycle, Yes)],
+            );
+            defaults.set_all(
+                Highway::Bridleway,
+                &[(Access, No), (Horse, Designated)],
+            );
+            defaults.set_all(
+                Highway::Cycleway,
+                &[(Access, No), (Bicycle, Designated)],
+            );
+            defaults.set_all(Highway::Footway, &foot_only);
+            defaults.set_all(Highway::Steps, &foot_only);
+            defaults.set_all(Highway::Corridor, &foot_only);
+            defaults.set_all(Highway::Platform, &foot_only);
+            defaults.set_all(
+                Highway::Busway,
+                &[(Access, No), (Psv, Designated), (Emergency, Yes)],
+            );
+            defaults.set_all(
+                Highway::BusGuideway,
+                &[(Access, No), (Bus, Designated)],
+            );
+            defaults.set(Highway::Construction, Access, No);
+            defaults.set(Highway::Proposed, Access, No);
+
+            defaults
+        })
+    }
+
+    /// Set the default for one mode on a highway type
+    pub fn set(&mut self, highway: Highway, mode: Mode, level: AccessLevel) {
+        self.highways
+            .entry(highway)
+            .or_default()
+            .insert(mode, level);
+    }
+
+    fn set_all(&mut self, highway: Highway, levels: &[(Mode, AccessLevel)]) {
+        for (mode, level) in levels {
+            self.set(highway.clone(), *mode, level.clone());
+        }
+    }
+
+    /// Default for the mode or its nearest ancestor on the highway type
+    pub fn get(&self, highway: &Highway, mode: Mode) -> Option<&AccessLevel> {
+        let modes = self.highways.get(highway)?;
+        mode.ancestors().find_map(|m| modes.get(&m))
+    }
+}
+
+/// Access for the mode using the worldwide defaults
+pub fn access<T: Tagged + ?Sized>(element: &T, mode: Mode) -> AccessLevel {
+    resolve(element, mode, DefaultAccess::world())
+}
+
+/// Access for the mode using the defaults of the country (ISO 3166-1 code)
+/// the element is in
+pub fn access_in<T: Tagged + ?Sized>(
+    element: &T,
+    mode: Mode,
+    country: &str,
+    defaults: &country::CountryDefaults,
+) -> AccessLevel {
+    resolve(element, mode, defaults.get(country))
+}
+
+/// Access for the mode from the element's tags, falling back to the given
+/// defaults for its highway type. Elements that have neither are open to
+/// every mode.
+pub fn resolve<T: Tagged + ?Sized>(
+    element: &T,
+    mode: Mode,
+    defaults: &DefaultAccess,
+) -> AccessLevel {
+    mode.ancestors()
+        .find_map(|m| element.get_tag(m.key()))
+        .map(|value| value.parse().unwrap())
+        .or_else(|| {
+            Highway::of(element)
+                .and_then(|highway| defaults.get(&highway, mode).cloned())
+        })
+        .unwrap_or(AccessLevel::Yes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{way::Way, TagMap};
+
+    fn way(tags: &[(&'static str, &'static str)]) -> Way<'static> {
+        Way {
+            tags: Some(tags.iter().copied().collect::<TagMap>()),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn highway_defaults() {
+        let footway = way(&[("highway", "footway")]);
+        assert_eq!(access(&footway, Mode::Foot), AccessLevel::Designated);
+        assert_eq!(access(&footway, Mode::Bicycle), AccessLevel::No);
+
+        let motorway = way(&[("highway", "motorway")]);
+        assert_eq!(access(&motorway, Mode::Motorcar), AccessLevel::Yes);
+        assert_eq!(access(&motorway, Mode::Bicycle), AccessLevel::No);
+        assert_eq!(access(&motorway, Mode::Moped), AccessLevel::No);
+    }
+
+    #[test]
+    fn explicit_tags_follow_hierarchy() {
+        let road = way(&[
+            ("highway", "residential"),
+            ("motor_vehicle", "destination"),
+            ("vehicle", "no"),
+            ("bicycle", "yes"),
+        ]);
+        assert_eq!(access(&road, Mode::Motorcar), AccessLevel::Destination);
+        assert_eq!(access(&road, Mode::Bicycle), AccessLevel::Yes);
+        assert_eq!(access(&road, Mode::Carriage), AccessLevel::No);
+        assert_eq!(access(&road, Mode::Foot), AccessLevel::Yes);
+
+        let private = way(&[("highway", "footway"), ("access", "private")]);
+        assert_eq!(access(&private, Mode::Foot), AccessLevel::Private);
+    }
+}
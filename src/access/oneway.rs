@@ -0,0 +1,140 @@
+//! Resolve the directions a mode of transport may travel along a way from
+//! [`oneway`](https://wiki.openstreetmap.org/wiki/Key:oneway) and its
+//! mode-specific forms such as `oneway:bicycle=no` or `oneway:psv=no`.
+//!
+//! Roundabouts and motorways are oneway unless tagged otherwise. Pedestrians
+//! are only bound by `oneway:foot`, and cyclists may travel against the flow
+//! on `cycleway=opposite*` contraflow lanes.
+use crate::tag::{
+    access::ONE_WAY, highway::Highway, travel_by::Mode, Tagged, JUNCTION_TYPE,
+};
+
+/// Key holding cycleway details of a road, including contraflow lanes
+pub static CYCLEWAY: &'static str = "cycleway";
+
+/// Directions of travel relative to the order of the way's nodes
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Direction {
+    Forward,
+    Backward,
+    Both,
+    /// Either direction but only one at a time, for `oneway=reversible`
+    /// (changing by time of day) and `oneway=alternating` (such as a single
+    /// lane controlled by traffic lights)
+    Reversible,
+}
+
+impl Direction {
+    /// Whether the way can be travelled in node order, at least some of the
+    /// time
+    pub fn allows_forward(&self) -> bool {
+        !matches!(self, Direction::Backward)
+    }
+
+    /// Whether the way can be travelled against node order, at least some of
+    /// the time
+    pub fn allows_backward(&self) -> bool {
+        !matches!(self, Direction::Forward)
+    }
+
+    fn parse(value: &str) -> Option<Direction> {
+        match value {
+            "yes" | "true" | "1" => Some(Direction::Forward),
+            "-1" | "reverse" => Some(Direction::Backward),
+            "no" | "false" | "0" => Some(Direction::Both),
+            "reversible" | "alternating" => Some(Direction::Reversible),
+            _ => None,
+        }
+    }
+}
+
+/// Directions the mode may travel along the element
+pub fn direction<T: Tagged + ?Sized>(element: &T, mode: Mode) -> Direction {
+    // the most specific `oneway:<mode>` tag decides
+    let specific = mode
+        .ancestors()
+        .take_while(|m| *m != Mode::Access)
+        .find_map(|m| {
+            element
+                .get_tag(&format!("{}:{}", ONE_WAY, m.key()))
+                .and_then(Direction::parse)
+        });
+    if let Some(direction) = specific {
+        return direction;
+    }
+    if mode == Mode::Foot {
+        return Direction::Both;
+    }
+
+    let general = match element.get_tag(ONE_WAY) {
+        Some(value) => Direction::parse(value).unwrap_or(Direction::Forward),
+        None if is_implied_oneway(element) => Direction::Forward,
+        None => Direction::Both,
+    };
+    if mode == Mode::Bicycle
+        && matches!(general, Direction::Forward | Direction::Backward)
+        && has_contraflow(element)
+    {
+        return Direction::Both;
+    }
+    general
+}
+
+/// Roundabouts and motorways are oneway without being tagged
+fn is_implied_oneway<T: Tagged + ?Sized>(element: &T) -> bool {
+    matches!(
+        element.get_tag(JUNCTION_TYPE),
+        Some("roundabout") | Some("circular")
+    ) || Highway::of(element) == Some(Highway::Motorway)
+}
+
+/// Whether a cycleway lets bicycles travel against the oneway direction
+fn has_contraflow<T: Tagged + ?Sized>(element: &T) -> bool {
+    ["", ":left", ":right", ":both"].iter().any(|side| {
+        element
+            .get_tag(&format!("{}{}", CYCLEWAY, side))
+            .is_some_and(|value| value.starts_with("opposite"))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::TagMap;
+
+    fn tags(pairs: &[(&'static str, &'static str)]) -> TagMap {
+        pairs.iter().copied().collect()
+    }
+
+    #[test]
+    fn tagged_and_implied_oneways() {
+        let oneway = tags(&[("highway", "residential"), ("oneway", "-1")]);
+        assert_eq!(direction(&oneway, Mode::Motorcar), Direction::Backward);
+        assert_eq!(direction(&oneway, Mode::Foot), Direction::Both);
+
+        let roundabout = tags(&[("junction", "roundabout")]);
+        assert_eq!(direction(&roundabout, Mode::Bicycle), Direction::Forward);
+
+        let motorway = tags(&[("highway", "motorway"), ("oneway", "no")]);
+        assert_eq!(direction(&motorway, Mode::Motorcar), Direction::Both);
+
+        let reversible = tags(&[("oneway", "reversible")]);
+        assert!(direction(&reversible, Mode::Hgv).allows_backward());
+    }
+
+    #[test]
+    fn mode_exceptions() {
+        let street = tags(&[
+            ("oneway", "yes"),
+            ("oneway:psv", "no"),
+            ("cycleway:left", "opposite_lane"),
+        ]);
+
+        assert_eq!(direction(&street, Mode::Bus), Direction::Both);
+        assert_eq!(direction(&street, Mode::Bicycle), Direction::Both);
+        assert_eq!(direction(&street, Mode::Motorcar), Direction::Forward);
+
+        let street = tags(&[("oneway", "yes"), ("oneway:bicycle", "no")]);
+        assert_eq!(direction(&street, Mode::Bicycle), Direction::Both);
+    }
+}
@@ -0,0 +1,423 @@
+//! [Conditional restrictions](https://wiki.openstreetmap.org/wiki/Conditional_restrictions)
+//! tagged as `<key>:conditional=<value> @ <condition>[; ...]`, for example
+//! `access:conditional=no @ (Mo-Fr 07:00-19:00)` or
+//! `maxspeed:conditional=30 @ (weight>7.5); 60 @ wet`.
+//!
+//! Several conditions may be combined with `AND`. Time conditions use the
+//! `opening_hours` syntax. When more than one restriction applies, the last
+//! one takes precedence.
+use crate::{
+    opening_hours::OpeningHours,
+    tag::{
+        measure::{parse_length, parse_weight},
+        Tagged,
+    },
+};
+use chrono::NaiveDateTime;
+use std::{error, fmt, str::FromStr};
+
+/// Suffix added to a key to make it conditional
+pub static SUFFIX: &'static str = ":conditional";
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct ParseError(pub String);
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "invalid conditional restriction: {}", self.0)
+    }
+}
+
+impl error::Error for ParseError {}
+
+/// Vehicle property that can be compared in a condition
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Property {
+    /// Tonnes
+    Weight,
+    /// Tonnes
+    AxleLoad,
+    /// Meters
+    Height,
+    /// Meters
+    Width,
+    /// Meters
+    Length,
+    Occupants,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Comparison {
+    Less,
+    LessOrEqual,
+    Greater,
+    GreaterOrEqual,
+    Equal,
+}
+
+impl Comparison {
+    fn test(&self, a: f32, b: f32) -> bool {
+        match self {
+            Comparison::Less => a < b,
+            Comparison::LessOrEqual => a <= b,
+            Comparison::Greater => a > b,
+            Comparison::GreaterOrEqual => a >= b,
+            Comparison::Equal => (a - b).abs() < f32::EPSILON,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Condition {
+    /// Times the `opening_hours` value is open
+    Time(OpeningHours),
+    Vehicle {
+        property: Property,
+        comparison: Comparison,
+        limit: f32,
+    },
+    /// Road surface is wet
+    Wet,
+    /// Road is covered with snow
+    Snow,
+    /// A condition that isn't understood, such as a user-defined one. It
+    /// never applies.
+    Other(String),
+}
+
+/// Value that applies when all its conditions are met
+#[derive(Clone, Debug, PartialEq)]
+pub struct ConditionalValue {
+    pub value: String,
+    pub conditions: Vec<Condition>,
+}
+
+/// Parsed value of a `*:conditional` tag
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Conditional {
+    pub values: Vec<ConditionalValue>,
+}
+
+/// Vehicle dimensions in tonnes and meters. Unknown properties never match
+/// a condition.
+#[derive(Clone, Debug, Default)]
+pub struct Vehicle {
+    pub weight: Option<f32>,
+    pub axle_load: Option<f32>,
+    pub height: Option<f32>,
+    pub width: Option<f32>,
+    pub length: Option<f32>,
+    pub occupants: Option<f32>,
+}
+
+impl Vehicle {
+    fn get(&self, property: Property) -> Option<f32> {
+        match property {
+            Property::Weight => self.weight,
+            Property::AxleLoad => self.axle_load,
+            Property::Height => self.height,
+            Property::Width => self.width,
+            Property::Length => self.length,
+            Property::Occupants => self.occupants,
+        }
+    }
+}
+
+/// Circumstances that conditions are evaluated against
+#[derive(Clone, Debug)]
+pub struct Context {
+    /// Local time
+    pub time: NaiveDateTime,
+    pub vehicle: Vehicle,
+    pub wet: bool,
+    pub snow: bool,
+}
+
+impl Context {
+    pub fn new(time: NaiveDateTime, vehicle: Vehicle) -> Self {
+        Context {
+            time,
+            vehicle,
+            wet: false,
+            snow: false,
+        }
+    }
+}
+
+impl Conditional {
+    /// Parsed `<key>:conditional` tag of the element, if present
+    pub fn of<T: Tagged + ?Sized>(
+        element: &T,
+        key: &str,
+    ) -> Option<Result<Conditional, ParseError>> {
+        element
+            .get_tag(&format!("{}{}", key, SUFFIX))
+            .map(str::parse)
+    }
+
+    /// Value of the last restriction whose conditions all apply
+    pub fn value(&self, context: &Context) -> Option<&str> {
+        self.values
+            .iter()
+            .rev()
+            .find(|v| v.conditions.iter().all(|c| c.applies(context)))
+            .map(|v| v.value.as_str())
+    }
+}
+
+/// Value of the key in the given context: the applicable conditional value if
+/// there is one, otherwise the plain tag value
+pub fn effective_value<T: Tagged + ?Sized>(
+    element: &T,
+    key: &str,
+    context: &Context,
+) -> Option<String> {
+    Conditional::of(element, key)
+        .and_then(Result::ok)
+        .and_then(|c| c.value(context).map(str::to_owned))
+        .or_else(|| element.get_tag(key).map(str::to_owned))
+}
+
+impl Condition {
+    pub fn applies(&self, context: &Context) -> bool {
+        match self {
+            Condition::Time(hours) => hours.is_open(context.time),
+            Condition::Vehicle {
+                property,
+                comparison,
+                limit,
+            } => context
+                .vehicle
+                .get(*property)
+                .is_some_and(|v| comparison.test(v, *limit)),
+            Condition::Wet => context.wet,
+            Condition::Snow => context.snow,
+            Condition::Other(_) => false,
+        }
+    }
+}
+
+impl FromStr for Conditional {
+    type Err = ParseError;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        let values = split_outside_parens(text, ';')
+            .into_iter()
+            .filter(|part| !part.trim().is_empty())
+            .map(parse_value)
+            .collect::<Result<_, _>>()?;
+
+        Ok(Conditional { values })
+    }
+}
+
+fn parse_value(text: &str) -> Result<ConditionalValue, ParseError> {
+    let at = text
+        .find('@')
+        .ok_or_else(|| ParseError(format!("missing @ in {}", text)))?;
+    let value = text[..at].trim();
+    let condition = text[at + 1..].trim();
+
+    if value.is_empty() || condition.is_empty() {
+        return Err(ParseError(text.trim().to_owned()));
+    }
+    let conditions = strip_parens(condition)
+        .split(" AND ")
+        .map(|c| parse_condition(strip_parens(c.trim())))
+        .collect::<Result<_, _>>()?;
+
+    Ok(ConditionalValue {
+        value: value.to_owned(),
+        conditions,
+    })
+}
+
+fn parse_condition(text: &str) -> Result<Condition, ParseError> {
+    match text {
+        "wet" => return Ok(Condition::Wet),
+        "snow" => return Ok(Condition::Snow),
+        _ => (),
+    }
+    if let Some(condition) = parse_comparison(text)? {
+        return Ok(condition);
+    }
+    match text.parse::<OpeningHours>() {
+        Ok(hours) => Ok(Condition::Time(hours)),
+        // not a time at all, such as a user-defined condition
+        Err(e) if e.position == 0 => Ok(Condition::Other(text.to_owned())),
+        Err(e) => Err(ParseError(format!("{} in {}", e, text))),
+    }
+}
+
+fn parse_comparison(text: &str) -> Result<Option<Condition>, ParseError> {
+    let operators = [
+        (">=", Comparison::GreaterOrEqual),
+        ("<=", Comparison::LessOrEqual),
+        (">", Comparison::Greater),
+        ("<", Comparison::Less),
+        ("=", Comparison::Equal),
+    ];
+    let found = operators
+        .iter()
+        .find_map(|(op, c)| text.find(op).map(|i| (i, op.len(), *c)));
+
+    let (i, len, comparison) = match found {
+        Some(f) => f,
+        None => return Ok(None),
+    };
+    let property = match text[..i].trim() {
+        "weight" => Property::Weight,
+        "axleload" => Property::AxleLoad,
+        "height" => Property::Height,
+        "width" => Property::Width,
+        "length" => Property::Length,
+        "occupants" => Property::Occupants,
+        _ => return Ok(None),
+    };
+    let limit = text[i + len..].trim();
+    let limit = match property {
+        Property::Weight | Property::AxleLoad => parse_weight(limit).ok(),
+        Property::Occupants => limit.parse().ok(),
+        _ => parse_length(limit).ok(),
+    }
+    .ok_or_else(|| ParseError(format!("invalid limit in {}", text)))?;
+
+    Ok(Some(Condition::Vehicle {
+        property,
+        comparison,
+        limit,
+    }))
+}
+
+/// Text without the parentheses around it, if the first opening parenthesis
+/// is closed by the last character. `(weight>7.5) AND (Mo-Fr)` is left as
+/// it is.
+fn strip_parens(text: &str) -> &str {
+    if !text.starts_with('(') {
+        return text;
+    }
+    let mut depth = 0;
+    for (i, c) in text.char_indices() {
+        match c {
+            '(' => depth += 1,
+            ')' => depth -= 1,
+            _ => (),
+        }
+        if depth == 0 {
+            return if i == text.len() - 1 {
+                text[1..i].trim()
+            } else {
+                text
+            };
+        }
+    }
+    text
+}
+
+/// Split on the separator wherever it isn't within parentheses
+fn split_outside_parens(text: &str, separator: char) -> Vec<&str> {
+    let mut parts = Vec::new();
+    let mut depth = 0;
+    let mut start = 0;
+
+    for (i, c) in text.char_indices() {
+        match c {
+            '(' => depth += 1,
+            ')' => depth -= 1,
+            c if c == separator && depth == 0 => {
+                parts.push(&text[start..i]);
+                start = i + c.len_utf8();
+            }
+            _ => (),
+        }
+    }
+    parts.push(&text[start..]);
+    parts
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDate;
+
+    fn at(day: u32, hour: u32) -> NaiveDateTime {
+        // 2020-06-01 was a Monday
+        NaiveDate::from_ymd_opt(2020, 6, day)
+            .unwrap()
+            .and_hms_opt(hour, 0, 0)
+            .unwrap()
+    }
+
+    #[test]
+    fn parses_values_and_conditions() {
+        let parsed: Conditional =
+            "no @ (Mo-Fr 07:00-19:00); destination @ (weight>7.5 AND wet)"
+                .parse()
+                .unwrap();
+
+        assert_eq!(parsed.values.len(), 2);
+        assert_eq!(parsed.values[0].value, "no");
+        assert_eq!(
+            parsed.values[1].conditions,
+            vec![
+                Condition::Vehicle {
+                    property: Property::Weight,
+                    comparison: Comparison::Greater,
+                    limit: 7.5
+                },
+                Condition::Wet
+            ]
+        );
+        assert!("no (Mo-Fr)".parse::<Conditional>().is_err());
+
+        let bracketed: Conditional =
+            "delivery @ (weight>7.5) AND (Mo-Fr)".parse().unwrap();
+        assert_eq!(bracketed.values[0].conditions.len(), 2);
+        assert_eq!(
+            bracketed.values[0].conditions[0],
+            Condition::Vehicle {
+                property: Property::Weight,
+                comparison: Comparison::Greater,
+                limit: 7.5
+            }
+        );
+        assert!(matches!(
+            bracketed.values[0].conditions[1],
+            Condition::Time(_)
+        ));
+    }
+
+    #[test]
+    fn evaluates_time_and_vehicle() {
+        let parsed: Conditional = "no @ (Mo-Fr 07:00-19:00); 30 @ (weight>7.5)"
+            .parse()
+            .unwrap();
+        let car = Vehicle::default();
+        let truck = Vehicle {
+            weight: Some(12.0),
+            ..Default::default()
+        };
+
+        assert_eq!(
+            parsed.value(&Context::new(at(1, 8), car.clone())),
+            Some("no")
+        );
+        assert_eq!(parsed.value(&Context::new(at(6, 8), car)), None);
+        assert_eq!(parsed.value(&Context::new(at(1, 8), truck)), Some("30"));
+    }
+
+    #[test]
+    fn time_conditions_use_opening_hours() {
+        let parsed: Conditional =
+            "no @ (Fr 22:00-06:00; PH off); yes @ delivery"
+                .parse()
+                .unwrap();
+        let context =
+            |day, hour| Context::new(at(day, hour), Vehicle::default());
+
+        assert_eq!(parsed.value(&context(5, 23)), Some("no"));
+        assert_eq!(parsed.value(&context(6, 3)), Some("no"));
+        assert_eq!(parsed.value(&context(6, 23)), None);
+        assert!("no @ (Mo-Fr 07:00-19:61)".parse::<Conditional>().is_err());
+    }
+}
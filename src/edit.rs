@@ -0,0 +1,574 @@
+//! Editing operations that change way geometry while keeping the relations
+//! that reference those ways consistent. Each operation returns the elements
+//! it created, modified or deleted so they can be uploaded as an
+//! [OsmChange](https://wiki.openstreetmap.org/wiki/OsmChange).
+use crate::{
+    item_type,
+    node::Node,
+    relation::{role, Member, Relation},
+    tag::{self, reverse, Tagged},
+    AreaData, ElementID, TagMap, Way,
+};
+use std::{error, fmt};
+
+/// Element type (one of the `item_type` values) and ID
+pub type ElementKey = (&'static str, ElementID);
+
+/// Elements affected by an edit, grouped as in an OsmChange document
+#[derive(Debug, Default, PartialEq)]
+pub struct Changes {
+    pub create: Vec<ElementKey>,
+    pub modify: Vec<ElementKey>,
+    pub delete: Vec<ElementKey>,
+}
+
+impl Changes {
+    fn modify_relation(&mut self, id: ElementID) {
+        let key = (item_type::RELATION, id);
+        if !self.modify.contains(&key) {
+            self.modify.push(key);
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum EditError {
+    WayNotFound(ElementID),
+    /// The node isn't an inner node of the way, so splitting there would
+    /// leave an empty way
+    NotInnerNode {
+        way: ElementID,
+        node: ElementID,
+    },
+    /// Closed ways need two split points
+    ClosedWay(ElementID),
+    /// The ways don't share an end node
+    NotConnected(ElementID, ElementID),
+    /// Both ways have the key with different values
+    TagConflict(String),
+    /// The ways play different parts in the same turn restriction
+    RestrictionConflict(ElementID),
+}
+
+impl fmt::Display for EditError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            EditError::WayNotFound(id) => write!(f, "way {} not found", id),
+            EditError::NotInnerNode { way, node } => {
+                write!(f, "node {} is not an inner node of way {}", node, way)
+            }
+            EditError::ClosedWay(id) => {
+                write!(f, "closed way {} cannot be split at one node", id)
+            }
+            EditError::NotConnected(a, b) => {
+                write!(f, "ways {} and {} do not share an end node", a, b)
+            }
+            EditError::TagConflict(key) => {
+                write!(f, "ways have different values for {}", key)
+            }
+            EditError::RestrictionConflict(id) => write!(
+                f,
+                "ways have different roles in restriction relation {}",
+                id
+            ),
+        }
+    }
+}
+
+impl error::Error for EditError {}
+
+impl<'a> AreaData<'a> {
+    /// ID for a new way that isn't yet in the database. New elements have
+    /// negative IDs until they're uploaded.
+    pub fn new_way_id(&self) -> ElementID {
+        self.ways
+            .keys()
+            .copied()
+            .filter(|id| *id < 0)
+            .min()
+            .unwrap_or(0)
+            - 1
+    }
+
+    /// Split a way at an inner node. The original way keeps the nodes up to
+    /// and including the split node and a new way with the same tags takes
+    /// the rest.
+    ///
+    /// Relations that contain the way gain the new way as a member next to
+    /// the original, ordered so that route relations stay continuous. In turn
+    /// restrictions, a `from` or `to` member is replaced by whichever part
+    /// still connects to the `via` member.
+    pub fn split_way(
+        &mut self,
+        way_id: ElementID,
+        node_id: ElementID,
+    ) -> Result<Changes, EditError> {
+        let new_id = self.new_way_id();
+        let way = self
+            .ways
+            .get_mut(&way_id)
+            .ok_or(EditError::WayNotFound(way_id))?;
+
+        if way.is_closed() {
+            return Err(EditError::ClosedWay(way_id));
+        }
+        let at = way
+            .nodes
+            .iter()
+            .enumerate()
+            .skip(1)
+            .take(way.nodes.len().saturating_sub(2))
+            .find(|(_, n)| n.id == node_id)
+            .map(|(i, _)| i)
+            .ok_or(EditError::NotInnerNode {
+                way: way_id,
+                node: node_id,
+            })?;
+
+        let tail: Vec<&'a Node> = way.nodes.split_off(at + 1);
+        let mut second = vec![way.nodes[at]];
+        second.extend(tail);
+
+        let first = way.nodes.clone();
+        let new_way = Way {
+            nodes: second.clone(),
+            id: new_id,
+            name: way.name.clone(),
+            timestamp: None,
+            tags: way.tags.clone(),
+        };
+        self.ways.insert(new_id, new_way);
+
+        let mut changes = Changes {
+            create: vec![(item_type::WAY, new_id)],
+            modify: vec![(item_type::WAY, way_id)],
+            delete: Vec::new(),
+        };
+
+        for relation in self.relations.iter_mut() {
+            if split_membership(relation, way_id, new_id, &first, &second) {
+                changes.modify_relation(relation.id);
+            }
+        }
+        Ok(changes)
+    }
+
+    /// Join two ways that share an end node into the first way and delete
+    /// the second. The second way is reversed, along with its
+    /// direction-dependent tags and roles, if its direction doesn't follow
+    /// the first. Fails without changing anything if the ways have
+    /// conflicting tags or different roles in one turn restriction, or if
+    /// only one is in a turn restriction whose via node they share.
+    pub fn merge_ways(
+        &mut self,
+        keep_id: ElementID,
+        merge_id: ElementID,
+    ) -> Result<Changes, EditError> {
+        let keep = self
+            .ways
+            .get(&keep_id)
+            .ok_or(EditError::WayNotFound(keep_id))?;
+        let merge = self
+            .ways
+            .get(&merge_id)
+            .ok_or(EditError::WayNotFound(merge_id))?;
+
+        if keep_id == merge_id {
+            return Err(EditError::NotConnected(keep_id, merge_id));
+        }
+        let (k_first, k_last) = match (keep.first_node(), keep.last_node()) {
+            (Some(f), Some(l)) => (f.id, l.id),
+            _ => return Err(EditError::NotConnected(keep_id, merge_id)),
+        };
+        let (m_first, m_last) = match (merge.first_node(), merge.last_node()) {
+            (Some(f), Some(l)) => (f.id, l.id),
+            _ => return Err(EditError::NotConnected(keep_id, merge_id)),
+        };
+
+        // whether `merge` is appended after `keep` and whether it must be
+        // reversed to follow the direction of `keep`
+        let (append, reverse) = if k_last == m_first {
+            (true, false)
+        } else if k_last == m_last {
+            (true, true)
+        } else if k_first == m_last {
+            (false, false)
+        } else if k_first == m_first {
+            (false, true)
+        } else {
+            return Err(EditError::NotConnected(keep_id, merge_id));
+        };
+        let shared = if append { k_last } else { k_first };
+
+        let merge_tags = match (&merge.tags, reverse) {
+            (Some(tags), true) => Some(reverse::reverse_tags(tags)),
+            (tags, _) => tags.clone(),
+        };
+        let tags = merge_tag_maps(&keep.tags, &merge_tags)?;
+
+        for relation in &self.relations {
+            if is_restriction(relation) {
+                let role_of = |id| {
+                    relation
+                        .members
+                        .iter()
+                        .find(|m| m.is_way(id))
+                        .map(|m| m.role)
+                };
+                let conflict = match (role_of(keep_id), role_of(merge_id)) {
+                    (Some(a), Some(b)) => a != b,
+                    // the via node would end up inside the merged way
+                    (Some(_), None) | (None, Some(_)) => {
+                        via_nodes(relation).contains(&shared)
+                    }
+                    (None, None) => false,
+                };
+                if conflict {
+                    return Err(EditError::RestrictionConflict(relation.id));
+                }
+            }
+        }
+
+        let mut merge_nodes = merge.nodes.clone();
+        if reverse {
+            merge_nodes.reverse();
+        }
+        let keep = self.ways.get_mut(&keep_id).unwrap();
+        if append {
+            keep.nodes.extend(merge_nodes.into_iter().skip(1));
+        } else {
+            merge_nodes.pop();
+            merge_nodes.append(&mut keep.nodes);
+            keep.nodes = merge_nodes;
+        }
+        keep.tags = tags;
+        let nodes = keep.nodes.clone();
+        self.ways.remove(&merge_id);
+
+        let mut changes = Changes {
+            create: Vec::new(),
+            modify: vec![(item_type::WAY, keep_id)],
+            delete: vec![(item_type::WAY, merge_id)],
+        };
+
+        if reverse {
+            for c in reverse::reverse_roles(merge_id, &mut self.relations) {
+                changes.modify_relation(c.relation);
+            }
+        }
+        for relation in self.relations.iter_mut() {
+            if merge_membership(relation, keep_id, merge_id, &nodes) {
+                changes.modify_relation(relation.id);
+            }
+        }
+        Ok(changes)
+    }
+}
+
+/// Union of two tag maps or the first key with conflicting values
+fn merge_tag_maps(
+    a: &Option<TagMap>,
+    b: &Option<TagMap>,
+) -> Result<Option<TagMap>, EditError> {
+    match (a, b) {
+        (None, None) => Ok(None),
+        (Some(tags), None) | (None, Some(tags)) => Ok(Some(tags.clone())),
+        (Some(a), Some(b)) => {
+            let mut merged = a.clone();
+            for (key, value) in b {
+                match merged.get(key) {
+                    Some(existing) if existing != value => {
+                        return Err(EditError::TagConflict(key.to_string()))
+                    }
+                    Some(_) => (),
+                    None => {
+                        merged.insert(key.to_string(), value.to_string());
+                    }
+                }
+            }
+            Ok(Some(merged))
+        }
+    }
+}
+
+/// Update memberships of a way that was split into `first` (keeping the
+/// original ID) and `second`. Returns whether the relation changed.
+fn split_membership<'a>(
+    relation: &mut Relation<'a>,
+    way_id: ElementID,
+    new_id: ElementID,
+    first: &[&'a Node],
+    second: &[&'a Node],
+) -> bool {
+    let positions: Vec<usize> = relation
+        .members
+        .iter()
+        .enumerate()
+        .filter(|(_, m)| m.is_way(way_id))
+        .map(|(i, _)| i)
+        .collect();
+
+    if positions.is_empty() {
+        return false;
+    }
+    let restriction = is_restriction(relation);
+    let via = via_nodes(relation);
+
+    // insert from the end so earlier positions stay valid
+    for &i in positions.iter().rev() {
+        let role = relation.members[i].role;
+
+        if restriction && (role == role::FROM || role == role::TO) {
+            // only the part touching the via member stays in the restriction
+            if touches(second, &via) && !touches(first, &via) {
+                relation.members[i].id = new_id;
+                relation.members[i].nodes = second.to_vec();
+            } else {
+                relation.members[i].nodes = first.to_vec();
+            }
+            continue;
+        }
+
+        relation.members[i].nodes = first.to_vec();
+        let member = Member {
+            id: new_id,
+            member_type: item_type::WAY,
+            nodes: second.to_vec(),
+            role,
+        };
+        // the new part goes first if the preceding member leads into it or
+        // the following member continues from the original part
+        let connects = |j: usize, part: &[&Node]| {
+            relation
+                .members
+                .get(j)
+                .is_some_and(|m| shares_end(&m.nodes, part))
+        };
+        let before =
+            (i > 0 && connects(i - 1, second) && !connects(i - 1, first))
+                || (connects(i + 1, first) && !connects(i + 1, second));
+
+        if before {
+            relation.members.insert(i, member);
+        } else {
+            relation.members.insert(i + 1, member);
+        }
+    }
+    true
+}
+
+/// Replace memberships of the merged way with the kept way, dropping them
+/// where the kept way is already a member. Returns whether the relation
+/// changed.
+fn merge_membership<'a>(
+    relation: &mut Relation<'a>,
+    keep_id: ElementID,
+    merge_id: ElementID,
+    nodes: &[&'a Node],
+) -> bool {
+    let has_keep = relation.members.iter().any(|m| m.is_way(keep_id));
+    let has_merge = relation.members.iter().any(|m| m.is_way(merge_id));
+
+    if !has_keep && !has_merge {
+        return false;
+    }
+    if has_keep {
+        relation.members.retain(|m| !m.is_way(merge_id));
+    }
+    for member in relation.members.iter_mut() {
+        if member.is_way(merge_id) {
+            member.id = keep_id;
+        }
+        if member.is_way(keep_id) {
+            member.nodes = nodes.to_vec();
+        }
+    }
+    true
+}
+
+fn is_restriction(relation: &Relation) -> bool {
+    relation.get_tag(tag::TYPE) == Some(tag::restriction::KEY)
+}
+
+/// IDs of the nodes a turn restriction passes through: the `via` node or the
+/// end nodes of `via` ways
+fn via_nodes(relation: &Relation) -> Vec<ElementID> {
+    relation
+        .members
+        .iter()
+        .filter(|m| m.role == role::VIA)
+        .flat_map(|m| {
+            let ends = [m.nodes.first(), m.nodes.last()];
+            ends.iter().flatten().map(|n| n.id).collect::<Vec<_>>()
+        })
+        .collect()
+}
+
+fn touches(nodes: &[&Node], ids: &[ElementID]) -> bool {
+    [nodes.first(), nodes.last()]
+        .iter()
+        .flatten()
+        .any(|n| ids.contains(&n.id))
+}
+
+fn shares_end(a: &[&Node], b: &[&Node]) -> bool {
+    let ids: Vec<ElementID> = [a.first(), a.last()]
+        .iter()
+        .flatten()
+        .map(|n| n.id)
+        .collect();
+    touches(b, &ids)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn nodes() -> Vec<Node> {
+        (1..=5)
+            .map(|id| Node {
+                id,
+                lat: 43.6,
+                lon: -116.2 + id as f32 * 0.001,
+                ..Default::default()
+            })
+            .collect()
+    }
+
+    fn way<'a>(id: ElementID, nodes: Vec<&'a Node>) -> Way<'a> {
+        let mut tags = TagMap::new();
+        tags.insert("highway", "residential");
+        Way {
+            id,
+            nodes,
+            tags: Some(tags),
+            ..Default::default()
+        }
+    }
+
+    fn member<'a>(way: &Way<'a>, role: &'static str) -> Member<'a> {
+        Member {
+            id: way.id,
+            member_type: item_type::WAY,
+            nodes: way.nodes.clone(),
+            role,
+        }
+    }
+
+    #[test]
+    fn split_keeps_route_order_and_restriction() {
+        let n = nodes();
+        let a = way(10, vec![&n[0], &n[1], &n[2]]);
+        let b = way(11, vec![&n[2], &n[3], &n[4]]);
+
+        let mut route_tags = TagMap::new();
+        route_tags.insert("type", "route");
+        let mut restriction_tags = TagMap::new();
+        restriction_tags.insert("type", "restriction");
+        restriction_tags.insert("restriction", "no_left_turn");
+
+        let mut data = AreaData::default();
+        data.relations.push(Relation {
+            id: 1,
+            members: vec![member(&a, ""), member(&b, "")],
+            tags: route_tags,
+            ..Default::default()
+        });
+        data.relations.push(Relation {
+            id: 2,
+            members: vec![
+                member(&a, role::FROM),
+                Member {
+                    id: 3,
+                    member_type: item_type::NODE,
+                    nodes: vec![&n[2]],
+                    role: role::VIA,
+                },
+                member(&b, role::TO),
+            ],
+            tags: restriction_tags,
+            ..Default::default()
+        });
+        data.ways.insert(10, a);
+        data.ways.insert(11, b);
+
+        let changes = data.split_way(10, 2).unwrap();
+
+        assert_eq!(changes.create, vec![(item_type::WAY, -1)]);
+        assert_eq!(changes.modify.len(), 3);
+        assert_eq!(data.ways[&10].nodes.len(), 2);
+        assert_eq!(data.ways[&-1].nodes.len(), 2);
+        assert_eq!(data.ways[&-1].get_tag("highway"), Some("residential"));
+
+        let route: Vec<ElementID> =
+            data.relations[0].members.iter().map(|m| m.id).collect();
+        assert_eq!(route, vec![10, -1, 11]);
+        assert_eq!(data.relations[1].members[0].id, -1);
+    }
+
+    #[test]
+    fn merge_reverses_opposing_way() {
+        let n = nodes();
+        let a = way(10, vec![&n[0], &n[1], &n[2]]);
+        let mut b = way(11, vec![&n[4], &n[3], &n[2]]);
+        b.tags.as_mut().unwrap().insert("oneway", "-1");
+
+        let mut data = AreaData::default();
+        data.relations.push(Relation {
+            id: 1,
+            members: vec![member(&b, role::BACKWARD)],
+            ..Default::default()
+        });
+        data.ways.insert(10, a);
+        data.ways.insert(11, b);
+
+        let changes = data.merge_ways(10, 11).unwrap();
+        let merged = &data.ways[&10];
+
+        assert_eq!(changes.delete, vec![(item_type::WAY, 11)]);
+        assert_eq!(merged.nodes.len(), 5);
+        assert_eq!(merged.last_node().unwrap().id, 5);
+        assert_eq!(merged.get_tag("oneway"), Some("yes"));
+        assert!(!data.ways.contains_key(&11));
+        assert_eq!(data.relations[0].members[0].id, 10);
+        assert_eq!(data.relations[0].members[0].role, role::FORWARD);
+    }
+
+    #[test]
+    fn merge_keeps_restriction_via_node_at_way_end() {
+        let n = nodes();
+        let a = way(10, vec![&n[0], &n[1], &n[2]]);
+        let b = way(11, vec![&n[2], &n[3], &n[4]]);
+        let c = way(12, vec![&n[2], &n[1]]);
+
+        let mut tags = TagMap::new();
+        tags.insert("type", "restriction");
+        tags.insert("restriction", "no_u_turn");
+        let mut data = AreaData::default();
+        data.relations.push(Relation {
+            id: 2,
+            members: vec![
+                member(&a, role::FROM),
+                Member {
+                    id: 3,
+                    member_type: item_type::NODE,
+                    nodes: vec![&n[2]],
+                    role: role::VIA,
+                },
+                member(&c, role::TO),
+            ],
+            tags,
+            ..Default::default()
+        });
+        data.ways.insert(10, a);
+        data.ways.insert(11, b);
+
+        assert!(matches!(
+            data.merge_ways(10, 11),
+            Err(EditError::RestrictionConflict(2))
+        ));
+        assert!(data.merge_ways(11, 10).is_err());
+        assert_eq!(data.ways[&10].nodes.len(), 3);
+        assert!(data.ways.contains_key(&11));
+    }
+}
@@ -0,0 +1,415 @@
+//! Ground elevation along ways, from the `ele` of their nodes or from a
+//! digital elevation model such as SRTM `.hgt` tiles.
+//!
+//! https://wiki.openstreetmap.org/wiki/Key:ele
+//! https://wiki.openstreetmap.org/wiki/SRTM
+use crate::{
+    geo::{haversine, Point},
+    node::Node,
+    way::Way,
+};
+use hashbrown::HashMap;
+use std::{
+    fs,
+    io::{self, Read},
+    path::Path,
+};
+
+/// Source of ground elevation in meters at a point
+pub trait Terrain {
+    fn elevation(&self, point: Point) -> Option<f32>;
+}
+
+/// Elevation from a function of the point
+impl<F: Fn(Point) -> Option<f32>> Terrain for F {
+    fn elevation(&self, point: Point) -> Option<f32> {
+        self(point)
+    }
+}
+
+/// Value of `.hgt` samples with no data
+static VOID: i16 = -32768;
+
+/// One degree square of elevation samples in the SRTM `.hgt` format: big
+/// endian 16-bit meters in rows from north to south, 1201 to a side at three
+/// arc seconds apart or 3601 at one arc second
+#[derive(Clone, Debug, PartialEq)]
+pub struct Hgt {
+    /// Latitude and longitude of the south-west corner
+    pub corner: (i32, i32),
+    size: usize,
+    samples: Vec<i16>,
+}
+
+impl Hgt {
+    /// Read the tile with the given south-west corner
+    pub fn read<R: Read>(
+        mut reader: R,
+        corner: (i32, i32),
+    ) -> io::Result<Self> {
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes)?;
+        let size = match bytes.len() {
+            2_884_802 => 1201,
+            25_934_402 => 3601,
+            len => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("{} bytes isn't an SRTM tile size", len),
+                ))
+            }
+        };
+        let samples = bytes
+            .chunks_exact(2)
+            .map(|pair| i16::from_be_bytes([pair[0], pair[1]]))
+            .collect();
+        Ok(Hgt {
+            corner,
+            size,
+            samples,
+        })
+    }
+
+    /// Read a tile named for its south-west corner, such as `N43W117.hgt`
+    pub fn open<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let path = path.as_ref();
+        let corner = path
+            .file_stem()
+            .and_then(|stem| stem.to_str())
+            .and_then(corner)
+            .ok_or_else(|| {
+                io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!("{} isn't named for an SRTM tile", path.display()),
+                )
+            })?;
+        Hgt::read(fs::File::open(path)?, corner)
+    }
+
+    /// Tile from samples in rows from north to south, which must form a
+    /// square of at least two by two
+    pub fn from_samples(
+        corner: (i32, i32),
+        samples: Vec<i16>,
+    ) -> io::Result<Self> {
+        let size = (samples.len() as f64).sqrt() as usize;
+        if size < 2 || size * size != samples.len() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("{} samples don't form a square tile", samples.len()),
+            ));
+        }
+        Ok(Hgt {
+            corner,
+            size,
+            samples,
+        })
+    }
+
+    fn sample(&self, row: usize, column: usize) -> Option<f32> {
+        let value = self.samples[row * self.size + column];
+        if value == VOID {
+            None
+        } else {
+            Some(f32::from(value))
+        }
+    }
+}
+
+/// Elevation interpolated between the four nearest samples, or `None`
+/// outside the tile or next to a void
+impl Terrain for Hgt {
+    fn elevation(&self, (lat, lon): Point) -> Option<f32> {
+        let steps = (self.size - 1) as f64;
+        let y = (f64::from(self.corner.0) + 1.0 - f64::from(lat)) * steps;
+        let x = (f64::from(lon) - f64::from(self.corner.1)) * steps;
+        if !(0.0..=steps).contains(&x) || !(0.0..=steps).contains(&y) {
+            return None;
+        }
+        let (row, column) = (
+            (y as usize).min(self.size - 2),
+            (x as usize).min(self.size - 2),
+        );
+        let (dy, dx) = ((y - row as f64) as f32, (x - column as f64) as f32);
+        let north = self.sample(row, column)? * (1.0 - dx)
+            + self.sample(row, column + 1)? * dx;
+        let south = self.sample(row + 1, column)? * (1.0 - dx)
+            + self.sample(row + 1, column + 1)? * dx;
+        Some(north * (1.0 - dy) + south * dy)
+    }
+}
+
+/// South-west corner from a tile name such as `N43W117`
+fn corner(name: &str) -> Option<(i32, i32)> {
+    let name = name.to_ascii_uppercase();
+    let lat_sign = match name.get(..1)? {
+        "N" => 1,
+        "S" => -1,
+        _ => return None,
+    };
+    let lon_sign = match name.get(3..4)? {
+        "E" => 1,
+        "W" => -1,
+        _ => return None,
+    };
+    let lat: i32 = name.get(1..3)?.parse().ok()?;
+    let lon: i32 = name.get(4..7)?.parse().ok()?;
+    Some((lat * lat_sign, lon * lon_sign))
+}
+
+/// Set of `.hgt` tiles
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Dem {
+    tiles: HashMap<(i32, i32), Hgt>,
+}
+
+impl Dem {
+    /// Read every `.hgt` tile in the directory
+    pub fn open<P: AsRef<Path>>(directory: P) -> io::Result<Self> {
+        let mut dem = Dem::default();
+        for entry in fs::read_dir(directory)? {
+            let path = entry?.path();
+            let is_hgt = path
+                .extension()
+                .is_some_and(|ext| ext.eq_ignore_ascii_case("hgt"));
+            if is_hgt {
+                dem.insert(Hgt::open(path)?);
+            }
+        }
+        Ok(dem)
+    }
+
+    pub fn insert(&mut self, tile: Hgt) {
+        self.tiles.insert(tile.corner, tile);
+    }
+}
+
+impl Terrain for Dem {
+    fn elevation(&self, point: Point) -> Option<f32> {
+        let corner = (point.0.floor() as i32, point.1.floor() as i32);
+        self.tiles.get(&corner)?.elevation(point)
+    }
+}
+
+/// Elevation at a distance along a line
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ProfilePoint {
+    /// Meters from the start
+    pub distance: f64,
+    /// Meters above sea level
+    pub ele: f32,
+}
+
+/// Elevation at each vertex of a line
+#[derive(Clone, Debug, PartialEq)]
+pub struct ElevationProfile {
+    pub points: Vec<ProfilePoint>,
+}
+
+impl ElevationProfile {
+    /// Profile along the nodes from their `ele`, or from the terrain for
+    /// nodes without one. Elevations still missing are interpolated from
+    /// the nodes either side, and the profile is `None` if there are none
+    /// to interpolate from.
+    pub fn of_nodes(
+        nodes: &[&Node],
+        terrain: Option<&dyn Terrain>,
+    ) -> Option<Self> {
+        let points: Vec<(Point, Option<f32>)> =
+            nodes.iter().map(|node| (node.point(), node.ele)).collect();
+        ElevationProfile::of_points(&points, terrain, 1)
+    }
+
+    /// Profile along the nodes like `of_nodes`, but `None` unless at least
+    /// two of them have a known elevation, so that the grades are measured
+    /// rather than all made up
+    pub(crate) fn measured(
+        nodes: &[&Node],
+        terrain: Option<&dyn Terrain>,
+    ) -> Option<Self> {
+        let points: Vec<(Point, Option<f32>)> =
+            nodes.iter().map(|node| (node.point(), node.ele)).collect();
+        ElevationProfile::of_points(&points, terrain, 2)
+    }
+
+    pub fn of_way(way: &Way, terrain: Option<&dyn Terrain>) -> Option<Self> {
+        ElevationProfile::of_nodes(&way.nodes, terrain)
+    }
+
+    /// Profile along a line such as a route's geometry
+    pub fn along(points: &[Point], terrain: &dyn Terrain) -> Option<Self> {
+        let points: Vec<(Point, Option<f32>)> =
+            points.iter().map(|point| (*point, None)).collect();
+        ElevationProfile::of_points(&points, Some(terrain), 1)
+    }
+
+    /// Profile with elevations interpolated where missing, or `None` with
+    /// fewer than `required` known elevations
+    fn of_points(
+        points: &[(Point, Option<f32>)],
+        terrain: Option<&dyn Terrain>,
+        required: usize,
+    ) -> Option<Self> {
+        let mut distance = 0.0;
+        let known: Vec<(f64, Option<f32>)> = points
+            .iter()
+            .enumerate()
+            .map(|(i, (point, ele))| {
+                if i > 0 {
+                    distance += haversine(points[i - 1].0, *point);
+                }
+                let ele = ele.or_else(|| terrain?.elevation(*point));
+                (distance, ele)
+            })
+            .collect();
+        let given: Vec<(f64, f32)> = known
+            .iter()
+            .filter_map(|(distance, ele)| ele.map(|ele| (*distance, ele)))
+            .collect();
+        if given.len() < required.max(1) {
+            return None;
+        }
+
+        let points = known
+            .iter()
+            .map(|(distance, ele)| ProfilePoint {
+                distance: *distance,
+                ele: ele.unwrap_or_else(|| interpolate(&given, *distance)),
+            })
+            .collect();
+        Some(ElevationProfile { points })
+    }
+
+    /// Meters climbed
+    pub fn ascent(&self) -> f64 {
+        self.climbs().filter(|d| *d > 0.0).sum()
+    }
+
+    /// Meters descended
+    pub fn descent(&self) -> f64 {
+        -self.climbs().filter(|d| *d < 0.0).sum::<f64>()
+    }
+
+    /// Grade in percent between each vertex and the next, zero where they
+    /// are at the same place
+    pub fn grades(&self) -> Vec<f32> {
+        self.points
+            .windows(2)
+            .map(|pair| {
+                let run = pair[1].distance - pair[0].distance;
+                let rise = f64::from(pair[1].ele - pair[0].ele);
+                if run > 0.0 {
+                    (rise / run * 100.0) as f32
+                } else {
+                    0.0
+                }
+            })
+            .collect()
+    }
+
+    fn climbs(&self) -> impl Iterator<Item = f64> + '_ {
+        self.points
+            .windows(2)
+            .map(|pair| f64::from(pair[1].ele - pair[0].ele))
+    }
+}
+
+/// Elevation at a distance between known elevations, or the nearest one
+/// beyond either end
+fn interpolate(given: &[(f64, f32)], distance: f64) -> f32 {
+    let after = given.iter().position(|(d, _)| *d >= distance);
+    match after {
+        Some(0) => given[0].1,
+        None => given[given.len() - 1].1,
+        Some(i) => {
+            let ((d0, e0), (d1, e1)) = (given[i - 1], given[i]);
+            let t = ((distance - d0) / (d1 - d0)) as f32;
+            e0 + (e1 - e0) * t
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn node(id: i64, lon: f32, ele: Option<f32>) -> Node {
+        Node {
+            id,
+            lat: 0.0,
+            lon,
+            ele,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn profiles_nodes() {
+        // 111 m apart, rising 10 m, gaining 5 m more over an unknown node,
+        // then falling 20 m
+        let nodes = [
+            node(1, 0.0, Some(100.0)),
+            node(2, 0.001, Some(110.0)),
+            node(3, 0.002, None),
+            node(4, 0.003, Some(120.0)),
+            node(5, 0.004, Some(100.0)),
+        ];
+        let nodes: Vec<&Node> = nodes.iter().collect();
+        let profile = ElevationProfile::of_nodes(&nodes, None).unwrap();
+        assert_eq!(profile.points[2].ele, 115.0);
+        assert!((profile.points[4].distance - 444.8).abs() < 0.1);
+        assert_eq!(profile.ascent(), 20.0);
+        assert_eq!(profile.descent(), 20.0);
+        let grades = profile.grades();
+        assert_eq!(grades.len(), 4);
+        assert!((grades[0] - 8.99).abs() < 0.01);
+        assert!((grades[3] + 17.99).abs() < 0.01);
+
+        // the terrain fills in before interpolating
+        let terrain = |_: Point| Some(130.0);
+        let profile =
+            ElevationProfile::of_nodes(&nodes, Some(&terrain)).unwrap();
+        assert_eq!(profile.points[2].ele, 130.0);
+
+        let flat = [node(1, 0.0, None), node(2, 0.001, None)];
+        let flat: Vec<&Node> = flat.iter().collect();
+        assert_eq!(ElevationProfile::of_nodes(&flat, None), None);
+    }
+
+    #[test]
+    fn reads_hgt_tiles() {
+        assert_eq!(corner("N43W117"), Some((43, -117)));
+        assert_eq!(corner("s01e002"), Some((-1, 2)));
+        assert_eq!(corner("X43W117"), None);
+
+        // rising by a meter for each sample to the east and to the north
+        let size = 1201;
+        let mut bytes = Vec::with_capacity(size * size * 2);
+        for row in 0..size {
+            for column in 0..size {
+                let ele = (column + size - 1 - row) as i16;
+                bytes.extend_from_slice(&ele.to_be_bytes());
+            }
+        }
+        bytes[0..2].copy_from_slice(&VOID.to_be_bytes());
+        let tile = Hgt::read(&bytes[..], (43, -117)).unwrap();
+        assert_eq!(tile.elevation((43.0, -117.0)), Some(0.0));
+        assert_eq!(tile.elevation((43.5, -116.5)), Some(1200.0));
+        let between = tile.elevation((43.0, -117.0 + 0.5 / 1200.0)).unwrap();
+        assert!((between - 0.5).abs() < 0.02);
+        // next to the void in the north-west corner
+        assert_eq!(tile.elevation((44.0, -117.0)), None);
+        assert_eq!(tile.elevation((42.9, -117.0)), None);
+
+        let mut dem = Dem::default();
+        dem.insert(tile);
+        assert_eq!(dem.elevation((43.5, -116.5)), Some(1200.0));
+        assert_eq!(dem.elevation((44.5, -116.5)), None);
+
+        assert!(Hgt::read(&bytes[..100], (43, -117)).is_err());
+        let square = Hgt::from_samples((0, 0), vec![10, 20, 30, 40]).unwrap();
+        assert_eq!(square.elevation((1.0, 0.0)), Some(10.0));
+        assert!(Hgt::from_samples((0, 0), vec![1, 2, 3]).is_err());
+        assert!(Hgt::from_samples((0, 0), vec![1]).is_err());
+    }
+}
@@ -0,0 +1,137 @@
+//! Planar and spherical geometry shared by the element models. Points are
+//! `(lat, lon)` tuples in degrees, matching `Node::point()`.
+
+/// Mean Earth radius in meters used for great-circle distances
+pub static EARTH_RADIUS: f64 = 6_371_008.8;
+
+pub type Point = (f32, f32);
+
+/// Great-circle distance in meters between two points using the haversine
+/// formula.
+pub fn haversine(a: Point, b: Point) -> f64 {
+    let (lat1, lon1) =
+        (f64::from(a.0).to_radians(), f64::from(a.1).to_radians());
+    let (lat2, lon2) =
+        (f64::from(b.0).to_radians(), f64::from(b.1).to_radians());
+    let d_lat = lat2 - lat1;
+    let d_lon = lon2 - lon1;
+    let h = (d_lat / 2.0).sin().powi(2)
+        + lat1.cos() * lat2.cos() * (d_lon / 2.0).sin().powi(2);
+
+    2.0 * EARTH_RADIUS * h.sqrt().asin()
+}
+
+/// Smallest latitude/longitude rectangle enclosing a set of points.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct BBox {
+    pub min_lat: f32,
+    pub min_lon: f32,
+    pub max_lat: f32,
+    pub max_lon: f32,
+}
+
+impl BBox {
+    /// Bounding box of the points or `None` if there are no points
+    pub fn from_points<I: IntoIterator<Item = Point>>(
+        points: I,
+    ) -> Option<BBox> {
+        let mut iter = points.into_iter();
+        let (lat, lon) = iter.next()?;
+        let mut bbox = BBox {
+            min_lat: lat,
+            min_lon: lon,
+            max_lat: lat,
+            max_lon: lon,
+        };
+        for p in iter {
+            bbox.extend(p);
+        }
+        Some(bbox)
+    }
+
+    /// Grow the box to include the point
+    pub fn extend(&mut self, (lat, lon): Point) {
+        self.min_lat = self.min_lat.min(lat);
+        self.min_lon = self.min_lon.min(lon);
+        self.max_lat = self.max_lat.max(lat);
+        self.max_lon = self.max_lon.max(lon);
+    }
+
+    pub fn contains(&self, (lat, lon): Point) -> bool {
+        lat >= self.min_lat
+            && lat <= self.max_lat
+            && lon >= self.min_lon
+            && lon <= self.max_lon
+    }
+
+    pub fn intersects(&self, other: &BBox) -> bool {
+        self.min_lat <= other.max_lat
+            && other.min_lat <= self.max_lat
+            && self.min_lon <= other.max_lon
+            && other.min_lon <= self.max_lon
+    }
+}
+
+/// Twice the signed area of a closed ring in degree units, treating longitude
+/// as `x` and latitude as `y`. Positive when the ring is counter-clockwise.
+pub fn signed_area(ring: &[Point]) -> f64 {
+    ring.windows(2)
+        .map(|w| {
+            let (y1, x1) = (f64::from(w[0].0), f64::from(w[0].1));
+            let (y2, x2) = (f64::from(w[1].0), f64::from(w[1].1));
+            x1 * y2 - x2 * y1
+        })
+        .sum()
+}
+
+/// Whether the point lies inside the closed ring (even-odd rule). Points on
+/// the boundary may be reported either way.
+pub fn ring_contains(ring: &[Point], (lat, lon): Point) -> bool {
+    let mut inside = false;
+    for w in ring.windows(2) {
+        let ((lat1, lon1), (lat2, lon2)) = (w[0], w[1]);
+        if (lat1 > lat) != (lat2 > lat) {
+            let cross = (lon2 - lon1) * (lat - lat1) / (lat2 - lat1) + lon1;
+            if lon < cross {
+                inside = !inside;
+            }
+        }
+    }
+    inside
+}
+
+/// Orientation of `c` relative to the line `a → b`: positive for a left turn,
+/// negative for a right turn and zero when collinear.
+fn orientation(a: Point, b: Point, c: Point) -> f64 {
+    let (ay, ax) = (f64::from(a.0), f64::from(a.1));
+    let (by, bx) = (f64::from(b.0), f64::from(b.1));
+    let (cy, cx) = (f64::from(c.0), f64::from(c.1));
+    (bx - ax) * (cy - ay) - (by - ay) * (cx - ax)
+}
+
+/// Whether collinear point `c` lies within the extent of segment `a → b`
+fn on_segment(a: Point, b: Point, c: Point) -> bool {
+    c.0 >= a.0.min(b.0)
+        && c.0 <= a.0.max(b.0)
+        && c.1 >= a.1.min(b.1)
+        && c.1 <= a.1.max(b.1)
+}
+
+/// Whether segments `a1 → a2` and `b1 → b2` share at least one point
+pub fn segments_intersect(a1: Point, a2: Point, b1: Point, b2: Point) -> bool {
+    let d1 = orientation(b1, b2, a1);
+    let d2 = orientation(b1, b2, a2);
+    let d3 = orientation(a1, a2, b1);
+    let d4 = orientation(a1, a2, b2);
+
+    if ((d1 > 0.0 && d2 < 0.0) || (d1 < 0.0 && d2 > 0.0))
+        && ((d3 > 0.0 && d4 < 0.0) || (d3 < 0.0 && d4 > 0.0))
+    {
+        return true;
+    }
+
+    (d1 == 0.0 && on_segment(b1, b2, a1))
+        || (d2 == 0.0 && on_segment(b1, b2, a2))
+        || (d3 == 0.0 && on_segment(a1, a2, b1))
+        || (d4 == 0.0 && on_segment(a1, a2, b2))
+}
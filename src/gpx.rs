@@ -0,0 +1,572 @@
+//! Reading and writing [GPX 1.1](https://www.topografix.com/GPX/1/1/) files
+//! of waypoints, routes and tracks.
+//!
+//! Track points convert to nodes for editing and to samples for map
+//! matching, and routes convert to and from routes found over a routing
+//! graph. Times are in UTC. Metadata and extensions are skipped when
+//! reading.
+use crate::{
+    geo::Point,
+    node::Node,
+    routing::{
+        matching::Sample,
+        path::{self, Cost, Router},
+    },
+    ElementID,
+};
+use chrono::{DateTime, NaiveDateTime};
+use serde::Deserialize;
+use std::{
+    error, fmt,
+    io::{self, Read, Write},
+};
+
+pub static NAMESPACE: &'static str = "http://www.topografix.com/GPX/1/1";
+
+#[derive(Debug)]
+pub enum ReadError {
+    Xml(serde_xml_rs::Error),
+    InvalidTime(String),
+}
+
+impl fmt::Display for ReadError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ReadError::Xml(e) => write!(f, "invalid GPX: {}", e),
+            ReadError::InvalidTime(time) => {
+                write!(f, "invalid time {} in GPX", time)
+            }
+        }
+    }
+}
+
+impl error::Error for ReadError {}
+
+impl From<serde_xml_rs::Error> for ReadError {
+    fn from(e: serde_xml_rs::Error) -> Self {
+        ReadError::Xml(e)
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Gpx {
+    /// Name of the software that made the file
+    pub creator: Option<String>,
+    pub waypoints: Vec<Waypoint>,
+    pub routes: Vec<Route>,
+    pub tracks: Vec<Track>,
+}
+
+/// Waypoint, route point or track point
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Waypoint {
+    pub lat: f32,
+    pub lon: f32,
+    /// Meters above mean sea level
+    pub ele: Option<f32>,
+    pub time: Option<NaiveDateTime>,
+    pub name: Option<String>,
+}
+
+/// Points to travel through in order
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Route {
+    pub name: Option<String>,
+    pub points: Vec<Waypoint>,
+}
+
+/// Points recorded while travelling, split into segments where recording
+/// stopped
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Track {
+    pub name: Option<String>,
+    pub segments: Vec<Vec<Waypoint>>,
+}
+
+impl Gpx {
+    pub fn read<R: Read>(reader: R) -> Result<Self, ReadError> {
+        let file: GpxFile = serde_xml_rs::from_reader(reader)?;
+        Ok(Gpx {
+            creator: file.creator,
+            waypoints: points(file.waypoints)?,
+            routes: file
+                .routes
+                .into_iter()
+                .map(|route| {
+                    Ok(Route {
+                        name: route.name,
+                        points: points(route.points)?,
+                    })
+                })
+                .collect::<Result<_, ReadError>>()?,
+            tracks: file
+                .tracks
+                .into_iter()
+                .map(|track| {
+                    Ok(Track {
+                        name: track.name,
+                        segments: track
+                            .segments
+                            .into_iter()
+                            .map(|segment| points(segment.points))
+                            .collect::<Result<_, _>>()?,
+                    })
+                })
+                .collect::<Result<_, ReadError>>()?,
+        })
+    }
+
+    pub fn write<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        writeln!(writer, r#"<?xml version="1.0" encoding="UTF-8"?>"#)?;
+        writeln!(
+            writer,
+            r#"<gpx version="1.1" creator="{}" xmlns="{}">"#,
+            escape(self.creator.as_deref().unwrap_or("osm_models")),
+            NAMESPACE
+        )?;
+        for waypoint in &self.waypoints {
+            write_point(writer, "wpt", waypoint, 1)?;
+        }
+        for route in &self.routes {
+            writeln!(writer, "  <rte>")?;
+            write_name(writer, &route.name, 2)?;
+            for point in &route.points {
+                write_point(writer, "rtept", point, 2)?;
+            }
+            writeln!(writer, "  </rte>")?;
+        }
+        for track in &self.tracks {
+            writeln!(writer, "  <trk>")?;
+            write_name(writer, &track.name, 2)?;
+            for segment in &track.segments {
+                writeln!(writer, "    <trkseg>")?;
+                for point in segment {
+                    write_point(writer, "trkpt", point, 3)?;
+                }
+                writeln!(writer, "    </trkseg>")?;
+            }
+            writeln!(writer, "  </trk>")?;
+        }
+        writeln!(writer, "</gpx>")
+    }
+}
+
+impl Waypoint {
+    pub fn point(&self) -> Point {
+        (self.lat, self.lon)
+    }
+
+    /// Node with the waypoint's position, elevation and time
+    pub fn to_node(&self, id: ElementID) -> Node {
+        Node {
+            id,
+            lat: self.lat,
+            lon: self.lon,
+            ele: self.ele,
+            timestamp: self.time.map(format_time),
+            ..Default::default()
+        }
+    }
+
+    /// Sample for map matching
+    pub fn to_sample(&self) -> Sample {
+        Sample {
+            point: self.point(),
+            time: self.time,
+        }
+    }
+}
+
+/// Waypoint at the node, without its time if the timestamp can't be read
+impl From<&Node> for Waypoint {
+    fn from(node: &Node) -> Self {
+        Waypoint {
+            lat: node.lat,
+            lon: node.lon,
+            ele: node.ele,
+            time: node
+                .timestamp
+                .as_deref()
+                .and_then(|time| parse_time(time).ok()),
+            name: None,
+        }
+    }
+}
+
+impl From<Point> for Waypoint {
+    fn from((lat, lon): Point) -> Self {
+        Waypoint {
+            lat,
+            lon,
+            ..Default::default()
+        }
+    }
+}
+
+impl Track {
+    pub fn points(&self) -> impl Iterator<Item = &Waypoint> {
+        self.segments.iter().flatten()
+    }
+
+    /// Nodes for the track points in order, with negative IDs counting down
+    /// from -1 as for elements not yet uploaded
+    pub fn to_nodes(&self) -> Vec<Node> {
+        self.points()
+            .zip(1..)
+            .map(|(point, i)| point.to_node(-i))
+            .collect()
+    }
+
+    /// Samples of the track points for map matching
+    pub fn to_samples(&self) -> Vec<Sample> {
+        self.points().map(Waypoint::to_sample).collect()
+    }
+
+    /// Track of a single segment through the nodes
+    pub fn from_nodes<'a, I: IntoIterator<Item = &'a Node>>(nodes: I) -> Self {
+        Track {
+            name: None,
+            segments: vec![nodes.into_iter().map(Waypoint::from).collect()],
+        }
+    }
+}
+
+impl Route {
+    /// Best route through the route points in order, or `None` if there
+    /// are fewer than two or a leg between them has no route
+    pub fn follow<C: Cost>(&self, router: &Router<C>) -> Option<path::Route> {
+        let mut legs = self
+            .points
+            .windows(2)
+            .map(|pair| router.route(pair[0].point(), pair[1].point()));
+        let mut route = legs.next()??;
+        for leg in legs {
+            let leg = leg?;
+            route.cost += leg.cost;
+            route.distance += leg.distance;
+            let mut edges = leg.edges.into_iter().peekable();
+            if edges.peek() == route.edges.last() {
+                edges.next();
+            }
+            route.edges.extend(edges);
+            for way in leg.ways {
+                if route.ways.last() != Some(&way) {
+                    route.ways.push(way);
+                }
+            }
+            for node in leg.nodes {
+                if route.nodes.last() != Some(&node) {
+                    route.nodes.push(node);
+                }
+            }
+            for point in leg.geometry {
+                if route.geometry.last() != Some(&point) {
+                    route.geometry.push(point);
+                }
+            }
+        }
+        Some(route)
+    }
+}
+
+/// GPX route along the geometry of a route
+impl From<&path::Route> for Route {
+    fn from(route: &path::Route) -> Self {
+        Route {
+            name: None,
+            points: route
+                .geometry
+                .iter()
+                .copied()
+                .map(Waypoint::from)
+                .collect(),
+        }
+    }
+}
+
+fn points(raw: Vec<PointFile>) -> Result<Vec<Waypoint>, ReadError> {
+    raw.into_iter()
+        .map(|point| {
+            Ok(Waypoint {
+                lat: point.lat,
+                lon: point.lon,
+                ele: point.ele,
+                time: point.time.as_deref().map(parse_time).transpose()?,
+                name: point.name,
+            })
+        })
+        .collect()
+}
+
+/// Time in UTC from an ISO 8601 date and time with an offset, such as
+/// `2020-06-01T12:00:00Z`. Times without an offset, which some devices
+/// write, are taken to be in UTC already.
+fn parse_time(time: &str) -> Result<NaiveDateTime, ReadError> {
+    let text = time.trim();
+    DateTime::parse_from_rfc3339(text)
+        .map(|time| time.naive_utc())
+        .or_else(|_| {
+            NaiveDateTime::parse_from_str(text, "%Y-%m-%dT%H:%M:%S%.f")
+        })
+        .map_err(|_| ReadError::InvalidTime(time.to_string()))
+}
+
+fn format_time(time: NaiveDateTime) -> String {
+    time.format("%Y-%m-%dT%H:%M:%S%.fZ").to_string()
+}
+
+fn write_point<W: Write>(
+    writer: &mut W,
+    element: &str,
+    point: &Waypoint,
+    depth: usize,
+) -> io::Result<()> {
+    let indent = "  ".repeat(depth);
+    write!(
+        writer,
+        r#"{}<{} lat="{}" lon="{}""#,
+        indent, element, point.lat, point.lon
+    )?;
+    if point.ele.is_none() && point.time.is_none() && point.name.is_none() {
+        return writeln!(writer, "/>");
+    }
+    writeln!(writer, ">")?;
+    if let Some(ele) = point.ele {
+        writeln!(writer, "{}  <ele>{}</ele>", indent, ele)?;
+    }
+    if let Some(time) = point.time {
+        writeln!(writer, "{}  <time>{}</time>", indent, format_time(time))?;
+    }
+    write_name(writer, &point.name, depth + 1)?;
+    writeln!(writer, "{}</{}>", indent, element)
+}
+
+fn write_name<W: Write>(
+    writer: &mut W,
+    name: &Option<String>,
+    depth: usize,
+) -> io::Result<()> {
+    match name {
+        Some(name) => writeln!(
+            writer,
+            "{}<name>{}</name>",
+            "  ".repeat(depth),
+            escape(name)
+        ),
+        None => Ok(()),
+    }
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;")
+}
+
+/// GPX as deserialized, before times are read
+#[derive(Deserialize)]
+struct GpxFile {
+    creator: Option<String>,
+    #[serde(rename = "wpt", default)]
+    waypoints: Vec<PointFile>,
+    #[serde(rename = "rte", default)]
+    routes: Vec<RouteFile>,
+    #[serde(rename = "trk", default)]
+    tracks: Vec<TrackFile>,
+}
+
+#[derive(Deserialize)]
+struct PointFile {
+    lat: f32,
+    lon: f32,
+    ele: Option<f32>,
+    time: Option<String>,
+    name: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct RouteFile {
+    name: Option<String>,
+    #[serde(rename = "rtept", default)]
+    points: Vec<PointFile>,
+}
+
+#[derive(Deserialize)]
+struct TrackFile {
+    name: Option<String>,
+    #[serde(rename = "trkseg", default)]
+    segments: Vec<SegmentFile>,
+}
+
+#[derive(Deserialize)]
+struct SegmentFile {
+    #[serde(rename = "trkpt", default)]
+    points: Vec<PointFile>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDate;
+
+    static GPX: &'static str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<gpx version="1.1" creator="Tracker &amp; Co" xmlns="http://www.topografix.com/GPX/1/1">
+  <metadata><name>Morning ride</name></metadata>
+  <wpt lat="43.6150" lon="-116.2023">
+    <ele>824.5</ele>
+    <name>Capitol</name>
+  </wpt>
+  <wpt lat="43.6" lon="-116.2"/>
+  <rte>
+    <name>Downtown</name>
+    <rtept lat="43.61" lon="-116.20"/>
+    <rtept lat="43.62" lon="-116.21"/>
+  </rte>
+  <trk>
+    <name>Ride</name>
+    <trkseg>
+      <trkpt lat="43.61" lon="-116.20">
+        <ele>820</ele>
+        <time>2020-06-01T12:00:00Z</time>
+        <extensions><speed>4.2</speed></extensions>
+      </trkpt>
+      <trkpt lat="43.611" lon="-116.201">
+        <ele>821.5</ele>
+        <time>2020-06-01T14:00:05.5+02:00</time>
+      </trkpt>
+    </trkseg>
+    <trkseg>
+      <trkpt lat="43.62" lon="-116.21"/>
+    </trkseg>
+  </trk>
+</gpx>
+"#;
+
+    fn at(second: u32, milli: u32) -> NaiveDateTime {
+        NaiveDate::from_ymd_opt(2020, 6, 1)
+            .unwrap()
+            .and_hms_milli_opt(12, 0, second, milli)
+            .unwrap()
+    }
+
+    #[test]
+    fn reads_and_writes() {
+        let gpx = Gpx::read(GPX.as_bytes()).unwrap();
+        assert_eq!(gpx.creator.as_deref(), Some("Tracker & Co"));
+        assert_eq!(gpx.waypoints.len(), 2);
+        assert_eq!(gpx.waypoints[0].name.as_deref(), Some("Capitol"));
+        assert_eq!(gpx.waypoints[0].ele, Some(824.5));
+        assert_eq!(gpx.waypoints[1].point(), (43.6, -116.2));
+        assert_eq!(gpx.routes[0].name.as_deref(), Some("Downtown"));
+        assert_eq!(gpx.routes[0].points.len(), 2);
+
+        let track = &gpx.tracks[0];
+        assert_eq!(track.segments.len(), 2);
+        assert_eq!(track.segments[0][0].time, Some(at(0, 0)));
+        assert_eq!(track.segments[0][1].time, Some(at(5, 500)));
+        assert_eq!(track.segments[1][0].ele, None);
+
+        let mut written = Vec::new();
+        gpx.write(&mut written).unwrap();
+        assert_eq!(Gpx::read(&written[..]).unwrap(), gpx);
+
+        let local = GPX
+            .replace("2020-06-01T12:00:00Z", "2020-06-01T12:00:00")
+            .replace("14:00:05.5+02:00", "12:00:05.5");
+        let local = Gpx::read(local.as_bytes()).unwrap();
+        assert_eq!(local.tracks, gpx.tracks);
+
+        let bad = GPX.replace("2020-06-01T12:00:00Z", "noon");
+        match Gpx::read(bad.as_bytes()) {
+            Err(ReadError::InvalidTime(time)) => assert_eq!(time, "noon"),
+            other => panic!("unexpected {:?}", other),
+        }
+    }
+
+    #[test]
+    fn converts_tracks() {
+        let gpx = Gpx::read(GPX.as_bytes()).unwrap();
+        let track = &gpx.tracks[0];
+
+        let nodes = track.to_nodes();
+        let ids: Vec<ElementID> = nodes.iter().map(|node| node.id).collect();
+        assert_eq!(ids, vec![-1, -2, -3]);
+        assert_eq!(nodes[1].ele, Some(821.5));
+        assert_eq!(
+            nodes[1].timestamp.as_deref(),
+            Some("2020-06-01T12:00:05.500Z")
+        );
+        assert_eq!(Track::from_nodes(&nodes).points().count(), 3);
+        assert_eq!(Waypoint::from(&nodes[1]), track.segments[0][1]);
+
+        let samples = track.to_samples();
+        assert_eq!(samples[0].point, (43.61, -116.2));
+        assert_eq!(samples[0].time, Some(at(0, 0)));
+        assert_eq!(samples[2].time, None);
+    }
+
+    #[test]
+    fn converts_routes() {
+        use crate::{
+            routing::{path::Distance, GraphBuilder},
+            tag::travel_by::Mode,
+            way::Way,
+            AreaData, TagMap,
+        };
+
+        // an L of two streets meeting at node 2
+        let nodes: Vec<Node> = [(0.0, 0.0), (0.0, 0.002), (0.002, 0.002)]
+            .iter()
+            .zip(1..)
+            .map(|((lat, lon), id)| Node {
+                id,
+                lat: *lat,
+                lon: *lon,
+                ..Default::default()
+            })
+            .collect();
+        let tags: TagMap =
+            vec![("highway", "residential")].into_iter().collect();
+        let way = |id, ids: &[usize]| Way {
+            id,
+            nodes: ids.iter().map(|i| &nodes[i - 1]).collect(),
+            tags: Some(tags.clone()),
+            ..Default::default()
+        };
+        let area = AreaData {
+            ways: vec![way(1, &[1, 2]), way(2, &[2, 3])]
+                .into_iter()
+                .map(|way| (way.id, way))
+                .collect(),
+            ..Default::default()
+        };
+        let graph = GraphBuilder::new(&area, Mode::Motorcar).build();
+        let router = Router::new(&graph, Distance);
+
+        let planned = Route {
+            name: None,
+            points: vec![
+                Waypoint::from((0.0, 0.0005)),
+                Waypoint::from((0.0, 0.0015)),
+                Waypoint::from((0.0015, 0.002)),
+            ],
+        };
+        let route = planned.follow(&router).unwrap();
+        assert_eq!(route.ways, vec![1, 2]);
+        assert_eq!(route.nodes, vec![2]);
+        assert!((route.distance - 333.6).abs() < 1.0);
+        assert_eq!(route.geometry.len(), 4);
+
+        let exported = Route::from(&route);
+        assert_eq!(exported.points.len(), 4);
+        assert_eq!(exported.points[2].point(), (0.0, 0.002));
+
+        let single = Route {
+            points: planned.points[..1].to_vec(),
+            ..planned
+        };
+        assert_eq!(single.follow(&router), None);
+    }
+}
@@ -0,0 +1,460 @@
+//! Lanes of a way in each direction of travel, built from
+//! [`lanes`](https://wiki.openstreetmap.org/wiki/Key:lanes),
+//! `lanes:forward`, `lanes:backward` and per-lane values such as
+//! [`turn:lanes`](https://wiki.openstreetmap.org/wiki/Key:turn),
+//! `change:lanes`, `destination:lanes` and `bus:lanes`.
+//!
+//! Per-lane values are separated by `|` and listed from left to right in the
+//! direction of travel. On ways carrying traffic both ways they are tagged
+//! separately for each direction with a `:forward` or `:backward` suffix, or
+//! without one to list every lane of the road as seen in the way direction:
+//! the backward lanes first, then those used both ways and the forward lanes
+//! last, mirrored where `driving_side=left`.
+use crate::{
+    access::oneway::{self, Direction},
+    tag::{access::AccessLevel, travel_by::Mode},
+    way::Way,
+    TagMap,
+};
+use hashbrown::HashMap;
+use std::{convert::Infallible, error, fmt, str::FromStr};
+
+pub static KEY: &'static str = "lanes";
+pub static FORWARD: &'static str = "lanes:forward";
+pub static BACKWARD: &'static str = "lanes:backward";
+/// Center lanes, such as a shared turn lane, usable in both directions
+pub static BOTH_WAYS: &'static str = "lanes:both_ways";
+
+/// Suffix of keys with a value for each lane
+pub static SUFFIX: &'static str = ":lanes";
+
+pub static TURN: &'static str = "turn";
+pub static CHANGE: &'static str = "change";
+pub static DESTINATION: &'static str = "destination";
+
+/// Side of the road traffic keeps to, `right` unless tagged `left`
+pub static DRIVING_SIDE: &'static str = "driving_side";
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum LaneError {
+    /// A lane count isn't a number
+    InvalidCount { key: String, value: String },
+    /// A per-lane value lists a different number of lanes than the way has
+    /// in that direction
+    CountMismatch {
+        key: String,
+        expected: usize,
+        found: usize,
+    },
+    /// `lanes` isn't the sum of the lanes in each direction
+    TotalMismatch {
+        total: usize,
+        forward: usize,
+        backward: usize,
+        both_ways: usize,
+    },
+}
+
+impl fmt::Display for LaneError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            LaneError::InvalidCount { key, value } => {
+                write!(f, "{}={} isn't a lane count", key, value)
+            }
+            LaneError::CountMismatch {
+                key,
+                expected,
+                found,
+            } => write!(
+                f,
+                "{} lists {} lanes but the way has {}",
+                key, found, expected
+            ),
+            LaneError::TotalMismatch {
+                total,
+                forward,
+                backward,
+                both_ways,
+            } => write!(
+                f,
+                "{} lanes don't add up to {} forward, {} backward and {} \
+                 both ways",
+                total, forward, backward, both_ways
+            ),
+        }
+    }
+}
+
+impl error::Error for LaneError {}
+
+/// Movement a lane is marked for at the next junction
+#[derive(Clone, Debug, PartialEq)]
+pub enum Turn {
+    Left,
+    SlightLeft,
+    SharpLeft,
+    Through,
+    Right,
+    SlightRight,
+    SharpRight,
+    Reverse,
+    MergeToLeft,
+    MergeToRight,
+    /// Any other value
+    Unknown(String),
+}
+
+impl Turn {
+    pub fn as_str(&self) -> &str {
+        match self {
+            Turn::Left => "left",
+            Turn::SlightLeft => "slight_left",
+            Turn::SharpLeft => "sharp_left",
+            Turn::Through => "through",
+            Turn::Right => "right",
+            Turn::SlightRight => "slight_right",
+            Turn::SharpRight => "sharp_right",
+            Turn::Reverse => "reverse",
+            Turn::MergeToLeft => "merge_to_left",
+            Turn::MergeToRight => "merge_to_right",
+            Turn::Unknown(value) => value,
+        }
+    }
+}
+
+impl FromStr for Turn {
+    type Err = Infallible;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        Ok(match value {
+            "left" => Turn::Left,
+            "slight_left" => Turn::SlightLeft,
+            "sharp_left" => Turn::SharpLeft,
+            "through" => Turn::Through,
+            "right" => Turn::Right,
+            "slight_right" => Turn::SlightRight,
+            "sharp_right" => Turn::SharpRight,
+            "reverse" => Turn::Reverse,
+            "merge_to_left" => Turn::MergeToLeft,
+            "merge_to_right" => Turn::MergeToRight,
+            other => Turn::Unknown(other.to_owned()),
+        })
+    }
+}
+
+impl fmt::Display for Turn {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Neighbouring lanes that traffic may change to, from `change:lanes`
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Change {
+    pub to_left: bool,
+    pub to_right: bool,
+}
+
+impl Change {
+    fn parse(value: &str) -> Option<Change> {
+        let (to_left, to_right) = match value {
+            "yes" => (true, true),
+            "no" => (false, false),
+            "not_left" | "only_right" => (false, true),
+            "not_right" | "only_left" => (true, false),
+            _ => return None,
+        };
+        Some(Change { to_left, to_right })
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Lane {
+    /// Marked turns, empty when the lane has none
+    pub turns: Vec<Turn>,
+    pub change: Option<Change>,
+    pub destination: Option<String>,
+    /// Access of the lane by mode, such as `bus:lanes=designated|yes`
+    pub access: HashMap<Mode, AccessLevel>,
+}
+
+/// Lanes of a way in each direction, each listed from left to right in the
+/// direction of travel
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Lanes {
+    pub forward: Vec<Lane>,
+    pub backward: Vec<Lane>,
+    pub both_ways: usize,
+}
+
+impl Lanes {
+    /// Lanes of the way, checking that the counts and per-lane values agree.
+    /// A two-way road with only a total lane count has its lanes split evenly
+    /// between directions, the extra lane of an odd count going forward,
+    /// except that a single lane is shared by both directions.
+    pub fn of(way: &Way) -> Result<Lanes, LaneError> {
+        match &way.tags {
+            Some(tags) => Lanes::from_tags(tags),
+            None => Ok(Lanes::default()),
+        }
+    }
+
+    pub fn from_tags(tags: &TagMap) -> Result<Lanes, LaneError> {
+        let total = count(tags, KEY)?;
+        let mut both_ways = count(tags, BOTH_WAYS)?.unwrap_or(0);
+
+        let direction = oneway::direction(tags, Mode::Vehicle);
+        if direction != Direction::Both {
+            let lanes = total.or_else(|| listed(tags, "")).unwrap_or(0);
+            let lanes = build(tags, "", lanes)?;
+
+            return Ok(if direction == Direction::Backward {
+                Lanes {
+                    backward: lanes,
+                    ..Default::default()
+                }
+            } else {
+                Lanes {
+                    forward: lanes,
+                    ..Default::default()
+                }
+            });
+        }
+
+        // a value for every lane of the road also counts them
+        let total = total.or_else(|| listed(tags, ""));
+        let forward =
+            count(tags, FORWARD)?.or_else(|| listed(tags, ":forward"));
+        let backward =
+            count(tags, BACKWARD)?.or_else(|| listed(tags, ":backward"));
+        let (forward, backward) = match (forward, backward, total) {
+            (Some(f), Some(b), _) => (f, b),
+            (Some(f), None, Some(t)) => (f, t.saturating_sub(f + both_ways)),
+            (None, Some(b), Some(t)) => (t.saturating_sub(b + both_ways), b),
+            (None, None, Some(1)) if both_ways == 0 => {
+                both_ways = 1;
+                (0, 0)
+            }
+            (None, None, Some(t)) => {
+                let t = t.saturating_sub(both_ways);
+                (t - t / 2, t / 2)
+            }
+            (f, b, None) => (f.unwrap_or(0), b.unwrap_or(0)),
+        };
+        if let Some(total) = total {
+            if total != forward + backward + both_ways {
+                return Err(LaneError::TotalMismatch {
+                    total,
+                    forward,
+                    backward,
+                    both_ways,
+                });
+            }
+        }
+
+        // values for the whole road, split so that each direction is listed
+        // from left to right in its own direction of travel
+        let mut road = build(tags, "", backward + both_ways + forward)?;
+        if tags.get(DRIVING_SIDE) == Some("left") {
+            road.reverse();
+        }
+        let mut backward_lanes: Vec<Lane> = road.drain(..backward).collect();
+        backward_lanes.reverse();
+        let mut forward_lanes = road.split_off(both_ways);
+
+        fill(tags, ":forward", &mut forward_lanes)?;
+        fill(tags, ":backward", &mut backward_lanes)?;
+
+        Ok(Lanes {
+            forward: forward_lanes,
+            backward: backward_lanes,
+            both_ways,
+        })
+    }
+}
+
+fn count(tags: &TagMap, key: &str) -> Result<Option<usize>, LaneError> {
+    tags.get(key)
+        .map(|value| {
+            value.trim().parse().map_err(|_| LaneError::InvalidCount {
+                key: key.to_owned(),
+                value: value.to_string(),
+            })
+        })
+        .transpose()
+}
+
+/// Prefix of a per-lane key for the direction suffix, such as `turn` for
+/// `turn:lanes:forward`
+fn lane_prefix<'k>(key: &'k str, suffix: &str) -> Option<&'k str> {
+    key.strip_suffix(suffix)?.strip_suffix(SUFFIX)
+}
+
+/// Number of lanes listed by a per-lane value in the direction
+fn listed(tags: &TagMap, suffix: &str) -> Option<usize> {
+    tags.iter()
+        .find(|(key, _)| lane_prefix(key, suffix).is_some())
+        .map(|(_, value)| value.split('|').count())
+}
+
+fn build(
+    tags: &TagMap,
+    suffix: &str,
+    count: usize,
+) -> Result<Vec<Lane>, LaneError> {
+    let mut lanes = vec![Lane::default(); count];
+    fill(tags, suffix, &mut lanes)?;
+    Ok(lanes)
+}
+
+/// Set the per-lane values tagged with the direction suffix, leaving lanes
+/// whose value is empty as they are
+fn fill(
+    tags: &TagMap,
+    suffix: &str,
+    lanes: &mut [Lane],
+) -> Result<(), LaneError> {
+    let count = lanes.len();
+
+    for (key, value) in tags {
+        let prefix = match lane_prefix(key, suffix) {
+            Some(prefix) => prefix,
+            None => continue,
+        };
+        let values: Vec<&str> = value.split('|').map(str::trim).collect();
+        if values.len() != count {
+            return Err(LaneError::CountMismatch {
+                key: key.to_string(),
+                expected: count,
+                found: values.len(),
+            });
+        }
+
+        for (lane, value) in lanes.iter_mut().zip(values) {
+            if value.is_empty() {
+                continue;
+            }
+            if prefix == TURN {
+                lane.turns = value
+                    .split(';')
+                    .filter(|turn| *turn != "none")
+                    .map(|turn| turn.parse().unwrap())
+                    .collect();
+            } else if prefix == CHANGE {
+                lane.change = Change::parse(value);
+            } else if prefix == DESTINATION {
+                lane.destination = Some(value.to_owned());
+            } else if let Ok(mode) = prefix.parse::<Mode>() {
+                lane.access.insert(mode, value.parse().unwrap());
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tags(pairs: &[(&'static str, &'static str)]) -> TagMap {
+        pairs.iter().copied().collect()
+    }
+
+    #[test]
+    fn two_way_lanes() {
+        let lanes = Lanes::from_tags(&tags(&[
+            ("lanes", "3"),
+            ("lanes:forward", "2"),
+            ("turn:lanes:forward", "left|through;right"),
+            ("bus:lanes:backward", "designated"),
+        ]))
+        .unwrap();
+
+        assert_eq!(lanes.forward.len(), 2);
+        assert_eq!(lanes.backward.len(), 1);
+        assert_eq!(lanes.forward[0].turns, vec![Turn::Left]);
+        assert_eq!(lanes.forward[1].turns, vec![Turn::Through, Turn::Right]);
+        assert_eq!(
+            lanes.backward[0].access.get(&Mode::Bus),
+            Some(&AccessLevel::Designated)
+        );
+    }
+
+    #[test]
+    fn whole_road_lanes() {
+        let road = [
+            ("lanes", "3"),
+            ("lanes:backward", "1"),
+            ("turn:lanes", "left|left|through;right"),
+            ("destination:lanes:forward", "Boise|"),
+        ];
+        let lanes = Lanes::from_tags(&tags(&road)).unwrap();
+
+        assert_eq!(lanes.forward.len(), 2);
+        assert_eq!(lanes.backward[0].turns, vec![Turn::Left]);
+        assert_eq!(lanes.forward[0].turns, vec![Turn::Left]);
+        assert_eq!(lanes.forward[1].turns, vec![Turn::Through, Turn::Right]);
+        assert_eq!(lanes.forward[0].destination.as_deref(), Some("Boise"));
+
+        let mut left = road.to_vec();
+        left.push(("driving_side", "left"));
+        let lanes = Lanes::from_tags(&tags(&left)).unwrap();
+        assert_eq!(lanes.backward[0].turns, vec![Turn::Through, Turn::Right]);
+
+        let single = Lanes::from_tags(&tags(&[("lanes", "1")])).unwrap();
+        assert_eq!(single.both_ways, 1);
+        assert!(single.forward.is_empty() && single.backward.is_empty());
+    }
+
+    #[test]
+    fn oneway_lanes() {
+        let lanes = Lanes::from_tags(&tags(&[
+            ("oneway", "yes"),
+            ("turn:lanes", "left|none"),
+            ("change:lanes", "not_left|yes"),
+            ("destination:lanes", "Boise|"),
+        ]))
+        .unwrap();
+
+        assert_eq!(lanes.forward.len(), 2);
+        assert!(lanes.backward.is_empty());
+        assert!(lanes.forward[1].turns.is_empty());
+        assert_eq!(
+            lanes.forward[0].change,
+            Some(Change {
+                to_left: false,
+                to_right: true
+            })
+        );
+        assert_eq!(lanes.forward[0].destination.as_deref(), Some("Boise"));
+        assert_eq!(lanes.forward[1].destination, None);
+    }
+
+    #[test]
+    fn reports_disagreeing_counts() {
+        assert!(matches!(
+            Lanes::from_tags(&tags(&[
+                ("lanes", "4"),
+                ("lanes:forward", "1"),
+                ("lanes:backward", "1"),
+            ])),
+            Err(LaneError::TotalMismatch { .. })
+        ));
+        assert!(matches!(
+            Lanes::from_tags(&tags(&[
+                ("oneway", "yes"),
+                ("lanes", "2"),
+                ("turn:lanes", "left|through|right"),
+            ])),
+            Err(LaneError::CountMismatch {
+                expected: 2,
+                found: 3,
+                ..
+            })
+        ));
+        assert!(matches!(
+            Lanes::from_tags(&tags(&[("lanes", "two")])),
+            Err(LaneError::InvalidCount { .. })
+        ));
+    }
+}
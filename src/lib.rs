@@ -1,8 +1,19 @@
 #![allow(dead_code)]
 
+pub mod access;
+pub mod conditional;
+pub mod edit;
+pub mod elevation;
+pub mod geo;
+pub mod gpx;
+pub mod lanes;
+pub mod multipolygon;
 pub mod node;
+pub mod opening_hours;
 pub mod overpass;
 pub mod relation;
+pub mod restriction;
+pub mod routing;
 pub mod tag;
 pub mod way;
 pub mod item_type {
@@ -12,7 +23,8 @@ pub mod item_type {
 }
 
 use hashbrown::HashMap;
-use serde::Deserialize;
+use serde::{Deserialize, Deserializer};
+use std::{borrow::Cow, iter::FromIterator};
 
 pub use node::Node;
 pub use relation::{role, Relation};
@@ -58,7 +70,88 @@ pub type ElementID = i64;
 ///
 /// https://wiki.openstreetmap.org/wiki/Tags
 ///
-pub type TagMap = HashMap<&'static str, &'static str>;
+/// Keys and values are usually static text, but the map owns any text built
+/// at runtime, such as a key with its direction reversed.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct TagMap(HashMap<Cow<'static, str>, Cow<'static, str>>);
+
+impl TagMap {
+    pub fn new() -> Self {
+        TagMap::default()
+    }
+
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.0.get(key).map(|value| value.as_ref())
+    }
+
+    pub fn contains_key(&self, key: &str) -> bool {
+        self.0.contains_key(key)
+    }
+
+    /// Set the tag, returning its previous value
+    pub fn insert<K, V>(
+        &mut self,
+        key: K,
+        value: V,
+    ) -> Option<Cow<'static, str>>
+    where
+        K: Into<Cow<'static, str>>,
+        V: Into<Cow<'static, str>>,
+    {
+        self.0.insert(key.into(), value.into())
+    }
+
+    pub fn remove(&mut self, key: &str) -> Option<Cow<'static, str>> {
+        self.0.remove(key)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.0
+            .iter()
+            .map(|(key, value)| (key.as_ref(), value.as_ref()))
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+}
+
+impl<K, V> FromIterator<(K, V)> for TagMap
+where
+    K: Into<Cow<'static, str>>,
+    V: Into<Cow<'static, str>>,
+{
+    fn from_iter<I: IntoIterator<Item = (K, V)>>(iter: I) -> Self {
+        TagMap(
+            iter.into_iter()
+                .map(|(key, value)| (key.into(), value.into()))
+                .collect(),
+        )
+    }
+}
+
+impl<'t> IntoIterator for &'t TagMap {
+    type Item = (&'t str, &'t str);
+    type IntoIter = Box<dyn Iterator<Item = (&'t str, &'t str)> + 't>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        Box::new(self.iter())
+    }
+}
+
+impl<'de> Deserialize<'de> for TagMap {
+    fn deserialize<D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Self, D::Error> {
+        let tags: std::collections::HashMap<String, String> =
+            Deserialize::deserialize(deserializer)?;
+        Ok(tags.into_iter().collect())
+    }
+}
 
 /// ISO 8601 time of the last modification (e.g. "2016-12-31T23:59:59.999Z")
 /// https://wiki.openstreetmap.org/wiki/Elements#Common_attributes
@@ -0,0 +1,420 @@
+//! Assemble [areas](https://wiki.openstreetmap.org/wiki/Area) from closed
+//! ways and [multipolygon relations](https://wiki.openstreetmap.org/wiki/Relation:multipolygon).
+//!
+//! Member ways are stitched end-to-end into closed rings, whatever their
+//! direction or however many ways a ring is split across. Rings are then
+//! classified as outer or inner by how deeply they nest within other rings,
+//! so mistagged `inner`/`outer` roles don't affect the result.
+use crate::{
+    geo::{self, Point},
+    node::Node,
+    relation::Relation,
+    way::Way,
+    ElementID,
+};
+use std::{error, fmt};
+
+/// Closed sequence of nodes whose first and last node are the same
+#[derive(Clone, Debug)]
+pub struct Ring<'a> {
+    pub nodes: Vec<&'a Node>,
+}
+
+impl<'a> Ring<'a> {
+    pub fn points(&self) -> Vec<Point> {
+        self.nodes.iter().map(|n| n.point()).collect()
+    }
+
+    /// Area in square degrees, positive when the ring is counter-clockwise
+    fn signed_area(&self) -> f64 {
+        geo::signed_area(&self.points()) / 2.0
+    }
+
+    fn reverse(&mut self) {
+        self.nodes.reverse();
+    }
+
+    /// Whether this ring lies inside `other`, tested with the first node not
+    /// shared by both rings so that touching rings are still classified
+    fn is_within(&self, other: &Ring) -> bool {
+        let outline = other.points();
+        self.nodes
+            .iter()
+            .find(|n| !other.nodes.iter().any(|o| o.id == n.id))
+            .is_some_and(|n| geo::ring_contains(&outline, n.point()))
+    }
+}
+
+/// An outer ring with any holes it contains. Outer rings are oriented
+/// counter-clockwise and inner rings clockwise.
+#[derive(Clone, Debug)]
+pub struct Polygon<'a> {
+    pub outer: Ring<'a>,
+    pub inners: Vec<Ring<'a>>,
+}
+
+/// One or more polygons making up a single area
+#[derive(Clone, Debug, Default)]
+pub struct MultiPolygon<'a> {
+    pub polygons: Vec<Polygon<'a>>,
+}
+
+/// Problems that prevent member ways from forming a valid area
+#[derive(Clone, Debug, PartialEq)]
+pub enum AssemblyError {
+    /// No member ways were found to build rings from
+    NoRings,
+    /// Member ways could not be joined into a closed ring. The IDs are the
+    /// nodes at either end of the unclosed sequence.
+    UnclosedRing { start: ElementID, end: ElementID },
+    /// A ring crosses or touches itself near the given point
+    SelfIntersection { at: Point },
+    /// Two rings share the given node
+    TouchingRings { node: ElementID },
+    /// Two rings cross each other near the given point
+    CrossingRings { at: Point },
+}
+
+impl fmt::Display for AssemblyError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            AssemblyError::NoRings => write!(f, "no member ways to assemble"),
+            AssemblyError::UnclosedRing { start, end } => {
+                write!(
+                    f,
+                    "ring from node {} to node {} is not closed",
+                    start, end
+                )
+            }
+            AssemblyError::SelfIntersection { at } => {
+                write!(f, "ring intersects itself at {:?}", at)
+            }
+            AssemblyError::TouchingRings { node } => {
+                write!(f, "rings touch at node {}", node)
+            }
+            AssemblyError::CrossingRings { at } => {
+                write!(f, "rings cross at {:?}", at)
+            }
+        }
+    }
+}
+
+impl error::Error for AssemblyError {}
+
+impl<'a> MultiPolygon<'a> {
+    /// Area from a single closed way
+    pub fn from_way(
+        way: &Way<'a>,
+    ) -> Result<MultiPolygon<'a>, Vec<AssemblyError>> {
+        assemble(vec![way.nodes.clone()])
+    }
+
+    /// Area from the member ways of a multipolygon (or boundary) relation.
+    /// Node members, such as `label` or `admin_centre`, are ignored.
+    pub fn from_relation(
+        relation: &Relation<'a>,
+    ) -> Result<MultiPolygon<'a>, Vec<AssemblyError>> {
+        assemble(
+            relation
+                .members
+                .iter()
+                .filter(|m| m.nodes.len() > 1)
+                .map(|m| m.nodes.clone())
+                .collect(),
+        )
+    }
+}
+
+/// Build rings from way segments and nest them into polygons. Segments of
+/// fewer than two nodes can't form part of a ring and are skipped.
+fn assemble<'a>(
+    mut segments: Vec<Vec<&'a Node>>,
+) -> Result<MultiPolygon<'a>, Vec<AssemblyError>> {
+    segments.retain(|nodes| nodes.len() > 1);
+    if segments.is_empty() {
+        return Err(vec![AssemblyError::NoRings]);
+    }
+    let (rings, mut errors) = stitch(segments);
+
+    errors.extend(validate(&rings));
+
+    if !errors.is_empty() {
+        return Err(errors);
+    }
+
+    Ok(nest(rings))
+}
+
+/// Join segments that share end nodes into closed rings. A segment may be
+/// appended in either direction.
+fn stitch<'a>(
+    segments: Vec<Vec<&'a Node>>,
+) -> (Vec<Ring<'a>>, Vec<AssemblyError>) {
+    let mut rings = Vec::new();
+    let mut errors = Vec::new();
+    let mut open: Vec<Vec<&Node>> = Vec::new();
+
+    for nodes in segments {
+        if is_closed(&nodes) {
+            rings.push(Ring { nodes });
+        } else {
+            open.push(nodes);
+        }
+    }
+
+    while let Some(mut current) = open.pop() {
+        while !is_closed(&current) {
+            let last = current[current.len() - 1].id;
+            let next = open
+                .iter()
+                .position(|s| s[0].id == last || s[s.len() - 1].id == last);
+
+            match next {
+                Some(i) => {
+                    let mut segment = open.swap_remove(i);
+                    if segment[0].id != last {
+                        segment.reverse();
+                    }
+                    current.extend(segment.into_iter().skip(1));
+                }
+                None => break,
+            }
+        }
+
+        if is_closed(&current) {
+            rings.push(Ring { nodes: current });
+        } else {
+            errors.push(AssemblyError::UnclosedRing {
+                start: current[0].id,
+                end: current[current.len() - 1].id,
+            });
+        }
+    }
+
+    (rings, errors)
+}
+
+fn is_closed(nodes: &[&Node]) -> bool {
+    nodes.len() > 3 && nodes[0].id == nodes[nodes.len() - 1].id
+}
+
+/// Report rings that cross or touch themselves or each other
+fn validate(rings: &[Ring]) -> Vec<AssemblyError> {
+    let mut errors = Vec::new();
+
+    for ring in rings {
+        let points = ring.points();
+        let n = points.len() - 1;
+        'ring: for i in 0..n {
+            // segments sharing an endpoint with i are adjacent
+            for j in (i + 2)..n {
+                if i == 0 && j == n - 1 {
+                    continue;
+                }
+                if geo::segments_intersect(
+                    points[i],
+                    points[i + 1],
+                    points[j],
+                    points[j + 1],
+                ) {
+                    errors.push(AssemblyError::SelfIntersection {
+                        at: points[j],
+                    });
+                    break 'ring;
+                }
+            }
+        }
+    }
+
+    for (i, a) in rings.iter().enumerate() {
+        for b in &rings[i + 1..] {
+            if let Some(shared) = a
+                .nodes
+                .iter()
+                .find(|n| b.nodes.iter().any(|m| m.id == n.id))
+            {
+                errors.push(AssemblyError::TouchingRings { node: shared.id });
+                continue;
+            }
+            let (pa, pb) = (a.points(), b.points());
+            let crossing = pa.windows(2).find_map(|s| {
+                pb.windows(2)
+                    .find(|t| geo::segments_intersect(s[0], s[1], t[0], t[1]))
+                    .map(|t| t[0])
+            });
+            if let Some(at) = crossing {
+                errors.push(AssemblyError::CrossingRings { at });
+            }
+        }
+    }
+
+    errors
+}
+
+/// Assign each ring a nesting depth within the other rings. Even depths are
+/// outer rings and odd depths are holes of their immediate parent.
+fn nest<'a>(mut rings: Vec<Ring<'a>>) -> MultiPolygon<'a> {
+    // larger rings first so a ring's possible parents are already placed
+    rings.sort_by(|a, b| {
+        b.signed_area()
+            .abs()
+            .partial_cmp(&a.signed_area().abs())
+            .unwrap_or(std::cmp::Ordering::Equal)
+    });
+
+    let mut depth: Vec<usize> = Vec::with_capacity(rings.len());
+    let mut parent: Vec<Option<usize>> = Vec::with_capacity(rings.len());
+
+    for (i, ring) in rings.iter().enumerate() {
+        // the smallest enclosing ring is the last one found
+        let p = (0..i).rev().find(|&j| ring.is_within(&rings[j]));
+        depth.push(p.map_or(0, |j| depth[j] + 1));
+        parent.push(p);
+    }
+
+    let mut polygons: Vec<Polygon> = Vec::new();
+    let mut polygon_of: Vec<Option<usize>> = vec![None; rings.len()];
+
+    for (i, mut ring) in rings.into_iter().enumerate() {
+        let is_outer = depth[i] % 2 == 0;
+        let ccw = ring.signed_area() > 0.0;
+
+        if is_outer {
+            if !ccw {
+                ring.reverse();
+            }
+            polygon_of[i] = Some(polygons.len());
+            polygons.push(Polygon {
+                outer: ring,
+                inners: Vec::new(),
+            });
+        } else {
+            if ccw {
+                ring.reverse();
+            }
+            if let Some(p) = parent[i].and_then(|p| polygon_of[p]) {
+                polygons[p].inners.push(ring);
+            }
+        }
+    }
+
+    MultiPolygon { polygons }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::relation::{role, Member};
+
+    fn node(id: ElementID, lat: f32, lon: f32) -> Node {
+        Node {
+            id,
+            lat,
+            lon,
+            ..Default::default()
+        }
+    }
+
+    fn member<'a>(nodes: Vec<&'a Node>, role: &'static str) -> Member<'a> {
+        Member {
+            nodes,
+            role,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn stitches_split_and_reversed_ways() {
+        let a = node(1, 0.0, 0.0);
+        let b = node(2, 0.0, 4.0);
+        let c = node(3, 4.0, 4.0);
+        let d = node(4, 4.0, 0.0);
+        let relation = Relation {
+            members: vec![
+                member(vec![&a, &b], role::OUTER),
+                member(vec![&c, &b], role::OUTER),
+                member(vec![&c, &d, &a], role::OUTER),
+            ],
+            ..Default::default()
+        };
+        let area = MultiPolygon::from_relation(&relation).unwrap();
+
+        assert_eq!(area.polygons.len(), 1);
+        assert_eq!(area.polygons[0].outer.nodes.len(), 5);
+        assert!(area.polygons[0].outer.signed_area() > 0.0);
+    }
+
+    #[test]
+    fn classifies_holes_by_containment() {
+        let outer = [
+            node(1, 0.0, 0.0),
+            node(2, 0.0, 4.0),
+            node(3, 4.0, 4.0),
+            node(4, 4.0, 0.0),
+        ];
+        let inner = [node(5, 1.0, 1.0), node(6, 1.0, 2.0), node(7, 2.0, 2.0)];
+        let relation = Relation {
+            members: vec![
+                // roles deliberately swapped
+                member(
+                    vec![&inner[0], &inner[1], &inner[2], &inner[0]],
+                    role::OUTER,
+                ),
+                member(
+                    vec![&outer[0], &outer[1], &outer[2], &outer[3], &outer[0]],
+                    role::INNER,
+                ),
+            ],
+            ..Default::default()
+        };
+        let area = MultiPolygon::from_relation(&relation).unwrap();
+
+        assert_eq!(area.polygons.len(), 1);
+        assert_eq!(area.polygons[0].outer.nodes[0].id, 1);
+        assert_eq!(area.polygons[0].inners.len(), 1);
+        assert!(area.polygons[0].inners[0].signed_area() < 0.0);
+    }
+
+    #[test]
+    fn reports_unclosed_and_self_intersecting_rings() {
+        let a = node(1, 0.0, 0.0);
+        let b = node(2, 0.0, 4.0);
+        let c = node(3, 4.0, 0.0);
+        let d = node(4, 4.0, 4.0);
+
+        let open = Relation {
+            members: vec![member(vec![&a, &b, &d], role::OUTER)],
+            ..Default::default()
+        };
+        assert_eq!(
+            MultiPolygon::from_relation(&open).unwrap_err(),
+            vec![AssemblyError::UnclosedRing { start: 1, end: 4 }]
+        );
+
+        let bowtie = Relation {
+            members: vec![member(vec![&a, &b, &c, &d, &a], role::OUTER)],
+            ..Default::default()
+        };
+        assert!(matches!(
+            MultiPolygon::from_relation(&bowtie).unwrap_err()[0],
+            AssemblyError::SelfIntersection { .. }
+        ));
+    }
+
+    #[test]
+    fn rejects_ways_too_short_for_rings() {
+        let a = node(1, 0.0, 0.0);
+        assert_eq!(
+            MultiPolygon::from_way(&Way::default()).unwrap_err(),
+            vec![AssemblyError::NoRings]
+        );
+        let single = Way {
+            nodes: vec![&a],
+            ..Default::default()
+        };
+        assert_eq!(
+            MultiPolygon::from_way(&single).unwrap_err(),
+            vec![AssemblyError::NoRings]
+        );
+    }
+}
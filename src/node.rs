@@ -12,7 +12,7 @@ use serde::Deserialize;
 ///
 /// https://wiki.openstreetmap.org/wiki/Node
 ///
-#[derive(Default, Deserialize)]
+#[derive(Debug, Default, Deserialize)]
 pub struct Node {
     pub id: ElementID,
 
@@ -43,14 +43,12 @@ impl<'a> Node {
 
 impl<'a> Tagged for Node {
     fn get_tag(&self, key: &str) -> Option<&str> {
-        self.tags
-            .as_ref()
-            .and_then(|tags| tags.get(key).map(|value| *value))
+        self.tags.as_ref().and_then(|tags| tags.get(key))
     }
 
     fn has_tag(&self, key: &str) -> bool {
         self.tags
             .as_ref()
-            .map_or(false, |tags| tags.contains_key(key))
+            .is_some_and(|tags| tags.contains_key(key))
     }
 }
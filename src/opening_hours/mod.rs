@@ -0,0 +1,762 @@
+//! Parse and evaluate [`opening_hours`](https://wiki.openstreetmap.org/wiki/Key:opening_hours/specification)
+//! values such as `Mo-Fr 08:00-18:00; Sa 09:00-13:00; PH off`.
+//!
+//! A value is a sequence of rules. Each rule selects dates (years, month
+//! days, weeks, weekdays and holidays), times of day, and whether those
+//! times are open, closed or unknown. Rules are joined by
+//!
+//! - `;` so the rule replaces earlier rules on the days it selects
+//! - `,` so the rule adds to earlier rules
+//! - `||` so the rule only applies when no earlier rule does
+//!
+//! Times may pass midnight (`22:00-02:00` or `22:00-26:00`) and may be
+//! relative to the sun (`sunrise-(sunset-01:00)`). Sun times are calculated
+//! for the `Environment` location or default to 06:00 sunrise and 18:00
+//! sunset.
+mod parse;
+mod sun;
+
+use crate::geo::Point;
+use chrono::{Datelike, Duration, NaiveDate, NaiveDateTime, Timelike};
+use hashbrown::HashSet;
+use std::{error, fmt, str::FromStr};
+
+/// Minutes in a day
+const DAY: u32 = 24 * 60;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct ParseError {
+    /// Byte offset of the problem within the value
+    pub position: usize,
+    pub message: String,
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "invalid opening_hours at {}: {}",
+            self.position, self.message
+        )
+    }
+}
+
+impl error::Error for ParseError {}
+
+/// How a rule combines with the rules before it
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Separator {
+    /// `;` replaces earlier rules on the selected days
+    Normal,
+    /// `,` adds to earlier rules
+    Additional,
+    /// `||` applies only where no earlier rule does
+    Fallback,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum State {
+    Open,
+    Closed,
+    Unknown,
+}
+
+/// Inclusive range of years, open-ended when `end` is `None` (`2020+`)
+#[derive(Clone, Debug, PartialEq)]
+pub struct YearRange {
+    pub start: i32,
+    pub end: Option<i32>,
+    pub step: u32,
+}
+
+/// Day of the year a month day selector refers to
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Day {
+    /// Month numbered from 1 and day of the month
+    Fixed {
+        month: u32,
+        day: u32,
+    },
+    Easter,
+}
+
+/// Day of the year shifted by a number of days (`easter -2 days`)
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct DatePoint {
+    pub day: Day,
+    pub offset: i32,
+}
+
+/// Inclusive range of months or dates, which may wrap past the end of the
+/// year (`Nov-Feb`, `Dec 24-Jan 02`)
+#[derive(Clone, Debug, PartialEq)]
+pub enum MonthdayRange {
+    /// Months numbered from 1
+    Months {
+        start: u32,
+        end: u32,
+    },
+    Dates {
+        start: DatePoint,
+        end: DatePoint,
+    },
+}
+
+/// Inclusive range of ISO week numbers
+#[derive(Clone, Debug, PartialEq)]
+pub struct WeekRange {
+    pub start: u32,
+    pub end: u32,
+    pub step: u32,
+}
+
+/// Inclusive range of weekdays, numbered from Monday, which may wrap past
+/// Sunday (`Sa-Mo`). When `nth` is not empty, only the given occurrences
+/// within the month match; negative numbers count from the end of the month
+/// (`Su[-1]` is the last Sunday).
+#[derive(Clone, Debug, PartialEq)]
+pub struct WeekdayRange {
+    pub start: u32,
+    pub end: u32,
+    pub nth: Vec<(i32, i32)>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Holiday {
+    /// `PH`
+    Public,
+    /// `SH`
+    School,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SunEvent {
+    /// Civil dawn
+    Dawn,
+    Sunrise,
+    Sunset,
+    /// Civil dusk
+    Dusk,
+}
+
+/// Time of day, either in minutes since midnight or relative to the sun
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Time {
+    Fixed(u32),
+    Event { event: SunEvent, offset: i32 },
+}
+
+/// Span of time from `start` up to but excluding `end`. A span with no end
+/// is a point in time, such as a collection time, and lasts one minute. An
+/// open end (`17:00+`) has an unknown closing time, so the state is unknown
+/// from the start, or from the end when one is given (`10:00-14:00+`), until
+/// midnight.
+#[derive(Clone, Debug, PartialEq)]
+pub struct TimeSpan {
+    pub start: Time,
+    pub end: Option<Time>,
+    pub open_end: bool,
+}
+
+/// Dates and times a rule applies to. Empty lists match everything.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Selector {
+    pub years: Vec<YearRange>,
+    pub monthdays: Vec<MonthdayRange>,
+    pub weeks: Vec<WeekRange>,
+    pub weekdays: Vec<WeekdayRange>,
+    pub holidays: Vec<Holiday>,
+    /// Whether a date must match both holidays and weekdays (`SH Mo-Fr`)
+    /// rather than either (`PH,Sa`)
+    pub holidays_and_weekdays: bool,
+    pub times: Vec<TimeSpan>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Rule {
+    pub separator: Separator,
+    pub selector: Selector,
+    pub state: State,
+    pub comment: Option<String>,
+}
+
+/// Parsed `opening_hours` value
+#[derive(Clone, Debug, PartialEq)]
+pub struct OpeningHours {
+    pub rules: Vec<Rule>,
+}
+
+/// Place and holidays that `opening_hours` are evaluated for
+#[derive(Clone, Debug, Default)]
+pub struct Environment {
+    /// Location used to calculate sun times
+    pub location: Option<Point>,
+    /// Minutes local time is ahead of UTC. When missing it's estimated from
+    /// the location's longitude.
+    pub utc_offset: Option<i32>,
+    pub public_holidays: HashSet<NaiveDate>,
+    /// Inclusive date ranges of school holidays
+    pub school_holidays: Vec<(NaiveDate, NaiveDate)>,
+}
+
+impl Environment {
+    fn is_holiday(&self, holiday: Holiday, date: NaiveDate) -> bool {
+        match holiday {
+            Holiday::Public => self.public_holidays.contains(&date),
+            Holiday::School => self
+                .school_holidays
+                .iter()
+                .any(|(start, end)| date >= *start && date <= *end),
+        }
+    }
+}
+
+/// Minutes of a day, which may extend into the next day, with the state and
+/// comment of the rule that produced them
+#[derive(Clone, Debug)]
+struct Interval<'r> {
+    start: u32,
+    end: u32,
+    state: State,
+    comment: Option<&'r str>,
+}
+
+/// Intervals from the rules that apply on one day
+#[derive(Debug, Default)]
+struct Schedule<'r> {
+    intervals: Vec<Interval<'r>>,
+    fallback: Vec<Interval<'r>>,
+}
+
+impl<'r> Schedule<'r> {
+    /// Last interval covering the minute, which may be past midnight
+    fn at(&self, minute: u32) -> Option<&Interval<'r>> {
+        self.intervals
+            .iter()
+            .rev()
+            .find(|i| minute >= i.start && minute < i.end)
+    }
+
+    fn fallback_at(&self, minute: u32) -> Option<&Interval<'r>> {
+        self.fallback
+            .iter()
+            .rev()
+            .find(|i| minute >= i.start && minute < i.end)
+    }
+}
+
+impl OpeningHours {
+    /// Whether open at the local time using the default environment
+    pub fn is_open(&self, time: NaiveDateTime) -> bool {
+        self.state(time, &Environment::default()) == State::Open
+    }
+
+    /// State at the local time
+    pub fn state(&self, time: NaiveDateTime, env: &Environment) -> State {
+        self.evaluate(time, env).0
+    }
+
+    /// Comment of the rule deciding the state at the local time
+    pub fn comment(
+        &self,
+        time: NaiveDateTime,
+        env: &Environment,
+    ) -> Option<&str> {
+        self.evaluate(time, env).1
+    }
+
+    /// Next time after the given local time that the state changes, looking
+    /// up to a year ahead, using the default environment
+    pub fn next_change(&self, time: NaiveDateTime) -> Option<NaiveDateTime> {
+        self.next_change_with(time, &Environment::default())
+    }
+
+    /// Next time after the given local time that the state changes, looking
+    /// up to a year ahead
+    pub fn next_change_with(
+        &self,
+        time: NaiveDateTime,
+        env: &Environment,
+    ) -> Option<NaiveDateTime> {
+        let current = self.state(time, env);
+        let today = time.date();
+
+        for offset in -1..=366 {
+            let date = match today.checked_add_signed(Duration::days(offset)) {
+                Some(date) => date,
+                None => continue,
+            };
+            let schedule = self.schedule(date, env);
+            let mut minutes: Vec<u32> = schedule
+                .intervals
+                .iter()
+                .chain(schedule.fallback.iter())
+                .flat_map(|i| vec![i.start, i.end])
+                .chain(std::iter::once(0))
+                .collect();
+            minutes.sort_unstable();
+            minutes.dedup();
+
+            for minute in minutes {
+                let candidate = match date
+                    .and_hms_opt(0, 0, 0)
+                    .unwrap()
+                    .checked_add_signed(Duration::minutes(i64::from(minute)))
+                {
+                    Some(candidate) => candidate,
+                    None => continue,
+                };
+                if candidate > time && self.state(candidate, env) != current {
+                    return Some(candidate);
+                }
+            }
+        }
+        None
+    }
+
+    fn evaluate(
+        &self,
+        time: NaiveDateTime,
+        env: &Environment,
+    ) -> (State, Option<&str>) {
+        let date = time.date();
+        let minute = time.hour() * 60 + time.minute();
+        let today = self.schedule(date, env);
+
+        if let Some(i) = today.at(minute) {
+            return (i.state, i.comment);
+        }
+        if let Some(yesterday) = date.pred_opt() {
+            if let Some(i) = self.schedule(yesterday, env).at(minute + DAY) {
+                return (i.state, i.comment);
+            }
+        }
+        today
+            .fallback_at(minute)
+            .map_or((State::Closed, None), |i| (i.state, i.comment))
+    }
+
+    /// Intervals that the rules produce for one day
+    fn schedule(&self, date: NaiveDate, env: &Environment) -> Schedule<'_> {
+        let mut schedule = Schedule::default();
+
+        for rule in &self.rules {
+            if !rule.selector.matches_date(date, env) {
+                continue;
+            }
+            let intervals = rule.selector.spans(date, env).into_iter().map(
+                |(start, end, open_end)| Interval {
+                    start,
+                    end,
+                    state: if open_end { State::Unknown } else { rule.state },
+                    comment: rule.comment.as_deref(),
+                },
+            );
+
+            match rule.separator {
+                Separator::Fallback => {
+                    if schedule.intervals.is_empty() {
+                        schedule.fallback.extend(intervals)
+                    }
+                }
+                Separator::Additional => schedule.intervals.extend(intervals),
+                Separator::Normal => {
+                    // closing times within a day keep the rest of the day
+                    let partial = rule.state == State::Closed
+                        && !rule.selector.times.is_empty();
+                    if !partial {
+                        schedule.intervals.clear();
+                    }
+                    schedule.intervals.extend(intervals);
+                }
+            }
+        }
+        schedule
+    }
+}
+
+impl FromStr for OpeningHours {
+    type Err = ParseError;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        parse::parse(text).map(|rules| OpeningHours { rules })
+    }
+}
+
+impl Selector {
+    fn matches_date(&self, date: NaiveDate, env: &Environment) -> bool {
+        let any = |empty: bool, matched: bool| empty || matched;
+
+        let years = any(
+            self.years.is_empty(),
+            self.years.iter().any(|y| y.contains(date.year())),
+        );
+        let monthdays = any(
+            self.monthdays.is_empty(),
+            self.monthdays.iter().any(|m| m.contains(date)),
+        );
+        let weeks = any(
+            self.weeks.is_empty(),
+            self.weeks
+                .iter()
+                .any(|w| w.contains(date.iso_week().week())),
+        );
+
+        years && monthdays && weeks && self.matches_day(date, env)
+    }
+
+    fn matches_day(&self, date: NaiveDate, env: &Environment) -> bool {
+        let holiday = self.holidays.iter().any(|h| env.is_holiday(*h, date));
+        let weekday = self.weekdays.iter().any(|w| w.contains(date));
+
+        match (self.holidays.is_empty(), self.weekdays.is_empty()) {
+            (true, true) => true,
+            (false, true) => holiday,
+            (true, false) => weekday,
+            (false, false) if self.holidays_and_weekdays => holiday && weekday,
+            (false, false) => holiday || weekday,
+        }
+    }
+
+    /// Minute spans of the day the rule covers, which may extend past
+    /// midnight, and whether each is the unknown part of an open end
+    fn spans(
+        &self,
+        date: NaiveDate,
+        env: &Environment,
+    ) -> Vec<(u32, u32, bool)> {
+        if self.times.is_empty() {
+            return vec![(0, DAY, false)];
+        }
+        let mut spans = Vec::with_capacity(self.times.len());
+        for span in &self.times {
+            let start = span.start.minutes(date, env);
+            match (&span.end, span.open_end) {
+                (Some(end), open_end) => {
+                    let mut end = end.minutes(date, env);
+                    if end <= start {
+                        end += DAY;
+                    }
+                    spans.push((start, end, false));
+                    if open_end && end < DAY {
+                        spans.push((end, DAY, true));
+                    }
+                }
+                (None, true) => spans.push((start, DAY.max(start + 1), true)),
+                (None, false) => spans.push((start, start + 1, false)),
+            }
+        }
+        spans
+    }
+}
+
+impl YearRange {
+    fn contains(&self, year: i32) -> bool {
+        year >= self.start
+            && self.end.is_none_or(|end| year <= end)
+            && (year - self.start) as u32 % self.step.max(1) == 0
+    }
+}
+
+impl WeekRange {
+    fn contains(&self, week: u32) -> bool {
+        week >= self.start
+            && week <= self.end
+            && (week - self.start) % self.step.max(1) == 0
+    }
+}
+
+impl WeekdayRange {
+    fn contains(&self, date: NaiveDate) -> bool {
+        let day = date.weekday().num_days_from_monday();
+        let in_range = if self.start <= self.end {
+            day >= self.start && day <= self.end
+        } else {
+            day >= self.start || day <= self.end
+        };
+        if !in_range || self.nth.is_empty() {
+            return in_range;
+        }
+        let nth = (date.day() as i32 - 1) / 7 + 1;
+        let nth_from_end =
+            -((days_in_month(date) as i32 - date.day() as i32) / 7 + 1);
+
+        self.nth.iter().any(|(a, b)| {
+            (nth >= *a && nth <= *b)
+                || (nth_from_end >= *a && nth_from_end <= *b)
+        })
+    }
+}
+
+impl MonthdayRange {
+    fn contains(&self, date: NaiveDate) -> bool {
+        match self {
+            MonthdayRange::Months { start, end } => {
+                let month = date.month();
+                if start <= end {
+                    month >= *start && month <= *end
+                } else {
+                    month >= *start || month <= *end
+                }
+            }
+            MonthdayRange::Dates { start, end } => {
+                let year = date.year();
+                match (start.resolve(year), end.resolve(year)) {
+                    (Some(s), Some(e)) if s <= e => date >= s && date <= e,
+                    (Some(s), Some(e)) => date >= s || date <= e,
+                    _ => false,
+                }
+            }
+        }
+    }
+}
+
+impl DatePoint {
+    fn resolve(&self, year: i32) -> Option<NaiveDate> {
+        let date = match self.day {
+            Day::Fixed { month, day } => {
+                NaiveDate::from_ymd_opt(year, month, day)?
+            }
+            Day::Easter => easter(year)?,
+        };
+        date.checked_add_signed(Duration::days(i64::from(self.offset)))
+    }
+}
+
+impl Time {
+    /// Minutes since midnight, which may be more than a day for extended
+    /// times such as `26:00`
+    fn minutes(&self, date: NaiveDate, env: &Environment) -> u32 {
+        match self {
+            Time::Fixed(minutes) => *minutes,
+            Time::Event { event, offset } => {
+                (sun::local_minutes(*event, date, env) + offset).max(0) as u32
+            }
+        }
+    }
+}
+
+fn days_in_month(date: NaiveDate) -> u32 {
+    let (y, m) = (date.year(), date.month());
+    let next = if m == 12 {
+        NaiveDate::from_ymd_opt(y + 1, 1, 1)
+    } else {
+        NaiveDate::from_ymd_opt(y, m + 1, 1)
+    };
+    next.and_then(|n| n.pred_opt())
+        .map_or(31, |last| last.day())
+}
+
+/// Western Easter Sunday (anonymous Gregorian algorithm)
+fn easter(year: i32) -> Option<NaiveDate> {
+    let a = year % 19;
+    let b = year / 100;
+    let c = year % 100;
+    let d = b / 4;
+    let e = b % 4;
+    let f = (b + 8) / 25;
+    let g = (b - f + 1) / 3;
+    let h = (19 * a + b - d - g + 15) % 30;
+    let i = c / 4;
+    let k = c % 4;
+    let l = (32 + 2 * e + 2 * i - h - k) % 7;
+    let m = (a + 11 * h + 22 * l) / 451;
+    let month = (h + l - 7 * m + 114) / 31;
+    let day = (h + l - 7 * m + 114) % 31 + 1;
+
+    NaiveDate::from_ymd_opt(year, month as u32, day as u32)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(y: i32, m: u32, d: u32, h: u32, min: u32) -> NaiveDateTime {
+        NaiveDate::from_ymd_opt(y, m, d)
+            .unwrap()
+            .and_hms_opt(h, min, 0)
+            .unwrap()
+    }
+
+    fn hours(value: &str) -> OpeningHours {
+        value.parse().unwrap_or_else(|e| panic!("{}: {}", value, e))
+    }
+
+    /// Values taken from tagged features
+    static CORPUS: &[&str] = &[
+        "24/7",
+        "Mo-Fr 08:00-18:00",
+        "Mo-Fr 09:00-12:00,13:00-17:30",
+        "Mo-Sa 08:00-20:00; Su,PH off",
+        "Mo-Fr 08:00-18:00; Sa 09:00-13:00; PH off",
+        "Mo-Th 10:00-24:00; Fr 10:00-02:00; Sa 12:00-02:00; Su 12:00-22:00",
+        "Mo-Su 07:00-22:00",
+        "sunrise-sunset",
+        "Mo-Fr 06:00-(sunset-01:00)",
+        "(sunrise+00:30)-(sunset-00:30)",
+        "Apr-Oct Mo-Su 10:00-18:00; Nov-Mar Sa,Su 11:00-16:00",
+        "Jan-Mar: Mo-Fr 08:00-12:00",
+        "Dec 24-26 off; Dec 31 10:00-14:00",
+        "Dec 24-Jan 02 off",
+        "easter off",
+        "Su[1] 10:00-14:00",
+        "Mo[-1] 18:00-20:00",
+        "week 01-53/2 Fr 09:00-12:00",
+        "2021-2023 Jun-Aug Mo-Fr 08:00-20:00",
+        "Mo-Fr 08:00-12:00, We 14:00-18:00",
+        "Mo-Fr 08:00-18:00; We 12:00-14:00 off",
+        "Mo-Fr 10:00-20:00 || \"by appointment\"",
+        "Mo-Fr 17:00+",
+        "Mo-Fr 08:00-18:00 \"call ahead\"",
+        "SH Mo-Fr 10:00-14:00",
+        "Mo-Fr 12:00; Sa 10:00",
+        "unknown \"seasonal\"",
+        "Mo 10:00-12:00 open; Tu closed",
+        "Fr-Mo 22:00-04:00",
+        "PH 10:00-14:00",
+        "off",
+    ];
+
+    #[test]
+    fn parses_corpus() {
+        for value in CORPUS {
+            hours(value);
+        }
+    }
+
+    #[test]
+    fn rejects_invalid_values() {
+        for value in &["Mo-Xx 10:00", "25:61-26:00", "Mo-Fr 08:00-", "Mo;;"] {
+            assert!(value.parse::<OpeningHours>().is_err(), "{}", value);
+        }
+        for value in &["Su[-2147483648] 10:00", "easter +4000000000 days"] {
+            assert!(value.parse::<OpeningHours>().is_err(), "{}", value);
+        }
+    }
+
+    #[test]
+    fn huge_offsets_fall_outside_the_calendar() {
+        let oh = hours("easter +2147483647 days 10:00-12:00");
+        let noon = NaiveDate::from_ymd_opt(2020, 6, 1)
+            .unwrap()
+            .and_hms_opt(11, 0, 0)
+            .unwrap();
+
+        assert!(!oh.is_open(noon));
+        assert_eq!(oh.next_change(NaiveDateTime::MAX), None);
+    }
+
+    #[test]
+    fn normal_rules_override_days() {
+        let oh = hours("Mo-Fr 08:00-18:00; We 10:00-12:00");
+
+        // 2020-06-01 is a Monday
+        assert!(oh.is_open(at(2020, 6, 1, 9, 0)));
+        assert!(!oh.is_open(at(2020, 6, 3, 9, 0)));
+        assert!(oh.is_open(at(2020, 6, 3, 11, 0)));
+        assert!(!oh.is_open(at(2020, 6, 6, 11, 0)));
+    }
+
+    #[test]
+    fn additional_and_closed_rules() {
+        let oh = hours("Mo-Fr 08:00-12:00, We 14:00-18:00");
+        assert!(oh.is_open(at(2020, 6, 3, 9, 0)));
+        assert!(oh.is_open(at(2020, 6, 3, 15, 0)));
+
+        let oh = hours("Mo-Fr 08:00-18:00; We 12:00-14:00 off");
+        assert!(oh.is_open(at(2020, 6, 3, 9, 0)));
+        assert!(!oh.is_open(at(2020, 6, 3, 13, 0)));
+    }
+
+    #[test]
+    fn spans_past_midnight() {
+        let oh = hours("Fr 22:00-02:00");
+
+        assert!(oh.is_open(at(2020, 6, 5, 23, 0)));
+        assert!(oh.is_open(at(2020, 6, 6, 1, 0)));
+        assert!(!oh.is_open(at(2020, 6, 6, 3, 0)));
+    }
+
+    #[test]
+    fn open_ends_are_unknown() {
+        let env = Environment::default();
+        let oh = hours("Mo-Su 17:00+");
+        assert_eq!(oh.state(at(2020, 6, 1, 16, 0), &env), State::Closed);
+        assert_eq!(oh.state(at(2020, 6, 1, 20, 0), &env), State::Unknown);
+        assert_eq!(oh.state(at(2020, 6, 2, 1, 0), &env), State::Closed);
+
+        let oh = hours("Mo-Su 10:00-14:00+");
+        assert_eq!(oh.state(at(2020, 6, 1, 11, 0), &env), State::Open);
+        assert_eq!(oh.state(at(2020, 6, 1, 20, 0), &env), State::Unknown);
+    }
+
+    #[test]
+    fn holidays_and_dates() {
+        let oh = hours("Mo-Fr 08:00-18:00; PH off; Dec 25 off");
+        let mut env = Environment::default();
+        env.public_holidays
+            .insert(NaiveDate::from_ymd_opt(2020, 6, 1).unwrap());
+
+        assert_eq!(oh.state(at(2020, 6, 1, 9, 0), &env), State::Closed);
+        assert_eq!(oh.state(at(2020, 6, 2, 9, 0), &env), State::Open);
+        assert!(!oh.is_open(at(2020, 12, 25, 9, 0)));
+
+        let easter = hours("easter off; Mo-Su 10:00-12:00");
+        assert!(easter.is_open(at(2021, 4, 4, 11, 0)));
+        let easter = hours("Mo-Su 10:00-12:00; easter off");
+        assert!(!easter.is_open(at(2021, 4, 4, 11, 0)));
+    }
+
+    #[test]
+    fn nth_weekday_of_month() {
+        let oh = hours("Su[1] 10:00-14:00; Mo[-1] 18:00-20:00");
+
+        assert!(oh.is_open(at(2020, 6, 7, 11, 0)));
+        assert!(!oh.is_open(at(2020, 6, 14, 11, 0)));
+        assert!(oh.is_open(at(2020, 6, 29, 19, 0)));
+        assert!(!oh.is_open(at(2020, 6, 22, 19, 0)));
+    }
+
+    #[test]
+    fn fallback_and_comments() {
+        let oh = hours("Mo-Fr 10:00-20:00 || \"by appointment\"");
+        let env = Environment::default();
+
+        assert_eq!(oh.state(at(2020, 6, 1, 11, 0), &env), State::Open);
+        assert_eq!(oh.state(at(2020, 6, 6, 11, 0), &env), State::Unknown);
+        assert_eq!(
+            oh.comment(at(2020, 6, 6, 11, 0), &env),
+            Some("by appointment")
+        );
+    }
+
+    #[test]
+    fn sun_times_follow_location() {
+        let oh = hours("sunrise-sunset");
+        let boise = Environment {
+            location: Some((43.6, -116.2)),
+            utc_offset: Some(-6 * 60),
+            ..Default::default()
+        };
+
+        // midsummer sunrise in Boise is around 06:00 local daylight time
+        assert_eq!(oh.state(at(2020, 6, 21, 5, 30), &boise), State::Closed);
+        assert_eq!(oh.state(at(2020, 6, 21, 6, 30), &boise), State::Open);
+        assert_eq!(oh.state(at(2020, 6, 21, 21, 45), &boise), State::Closed);
+    }
+
+    #[test]
+    fn next_change() {
+        let oh = hours("Mo-Fr 08:00-18:00");
+
+        assert_eq!(
+            oh.next_change(at(2020, 6, 1, 9, 0)),
+            Some(at(2020, 6, 1, 18, 0))
+        );
+        assert_eq!(
+            oh.next_change(at(2020, 6, 5, 19, 0)),
+            Some(at(2020, 6, 8, 8, 0))
+        );
+        assert_eq!(hours("24/7").next_change(at(2020, 6, 1, 9, 0)), None);
+    }
+}
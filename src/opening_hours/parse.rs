@@ -0,0 +1,567 @@
+//! Recursive descent parser for the `opening_hours` grammar
+use super::{
+    DatePoint, Day, Holiday, MonthdayRange, ParseError, Rule, Selector,
+    Separator, State, SunEvent, Time, TimeSpan, WeekRange, WeekdayRange,
+    YearRange,
+};
+use std::convert::TryFrom;
+
+static WEEKDAYS: [&str; 7] = ["Mo", "Tu", "We", "Th", "Fr", "Sa", "Su"];
+
+static MONTHS: [&str; 12] = [
+    "Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct",
+    "Nov", "Dec",
+];
+
+static EVENTS: [(&str, SunEvent); 4] = [
+    ("dawn", SunEvent::Dawn),
+    ("sunrise", SunEvent::Sunrise),
+    ("sunset", SunEvent::Sunset),
+    ("dusk", SunEvent::Dusk),
+];
+
+pub(super) fn parse(text: &str) -> Result<Vec<Rule>, ParseError> {
+    let mut parser = Parser { text, pos: 0 };
+    let mut rules = Vec::new();
+    let mut separator = Separator::Normal;
+
+    loop {
+        parser.skip_ws();
+        rules.push(parser.rule(separator)?);
+        parser.skip_ws();
+        if parser.at_end() {
+            break;
+        }
+        separator = if parser.eat("||") {
+            Separator::Fallback
+        } else if parser.eat(";") {
+            Separator::Normal
+        } else if parser.eat(",") {
+            Separator::Additional
+        } else {
+            return Err(parser.error("expected `;`, `,` or `||`"));
+        };
+        parser.skip_ws();
+        // tolerate a trailing `;`
+        if parser.at_end() && separator == Separator::Normal {
+            break;
+        }
+    }
+    Ok(rules)
+}
+
+struct Parser<'t> {
+    text: &'t str,
+    pos: usize,
+}
+
+impl<'t> Parser<'t> {
+    fn rule(&mut self, separator: Separator) -> Result<Rule, ParseError> {
+        let start = self.pos;
+        let mut selector = Selector::default();
+
+        if !self.eat("24/7") {
+            selector.years = self.years()?;
+            self.skip_ws();
+            selector.monthdays = self.monthdays()?;
+            self.skip_ws();
+            if self.eat_word("week") {
+                self.skip_ws();
+                selector.weeks = self.weeks()?;
+            }
+            self.skip_ws();
+            let wide = !selector.years.is_empty()
+                || !selector.monthdays.is_empty()
+                || !selector.weeks.is_empty();
+            if wide && self.eat(":") {
+                self.skip_ws();
+            }
+            self.days(&mut selector)?;
+            self.skip_ws();
+            selector.times = self.times()?;
+        }
+        self.skip_ws();
+        let state = self.modifier();
+        self.skip_ws();
+        let comment = self.comment()?;
+
+        if self.pos == start {
+            return Err(self.error("expected a rule"));
+        }
+        let state = match state {
+            Some(state) => state,
+            // a rule of just a comment, such as "by appointment"
+            None if comment.is_some() && selector == Selector::default() => {
+                State::Unknown
+            }
+            None => State::Open,
+        };
+        Ok(Rule {
+            separator,
+            selector,
+            state,
+            comment,
+        })
+    }
+
+    fn years(&mut self) -> Result<Vec<YearRange>, ParseError> {
+        let mut years = Vec::new();
+
+        while self.starts_year() {
+            let start = self.number()? as i32;
+            let mut range = YearRange {
+                start,
+                end: Some(start),
+                step: 1,
+            };
+            if self.eat("+") {
+                range.end = None;
+            } else if self.eat("-") {
+                range.end = Some(self.number()? as i32);
+                if self.eat("/") {
+                    range.step = self.number()?;
+                }
+            }
+            years.push(range);
+            if !self.list_continues(Self::starts_year) {
+                break;
+            }
+        }
+        Ok(years)
+    }
+
+    fn monthdays(&mut self) -> Result<Vec<MonthdayRange>, ParseError> {
+        let mut ranges = Vec::new();
+
+        loop {
+            let range = if let Some(month) = self.month() {
+                self.month_range(month)?
+            } else if self.eat_word("easter") {
+                let start = DatePoint {
+                    day: Day::Easter,
+                    offset: self.day_offset()?,
+                };
+                MonthdayRange::Dates { start, end: start }
+            } else {
+                break;
+            };
+            ranges.push(range);
+            if !self.list_continues(Self::starts_monthday) {
+                break;
+            }
+        }
+        Ok(ranges)
+    }
+
+    /// Range starting with the month, `Jan`, `Jan-Mar`, `Dec 24-26` or
+    /// `Dec 24-Jan 02`
+    fn month_range(&mut self, month: u32) -> Result<MonthdayRange, ParseError> {
+        let save = self.pos;
+        self.skip_ws();
+        let day = match self.day() {
+            Some(day) => day,
+            None => {
+                self.pos = save;
+                let end = if self.eat("-") {
+                    self.month().ok_or_else(|| self.error("expected month"))?
+                } else {
+                    month
+                };
+                return Ok(MonthdayRange::Months { start: month, end });
+            }
+        };
+        let start = fixed(month, day);
+        let end = if self.eat("-") {
+            if let Some(end_month) = self.month() {
+                self.skip_ws();
+                let day =
+                    self.day().ok_or_else(|| self.error("expected day"))?;
+                fixed(end_month, day)
+            } else if let Some(day) = self.day() {
+                fixed(month, day)
+            } else {
+                return Err(self.error("expected month or day"));
+            }
+        } else {
+            start
+        };
+        Ok(MonthdayRange::Dates { start, end })
+    }
+
+    /// Optional ` +2 days` or ` -1 day` after a date
+    fn day_offset(&mut self) -> Result<i32, ParseError> {
+        let save = self.pos;
+        self.skip_ws();
+        let sign = if self.eat("+") {
+            1
+        } else if self.eat("-") {
+            -1
+        } else {
+            self.pos = save;
+            return Ok(0);
+        };
+        self.skip_ws();
+        let days = self.int()?;
+        self.skip_ws();
+        if !(self.eat_word("days") || self.eat_word("day")) {
+            return Err(self.error("expected `day` or `days`"));
+        }
+        Ok(sign * days)
+    }
+
+    fn weeks(&mut self) -> Result<Vec<WeekRange>, ParseError> {
+        let mut weeks = Vec::new();
+
+        loop {
+            let start = self.number()?;
+            let mut range = WeekRange {
+                start,
+                end: start,
+                step: 1,
+            };
+            if self.eat("-") {
+                range.end = self.number()?;
+                if self.eat("/") {
+                    range.step = self.number()?;
+                }
+            }
+            if range.start < 1 || range.end > 53 {
+                return Err(self.error("weeks are numbered 1 to 53"));
+            }
+            weeks.push(range);
+            if !self.list_continues(Self::starts_number) {
+                break;
+            }
+        }
+        Ok(weeks)
+    }
+
+    /// Weekdays and holidays, `Mo-Fr`, `Sa,Su,PH`, `Su[1]` or `SH Mo-Fr`
+    fn days(&mut self, selector: &mut Selector) -> Result<(), ParseError> {
+        loop {
+            if self.eat_word("PH") {
+                selector.holidays.push(Holiday::Public);
+            } else if self.eat_word("SH") {
+                selector.holidays.push(Holiday::School);
+            } else if let Some(start) = self.weekday() {
+                let range = self.weekday_range(start)?;
+                selector.weekdays.push(range);
+            } else {
+                return Ok(());
+            }
+            if self.list_continues(Self::starts_day) {
+                continue;
+            }
+            // holidays followed by weekdays must match both
+            let save = self.pos;
+            self.skip_ws();
+            if selector.weekdays.is_empty()
+                && !selector.holidays.is_empty()
+                && self.starts_weekday()
+            {
+                selector.holidays_and_weekdays = true;
+                continue;
+            }
+            self.pos = save;
+            return Ok(());
+        }
+    }
+
+    fn weekday_range(
+        &mut self,
+        start: u32,
+    ) -> Result<WeekdayRange, ParseError> {
+        let end = if self.eat("-") {
+            self.weekday()
+                .ok_or_else(|| self.error("expected weekday"))?
+        } else {
+            start
+        };
+        let mut nth = Vec::new();
+
+        if self.eat("[") {
+            loop {
+                let a = self.signed_number()?;
+                let b = if self.eat("-") {
+                    self.signed_number()?
+                } else {
+                    a
+                };
+                if a == 0 || b == 0 || a.abs() > 5 || b.abs() > 5 {
+                    return Err(self.error("expected week of month 1 to 5"));
+                }
+                nth.push((a, b));
+                if !self.eat(",") {
+                    break;
+                }
+            }
+            if !self.eat("]") {
+                return Err(self.error("expected `]`"));
+            }
+        }
+        Ok(WeekdayRange { start, end, nth })
+    }
+
+    fn times(&mut self) -> Result<Vec<TimeSpan>, ParseError> {
+        let mut spans = Vec::new();
+
+        while self.starts_time() {
+            let mut span = TimeSpan {
+                start: self.time()?,
+                end: None,
+                open_end: false,
+            };
+            if self.eat("-") {
+                span.end = Some(self.time()?);
+                // repeating times are treated as the whole span
+                if self.eat("/") {
+                    if self.starts_clock() {
+                        self.clock()?;
+                    } else {
+                        self.number()?;
+                    }
+                }
+            }
+            span.open_end = self.eat("+");
+            spans.push(span);
+            if !self.list_continues(Self::starts_time) {
+                break;
+            }
+        }
+        Ok(spans)
+    }
+
+    fn time(&mut self) -> Result<Time, ParseError> {
+        if self.eat("(") {
+            let event = self
+                .event()
+                .ok_or_else(|| self.error("expected sun event"))?;
+            let sign = if self.eat("+") {
+                1
+            } else if self.eat("-") {
+                -1
+            } else {
+                return Err(self.error("expected `+` or `-`"));
+            };
+            let offset = sign * self.clock()? as i32;
+            if !self.eat(")") {
+                return Err(self.error("expected `)`"));
+            }
+            Ok(Time::Event { event, offset })
+        } else if let Some(event) = self.event() {
+            Ok(Time::Event { event, offset: 0 })
+        } else {
+            Ok(Time::Fixed(self.clock()?))
+        }
+    }
+
+    /// `hh:mm` in minutes, up to `48:00` for times extending into the next
+    /// day
+    fn clock(&mut self) -> Result<u32, ParseError> {
+        let start = self.pos;
+        let hours = self.number()?;
+        if !self.eat(":") {
+            return Err(self.error("expected `:`"));
+        }
+        let digits = self.pos;
+        let minutes = self.number()?;
+        if self.pos - digits != 2 || minutes > 59 || hours > 48 {
+            self.pos = start;
+            return Err(self.error("expected time from 00:00 to 48:00"));
+        }
+        Ok(hours * 60 + minutes)
+    }
+
+    fn modifier(&mut self) -> Option<State> {
+        if self.eat_word("open") {
+            Some(State::Open)
+        } else if self.eat_word("closed") || self.eat_word("off") {
+            Some(State::Closed)
+        } else if self.eat_word("unknown") {
+            Some(State::Unknown)
+        } else {
+            None
+        }
+    }
+
+    fn comment(&mut self) -> Result<Option<String>, ParseError> {
+        if !self.eat("\"") {
+            return Ok(None);
+        }
+        match self.rest().find('"') {
+            Some(end) => {
+                let comment = self.rest()[..end].to_owned();
+                self.pos += end + 1;
+                Ok(Some(comment))
+            }
+            None => Err(self.error("unterminated comment")),
+        }
+    }
+
+    fn month(&mut self) -> Option<u32> {
+        let index = MONTHS.iter().position(|m| self.starts_word(m))?;
+        self.pos += 3;
+        Some(index as u32 + 1)
+    }
+
+    fn weekday(&mut self) -> Option<u32> {
+        let index = WEEKDAYS.iter().position(|d| self.starts_word(d))?;
+        self.pos += 2;
+        Some(index as u32)
+    }
+
+    fn event(&mut self) -> Option<SunEvent> {
+        let (name, event) = EVENTS.iter().find(|(e, _)| self.starts_word(e))?;
+        self.pos += name.len();
+        Some(*event)
+    }
+
+    /// Day of the month, which unlike the hour of a time isn't followed by
+    /// `:`
+    fn day(&mut self) -> Option<u32> {
+        let digits = self.digits();
+        if digits == 0 || digits > 2 || self.rest()[digits..].starts_with(':') {
+            return None;
+        }
+        let day = self.rest()[..digits].parse().ok()?;
+        if !(1..=31).contains(&day) {
+            return None;
+        }
+        self.pos += digits;
+        Some(day)
+    }
+
+    fn number(&mut self) -> Result<u32, ParseError> {
+        let digits = self.digits();
+        let number = self.rest()[..digits]
+            .parse()
+            .map_err(|_| self.error("expected number"))?;
+        self.pos += digits;
+        Ok(number)
+    }
+
+    /// Number small enough to take part in signed arithmetic
+    fn int(&mut self) -> Result<i32, ParseError> {
+        let start = self.pos;
+        let number = self.number()?;
+        i32::try_from(number).map_err(|_| ParseError {
+            position: start,
+            message: "number out of range".to_owned(),
+        })
+    }
+
+    fn signed_number(&mut self) -> Result<i32, ParseError> {
+        let sign = if self.eat("-") { -1 } else { 1 };
+        Ok(sign * self.int()?)
+    }
+
+    /// After an item of a list, consume a `,` if it's followed by another
+    /// item of the list rather than a new rule
+    fn list_continues(&mut self, starts_item: fn(&Self) -> bool) -> bool {
+        let save = self.pos;
+        self.skip_ws();
+        if self.eat(",") {
+            self.skip_ws();
+            if starts_item(self) {
+                return true;
+            }
+        }
+        self.pos = save;
+        false
+    }
+
+    fn starts_year(&self) -> bool {
+        let rest = self.rest().as_bytes();
+        rest.len() >= 4
+            && rest[..4].iter().all(u8::is_ascii_digit)
+            && rest
+                .get(4)
+                .is_none_or(|c| !c.is_ascii_digit() && *c != b':')
+    }
+
+    fn starts_monthday(&self) -> bool {
+        MONTHS.iter().any(|m| self.starts_word(m)) || self.starts_word("easter")
+    }
+
+    fn starts_number(&self) -> bool {
+        self.digits() > 0
+    }
+
+    fn starts_weekday(&self) -> bool {
+        WEEKDAYS.iter().any(|d| self.starts_word(d))
+    }
+
+    fn starts_day(&self) -> bool {
+        self.starts_word("PH")
+            || self.starts_word("SH")
+            || self.starts_weekday()
+    }
+
+    fn starts_clock(&self) -> bool {
+        let digits = self.digits();
+        digits > 0 && self.rest()[digits..].starts_with(':')
+    }
+
+    fn starts_time(&self) -> bool {
+        self.starts_clock()
+            || self.rest().starts_with('(')
+            || EVENTS.iter().any(|(e, _)| self.starts_word(e))
+    }
+
+    /// Whether the word is next and not part of a longer word
+    fn starts_word(&self, word: &str) -> bool {
+        let rest = self.rest();
+        rest.starts_with(word)
+            && !rest[word.len()..]
+                .chars()
+                .next()
+                .is_some_and(char::is_alphabetic)
+    }
+
+    fn eat_word(&mut self, word: &str) -> bool {
+        let found = self.starts_word(word);
+        if found {
+            self.pos += word.len();
+        }
+        found
+    }
+
+    fn eat(&mut self, token: &str) -> bool {
+        let found = self.rest().starts_with(token);
+        if found {
+            self.pos += token.len();
+        }
+        found
+    }
+
+    fn digits(&self) -> usize {
+        self.rest().bytes().take_while(u8::is_ascii_digit).count()
+    }
+
+    fn skip_ws(&mut self) {
+        let rest = self.rest();
+        self.pos += rest.len() - rest.trim_start().len();
+    }
+
+    fn at_end(&self) -> bool {
+        self.pos >= self.text.len()
+    }
+
+    fn rest(&self) -> &'t str {
+        &self.text[self.pos..]
+    }
+
+    fn error(&self, message: &str) -> ParseError {
+        ParseError {
+            position: self.pos,
+            message: message.to_owned(),
+        }
+    }
+}
+
+fn fixed(month: u32, day: u32) -> DatePoint {
+    DatePoint {
+        day: Day::Fixed { month, day },
+        offset: 0,
+    }
+}
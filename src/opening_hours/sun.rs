@@ -0,0 +1,62 @@
+//! Sun event times using the NOAA approximation
+//!
+//! https://gml.noaa.gov/grad/solcalc/solareqns.PDF
+use super::{Environment, SunEvent};
+use chrono::{Datelike, NaiveDate};
+use std::f64::consts::PI;
+
+/// Minutes since local midnight of the event on the date. Without a location
+/// dawn, sunrise, sunset and dusk are 05:30, 06:00, 18:00 and 18:30.
+pub(super) fn local_minutes(
+    event: SunEvent,
+    date: NaiveDate,
+    env: &Environment,
+) -> i32 {
+    let (lat, lon) = match env.location {
+        Some((lat, lon)) => (f64::from(lat), f64::from(lon)),
+        None => {
+            return match event {
+                SunEvent::Dawn => 5 * 60 + 30,
+                SunEvent::Sunrise => 6 * 60,
+                SunEvent::Sunset => 18 * 60,
+                SunEvent::Dusk => 18 * 60 + 30,
+            }
+        }
+    };
+    let utc_offset = env
+        .utc_offset
+        .map_or((lon / 15.0).round() * 60.0, f64::from);
+
+    let gamma = 2.0 * PI / 365.0 * f64::from(date.ordinal0());
+    let eqtime = 229.18
+        * (0.000075 + 0.001868 * gamma.cos()
+            - 0.032077 * gamma.sin()
+            - 0.014615 * (2.0 * gamma).cos()
+            - 0.040849 * (2.0 * gamma).sin());
+    let decl = 0.006918 - 0.399912 * gamma.cos() + 0.070257 * gamma.sin()
+        - 0.006758 * (2.0 * gamma).cos()
+        + 0.000907 * (2.0 * gamma).sin()
+        - 0.002697 * (3.0 * gamma).cos()
+        + 0.00148 * (3.0 * gamma).sin();
+
+    // civil twilight ends when the sun is 6° below the horizon
+    let zenith: f64 = match event {
+        SunEvent::Sunrise | SunEvent::Sunset => 90.833,
+        SunEvent::Dawn | SunEvent::Dusk => 96.0,
+    };
+    let lat = lat.to_radians();
+    let cos_hour_angle = zenith.to_radians().cos() / (lat.cos() * decl.cos())
+        - lat.tan() * decl.tan();
+    // polar day and night have no event; clamp to midnight or noon
+    let hour_angle = cos_hour_angle.clamp(-1.0, 1.0).acos().to_degrees();
+
+    let utc = match event {
+        SunEvent::Dawn | SunEvent::Sunrise => {
+            720.0 - 4.0 * (lon + hour_angle) - eqtime
+        }
+        SunEvent::Sunset | SunEvent::Dusk => {
+            720.0 - 4.0 * (lon - hour_angle) - eqtime
+        }
+    };
+    (utc + utc_offset).round() as i32
+}
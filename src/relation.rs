@@ -1,14 +1,27 @@
-use crate::{node::Node, tag::Tagged, ElementID, TagMap, Timestamp};
+use crate::{item_type, node::Node, tag::Tagged, ElementID, TagMap, Timestamp};
 use serde::Deserialize;
 
 /// Relation member entry may refer to a single `Node` or a `Way` of nodes
 #[derive(Default, Deserialize)]
 pub struct Member<'a> {
+    /// ID of the member element
+    #[serde(rename = "ref")]
+    pub id: ElementID,
+    /// Type of the member element, one of the `item_type` values
+    #[serde(rename = "type")]
+    pub member_type: &'static str,
     pub nodes: Vec<&'a Node>,
     /// https://wiki.openstreetmap.org/wiki/Relation#Roles
     pub role: &'static str,
 }
 
+impl<'a> Member<'a> {
+    /// Whether the member is the way with the given ID
+    pub fn is_way(&self, id: ElementID) -> bool {
+        self.id == id && self.member_type == item_type::WAY
+    }
+}
+
 /// A **relation** is a group of [elements](https://wiki.openstreetmap.org/wiki/Elements).
 /// To be more exact it is one of the core data elements that consists of one or
 /// more [tags](https://wiki.openstreetmap.org/wiki/Tags) and also an **ordered
@@ -31,7 +44,7 @@ pub struct Relation<'a> {
 
 impl<'a> Tagged for Relation<'a> {
     fn get_tag(&self, key: &str) -> Option<&str> {
-        self.tags.get(key).map(|value| *value)
+        self.tags.get(key)
     }
 
     fn has_tag(&self, key: &str) -> bool {
@@ -0,0 +1,800 @@
+//! Typed [turn restrictions](https://wiki.openstreetmap.org/wiki/Relation:restriction)
+//! built from relations tagged `type=restriction`.
+//!
+//! A restriction leads `from` a way, `via` a node or a chain of ways, `to`
+//! another way. It may be limited to some modes with `restriction:<mode>`,
+//! exempt others with `except`, and apply only at certain times with
+//! `restriction:conditional` (or `restriction:<mode>:conditional`) or the
+//! older `day_on`/`day_off` and `hour_on`/`hour_off` tags.
+use crate::{
+    conditional::{self, Conditional, Context},
+    item_type,
+    opening_hours::OpeningHours,
+    relation::{role, Relation},
+    tag::{restriction, travel_by::Mode, Tagged, TYPE},
+    AreaData, ElementID,
+};
+use std::{error, fmt};
+
+pub static DAY_ON: &'static str = "day_on";
+pub static DAY_OFF: &'static str = "day_off";
+pub static HOUR_ON: &'static str = "hour_on";
+pub static HOUR_OFF: &'static str = "hour_off";
+
+static DAYS: [(&'static str, &'static str); 7] = [
+    ("Monday", "Mo"),
+    ("Tuesday", "Tu"),
+    ("Wednesday", "We"),
+    ("Thursday", "Th"),
+    ("Friday", "Fr"),
+    ("Saturday", "Sa"),
+    ("Sunday", "Su"),
+];
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum RestrictionError {
+    /// The relation isn't tagged `type=restriction`
+    NotARestriction,
+    /// No `restriction`, `restriction:<mode>` or `restriction:conditional`
+    MissingValue,
+    UnknownValue(String),
+    /// Different values for `restriction`, `restriction:<mode>` and their
+    /// conditional tags, which would need a restriction for each
+    ConflictingValues(Vec<String>),
+    /// An `except` value or `restriction:<mode>` key isn't a transport mode
+    UnknownMode(String),
+    /// Day and hour tags that don't form a valid time
+    InvalidSchedule(String),
+    MissingMember(&'static str),
+    /// More members with the role than the restriction allows, such as two
+    /// `from` ways on a turn other than `no_entry`
+    MultipleMembers(&'static str),
+    /// A `from` or `to` member that isn't a way
+    NotAWay(ElementID),
+    /// `via` members that aren't a single node or only ways
+    InvalidVia,
+    /// A member way missing from the area data
+    MissingWay(ElementID),
+    /// Consecutive members that don't share an end node
+    NotConnected(ElementID, ElementID),
+}
+
+impl fmt::Display for RestrictionError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            RestrictionError::NotARestriction => {
+                write!(f, "relation isn't a turn restriction")
+            }
+            RestrictionError::MissingValue => {
+                write!(f, "turn restriction has no restriction value")
+            }
+            RestrictionError::UnknownValue(value) => {
+                write!(f, "unknown turn restriction {}", value)
+            }
+            RestrictionError::ConflictingValues(values) => write!(
+                f,
+                "turn restriction has conflicting values {}",
+                values.join(", ")
+            ),
+            RestrictionError::UnknownMode(mode) => {
+                write!(f, "unknown transport mode {}", mode)
+            }
+            RestrictionError::InvalidSchedule(hours) => {
+                write!(f, "invalid restriction times {}", hours)
+            }
+            RestrictionError::MissingMember(role) => {
+                write!(f, "turn restriction has no {} member", role)
+            }
+            RestrictionError::MultipleMembers(role) => {
+                write!(f, "turn restriction has more than one {} member", role)
+            }
+            RestrictionError::NotAWay(id) => {
+                write!(f, "member {} of turn restriction isn't a way", id)
+            }
+            RestrictionError::InvalidVia => write!(
+                f,
+                "via members must be a single node or one or more ways"
+            ),
+            RestrictionError::MissingWay(id) => {
+                write!(f, "way {} isn't loaded", id)
+            }
+            RestrictionError::NotConnected(a, b) => {
+                write!(f, "members {} and {} don't share an end node", a, b)
+            }
+        }
+    }
+}
+
+impl error::Error for RestrictionError {}
+
+/// Whether the restriction forbids the turn or makes it the only one allowed
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Kind {
+    /// `no_*`
+    Prohibitory,
+    /// `only_*`
+    Mandatory,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Turn {
+    Left,
+    Right,
+    Straight,
+    UTurn,
+    /// `no_entry`: no way from any of several `from` ways into the `to` way
+    Entry,
+    /// `no_exit`: no way from the `from` way into any of several `to` ways
+    Exit,
+}
+
+/// Kind and turn of a `restriction` value such as `no_left_turn`
+pub fn parse_value(value: &str) -> Option<(Kind, Turn)> {
+    Some(match value {
+        "no_left_turn" => (Kind::Prohibitory, Turn::Left),
+        "no_right_turn" => (Kind::Prohibitory, Turn::Right),
+        "no_straight_on" => (Kind::Prohibitory, Turn::Straight),
+        "no_u_turn" => (Kind::Prohibitory, Turn::UTurn),
+        "no_entry" => (Kind::Prohibitory, Turn::Entry),
+        "no_exit" => (Kind::Prohibitory, Turn::Exit),
+        "only_left_turn" => (Kind::Mandatory, Turn::Left),
+        "only_right_turn" => (Kind::Mandatory, Turn::Right),
+        "only_straight_on" => (Kind::Mandatory, Turn::Straight),
+        "only_u_turn" => (Kind::Mandatory, Turn::UTurn),
+        _ => return None,
+    })
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Via {
+    Node(ElementID),
+    /// Ways in order from the `from` way to the `to` way
+    Ways(Vec<ElementID>),
+}
+
+#[derive(Clone, Debug)]
+pub struct TurnRestriction {
+    /// ID of the relation
+    pub id: ElementID,
+    pub kind: Kind,
+    pub turn: Turn,
+    /// Ways the turn starts from, more than one only for `no_entry`
+    pub from: Vec<ElementID>,
+    pub via: Via,
+    /// Ways the turn leads to, more than one only for `no_exit`
+    pub to: Vec<ElementID>,
+    /// Modes the restriction is limited to by `restriction:<mode>`, empty
+    /// when it applies to every mode
+    pub modes: Vec<Mode>,
+    /// Modes exempt from the restriction
+    pub except: Vec<Mode>,
+    /// Times from `day_on`, `day_off`, `hour_on` and `hour_off`
+    pub schedule: Option<OpeningHours>,
+    /// Parsed `restriction:conditional`
+    pub condition: Option<Conditional>,
+    /// Whether a plain `restriction` value applies outside the condition
+    unconditional: bool,
+}
+
+impl TurnRestriction {
+    /// Restriction of the relation, checking its tags and member roles.
+    /// Whether the members connect is checked by `validate`.
+    pub fn from_relation(
+        relation: &Relation,
+    ) -> Result<TurnRestriction, Vec<RestrictionError>> {
+        use RestrictionError::*;
+
+        if relation.get_tag(TYPE) != Some(restriction::KEY) {
+            return Err(vec![NotARestriction]);
+        }
+        let mut errors = Vec::new();
+        let (value, mut modes) = restriction_value(relation, &mut errors);
+        let (condition, condition_modes) =
+            conditional_value(relation, &mut errors);
+        if value.is_none() {
+            modes = condition_modes;
+        } else if !condition_modes.is_empty() && condition_modes != modes {
+            // a condition limited to other modes than the value it changes
+            let values = value
+                .iter()
+                .map(String::as_str)
+                .chain(
+                    conditional_keys(relation)
+                        .filter_map(|key| relation.get_tag(key)),
+                )
+                .map(str::to_owned)
+                .collect();
+            errors.push(ConflictingValues(values));
+        }
+
+        // a restriction that only applies under a condition takes its turn
+        // from the conditional value
+        let unconditional = value.is_some();
+        let value = value.or_else(|| {
+            condition
+                .as_ref()
+                .and_then(|c| c.values.first())
+                .map(|v| v.value.clone())
+        });
+        let (kind, turn) = match value.as_deref().map(|v| (v, parse_value(v))) {
+            Some((v, Some(parsed))) => {
+                // every conditional value must lift or impose the same turn
+                let mut values: Vec<&str> = condition
+                    .iter()
+                    .flat_map(|c| c.values.iter())
+                    .map(|c| c.value.as_str())
+                    .filter(|c| parse_value(c).is_some())
+                    .chain(std::iter::once(v))
+                    .collect();
+                values.sort_unstable();
+                values.dedup();
+                if values.len() > 1 {
+                    errors.push(ConflictingValues(
+                        values.into_iter().map(str::to_owned).collect(),
+                    ));
+                }
+                parsed
+            }
+            Some((v, None)) => {
+                errors.push(UnknownValue(v.to_owned()));
+                (Kind::Prohibitory, Turn::Left)
+            }
+            None => {
+                errors.push(MissingValue);
+                (Kind::Prohibitory, Turn::Left)
+            }
+        };
+
+        let except = relation
+            .get_tag(restriction::EXCEPTION)
+            .map(|value| parse_modes(value, &mut errors))
+            .unwrap_or_default();
+        let schedule = schedule(relation).unwrap_or_else(|e| {
+            errors.push(e);
+            None
+        });
+
+        let from = way_members(relation, role::FROM, &mut errors);
+        let to = way_members(relation, role::TO, &mut errors);
+        let via = via_member(relation, &mut errors);
+
+        if from.len() > 1 && turn != Turn::Entry {
+            errors.push(MultipleMembers(role::FROM));
+        }
+        if to.len() > 1 && turn != Turn::Exit {
+            errors.push(MultipleMembers(role::TO));
+        }
+
+        match via {
+            Some(via) if errors.is_empty() => Ok(TurnRestriction {
+                id: relation.id,
+                kind,
+                turn,
+                from,
+                via,
+                to,
+                modes,
+                except,
+                schedule,
+                condition,
+                unconditional,
+            }),
+            _ => Err(errors),
+        }
+    }
+
+    /// Whether the restriction only applies at some times or to some
+    /// vehicles
+    pub fn is_conditional(&self) -> bool {
+        self.schedule.is_some() || self.condition.is_some()
+    }
+
+    /// Whether the restriction applies to the mode and, for conditional
+    /// restrictions, in the context. Without a context only unconditional
+    /// restrictions apply.
+    pub fn applies(&self, mode: Mode, context: Option<&Context>) -> bool {
+        let limited =
+            |modes: &[Mode]| mode.ancestors().any(|m| modes.contains(&m));
+
+        if limited(&self.except)
+            || (!self.modes.is_empty() && !limited(&self.modes))
+        {
+            return false;
+        }
+        let context = match context {
+            Some(context) => context,
+            None => return !self.is_conditional(),
+        };
+        if let Some(schedule) = &self.schedule {
+            if !schedule.is_open(context.time) {
+                return false;
+            }
+        }
+        match &self.condition {
+            Some(condition) => match condition.value(context) {
+                Some(value) => parse_value(value).is_some(),
+                None => self.unconditional,
+            },
+            None => true,
+        }
+    }
+
+    /// Check that `from`, `via` and `to` connect end to end
+    pub fn validate(&self, area: &AreaData) -> Vec<RestrictionError> {
+        let mut errors = Vec::new();
+        let mut ends = |id: ElementID| match area.ways.get(&id) {
+            Some(way) => way
+                .first_node()
+                .zip(way.last_node())
+                .map(|(a, b)| [a.id, b.id]),
+            None => {
+                errors.push(RestrictionError::MissingWay(id));
+                None
+            }
+        };
+
+        let from: Vec<_> =
+            self.from.iter().map(|id| (*id, ends(*id))).collect();
+        let to: Vec<_> = self.to.iter().map(|id| (*id, ends(*id))).collect();
+        let via: Vec<_> = match &self.via {
+            Via::Node(node) => vec![(*node, Some([*node, *node]))],
+            Via::Ways(ways) => ways.iter().map(|id| (*id, ends(*id))).collect(),
+        };
+
+        let mut chains: Vec<Vec<_>> = Vec::new();
+        for start in &from {
+            let mut chain = vec![*start];
+            chain.extend(via.iter().copied());
+            chains.push(chain);
+        }
+        for end in &to {
+            let mut chain = via.clone();
+            chain.push(*end);
+            chains.push(chain);
+        }
+
+        for chain in chains {
+            for pair in chain.windows(2) {
+                if let ((a, Some(a_ends)), (b, Some(b_ends))) =
+                    (pair[0], pair[1])
+                {
+                    if !a_ends.iter().any(|n| b_ends.contains(n)) {
+                        let error = RestrictionError::NotConnected(a, b);
+                        if !errors.contains(&error) {
+                            errors.push(error);
+                        }
+                    }
+                }
+            }
+        }
+        errors
+    }
+}
+
+/// Plain or mode-specific restriction value and the modes it's limited to.
+/// Modes with different values, or a plain value alongside a different
+/// mode-specific one, are reported as conflicting.
+fn restriction_value(
+    relation: &Relation,
+    errors: &mut Vec<RestrictionError>,
+) -> (Option<String>, Vec<Mode>) {
+    let prefix = format!("{}:", restriction::KEY);
+    let mut limited = Vec::new();
+
+    for (key, value) in &relation.tags {
+        let suffix = match key.strip_prefix(&prefix) {
+            Some(suffix) if !key.ends_with(conditional::SUFFIX) => suffix,
+            _ => continue,
+        };
+        match suffix.parse::<Mode>() {
+            Ok(mode) => limited.push((suffix, mode, value)),
+            Err(_) => errors.push(RestrictionError::UnknownMode(suffix.into())),
+        }
+    }
+    // tags come in no particular order
+    limited.sort_unstable_by_key(|(suffix, _, _)| *suffix);
+
+    let plain = relation.get_tag(restriction::KEY);
+    let mut values: Vec<&str> = plain
+        .into_iter()
+        .chain(limited.iter().map(|(_, _, value)| *value))
+        .collect();
+    values.sort_unstable();
+    values.dedup();
+    if values.len() > 1 {
+        let values = values.into_iter().map(str::to_owned).collect();
+        errors.push(RestrictionError::ConflictingValues(values));
+    }
+
+    match plain {
+        Some(value) => (Some(value.to_owned()), Vec::new()),
+        None => (
+            limited.first().map(|(_, _, value)| value.to_string()),
+            limited.into_iter().map(|(_, mode, _)| mode).collect(),
+        ),
+    }
+}
+
+/// Conditional restriction and the modes it's limited to, read from
+/// `restriction:conditional` or `restriction:<mode>:conditional`. Different
+/// conditions for different modes are reported as conflicting.
+fn conditional_value(
+    relation: &Relation,
+    errors: &mut Vec<RestrictionError>,
+) -> (Option<Conditional>, Vec<Mode>) {
+    let mut plain = false;
+    let mut limited = Vec::new();
+    let mut values: Vec<&str> = Vec::new();
+
+    for key in conditional_keys(relation) {
+        let middle =
+            &key[restriction::KEY.len()..key.len() - conditional::SUFFIX.len()];
+        match middle.strip_prefix(':') {
+            None => plain = true,
+            Some(suffix) => match suffix.parse::<Mode>() {
+                Ok(mode) => limited.push((suffix, mode)),
+                Err(_) => {
+                    errors.push(RestrictionError::UnknownMode(suffix.into()))
+                }
+            },
+        }
+        values.extend(relation.get_tag(key));
+    }
+    values.sort_unstable();
+    values.dedup();
+    if values.len() > 1 {
+        let values = values.into_iter().map(str::to_owned).collect();
+        errors.push(RestrictionError::ConflictingValues(values));
+        return (None, Vec::new());
+    }
+    if plain {
+        limited.clear();
+    }
+    // tags come in no particular order
+    limited.sort_unstable_by_key(|(suffix, _)| *suffix);
+    let modes = limited.into_iter().map(|(_, mode)| mode).collect();
+
+    let condition = values.first().and_then(|value| value.parse().ok());
+    (condition, modes)
+}
+
+/// `restriction:conditional` and `restriction:<mode>:conditional` keys of the
+/// relation
+fn conditional_keys<'r>(
+    relation: &'r Relation,
+) -> impl Iterator<Item = &'r str> + 'r {
+    relation.tags.iter().map(|(key, _)| key).filter(|key| {
+        key.strip_prefix(restriction::KEY)
+            .is_some_and(|rest| rest.starts_with(':'))
+            && key.ends_with(conditional::SUFFIX)
+    })
+}
+
+fn parse_modes(value: &str, errors: &mut Vec<RestrictionError>) -> Vec<Mode> {
+    value
+        .split(';')
+        .map(str::trim)
+        .filter_map(|key| match key.parse() {
+            Ok(mode) => Some(mode),
+            Err(_) => {
+                errors.push(RestrictionError::UnknownMode(key.to_owned()));
+                None
+            }
+        })
+        .collect()
+}
+
+/// `opening_hours` equivalent of the day and hour tags, such as
+/// `day_on=Monday day_off=Friday hour_on=07:00 hour_off=09:00` becoming
+/// `Mo-Fr 07:00-09:00`
+fn schedule(
+    relation: &Relation,
+) -> Result<Option<OpeningHours>, RestrictionError> {
+    let day = |key| {
+        relation.get_tag(key).map(|name| {
+            DAYS.iter()
+                .find(|(long, _)| long.eq_ignore_ascii_case(name))
+                .map_or(name, |(_, short)| short)
+        })
+    };
+    let days = match (day(DAY_ON), day(DAY_OFF)) {
+        (Some(on), Some(off)) => format!("{}-{}", on, off),
+        (Some(on), None) => on.to_owned(),
+        _ => String::new(),
+    };
+    let hours = match (relation.get_tag(HOUR_ON), relation.get_tag(HOUR_OFF)) {
+        (Some(on), Some(off)) => format!("{}-{}", on, off),
+        _ => String::new(),
+    };
+    if days.is_empty() && hours.is_empty() {
+        return Ok(None);
+    }
+    let text = format!("{} {}", days, hours);
+    text.trim()
+        .parse()
+        .map(Some)
+        .map_err(|_| RestrictionError::InvalidSchedule(text.trim().to_owned()))
+}
+
+fn way_members(
+    relation: &Relation,
+    role: &'static str,
+    errors: &mut Vec<RestrictionError>,
+) -> Vec<ElementID> {
+    let mut ways = Vec::new();
+
+    for member in relation.members.iter().filter(|m| m.role == role) {
+        if member.member_type == item_type::WAY {
+            ways.push(member.id);
+        } else {
+            errors.push(RestrictionError::NotAWay(member.id));
+        }
+    }
+    if ways.is_empty() {
+        errors.push(RestrictionError::MissingMember(role));
+    }
+    ways
+}
+
+fn via_member(
+    relation: &Relation,
+    errors: &mut Vec<RestrictionError>,
+) -> Option<Via> {
+    let members: Vec<_> = relation
+        .members
+        .iter()
+        .filter(|m| m.role == role::VIA)
+        .collect();
+
+    match members.as_slice() {
+        [] => {
+            errors.push(RestrictionError::MissingMember(role::VIA));
+            None
+        }
+        [node] if node.member_type == item_type::NODE => {
+            Some(Via::Node(node.id))
+        }
+        ways if ways.iter().all(|m| m.member_type == item_type::WAY) => {
+            Some(Via::Ways(ways.iter().map(|m| m.id).collect()))
+        }
+        _ => {
+            errors.push(RestrictionError::InvalidVia);
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{relation::Member, TagMap};
+    use chrono::NaiveDate;
+
+    fn member(
+        member_type: &'static str,
+        id: ElementID,
+        role: &'static str,
+    ) -> Member<'static> {
+        Member {
+            id,
+            member_type,
+            role,
+            ..Default::default()
+        }
+    }
+
+    fn relation(
+        tags: &[(&'static str, &'static str)],
+        members: Vec<Member<'static>>,
+    ) -> Relation<'static> {
+        let mut all: TagMap = tags.iter().copied().collect();
+        all.insert("type", "restriction");
+        Relation {
+            id: 9,
+            members,
+            tags: all,
+            ..Default::default()
+        }
+    }
+
+    fn simple_members() -> Vec<Member<'static>> {
+        vec![
+            member(item_type::WAY, 1, role::FROM),
+            member(item_type::NODE, 5, role::VIA),
+            member(item_type::WAY, 2, role::TO),
+        ]
+    }
+
+    #[test]
+    fn parses_kind_modes_and_times() {
+        let r = TurnRestriction::from_relation(&relation(
+            &[
+                ("restriction:hgv", "no_left_turn"),
+                ("except", "emergency"),
+                ("day_on", "Monday"),
+                ("day_off", "Friday"),
+                ("hour_on", "07:00"),
+                ("hour_off", "09:00"),
+            ],
+            simple_members(),
+        ))
+        .unwrap();
+        let monday = NaiveDate::from_ymd_opt(2020, 6, 1).unwrap();
+        let context = |hour| {
+            Context::new(
+                monday.and_hms_opt(hour, 0, 0).unwrap(),
+                Default::default(),
+            )
+        };
+
+        assert_eq!((r.kind, r.turn), (Kind::Prohibitory, Turn::Left));
+        assert_eq!(r.via, Via::Node(5));
+        assert!(r.applies(Mode::Hgv, Some(&context(8))));
+        assert!(!r.applies(Mode::Hgv, Some(&context(10))));
+        assert!(!r.applies(Mode::Motorcar, Some(&context(8))));
+        assert!(!r.applies(Mode::Hgv, None));
+    }
+
+    #[test]
+    fn rejects_conflicting_mode_values() {
+        let errors = TurnRestriction::from_relation(&relation(
+            &[
+                ("restriction:hgv", "no_left_turn"),
+                ("restriction:bus", "only_straight_on"),
+            ],
+            simple_members(),
+        ))
+        .unwrap_err();
+        assert_eq!(
+            errors,
+            vec![RestrictionError::ConflictingValues(vec![
+                "no_left_turn".to_owned(),
+                "only_straight_on".to_owned()
+            ])]
+        );
+
+        let errors = TurnRestriction::from_relation(&relation(
+            &[
+                ("restriction", "no_u_turn"),
+                ("restriction:hgv", "no_left_turn"),
+            ],
+            simple_members(),
+        ))
+        .unwrap_err();
+        assert_eq!(errors.len(), 1);
+
+        let r = TurnRestriction::from_relation(&relation(
+            &[
+                ("restriction:hgv", "no_left_turn"),
+                ("restriction:bus", "no_left_turn"),
+            ],
+            simple_members(),
+        ))
+        .unwrap();
+        assert_eq!(r.modes, vec![Mode::Bus, Mode::Hgv]);
+    }
+
+    #[test]
+    fn conditional_restrictions() {
+        let r = TurnRestriction::from_relation(&relation(
+            &[("restriction:conditional", "only_right_turn @ (Mo-Fr)")],
+            simple_members(),
+        ))
+        .unwrap();
+        let saturday = NaiveDate::from_ymd_opt(2020, 6, 6).unwrap();
+        let context = Context::new(
+            saturday.and_hms_opt(8, 0, 0).unwrap(),
+            Default::default(),
+        );
+
+        assert_eq!((r.kind, r.turn), (Kind::Mandatory, Turn::Right));
+        assert!(!r.applies(Mode::Motorcar, Some(&context)));
+
+        let r = TurnRestriction::from_relation(&relation(
+            &[("restriction:hgv:conditional", "no_left_turn @ (Sa)")],
+            simple_members(),
+        ))
+        .unwrap();
+        assert_eq!(r.modes, vec![Mode::Hgv]);
+        assert!(r.applies(Mode::Hgv, Some(&context)));
+        assert!(!r.applies(Mode::Motorcar, Some(&context)));
+        assert!(TurnRestriction::from_relation(&relation(
+            &[
+                ("restriction", "no_left_turn"),
+                ("restriction:hgv:conditional", "none @ (Sa)"),
+            ],
+            simple_members(),
+        ))
+        .is_err());
+
+        for tags in &[
+            &[
+                ("restriction", "no_left_turn"),
+                ("restriction:conditional", "no_right_turn @ (Mo-Fr)"),
+            ][..],
+            &[(
+                "restriction:conditional",
+                "no_left_turn @ (Mo); no_right_turn @ (Tu)",
+            )][..],
+        ] {
+            let errors = TurnRestriction::from_relation(&relation(
+                tags,
+                simple_members(),
+            ))
+            .unwrap_err();
+            assert_eq!(
+                errors,
+                vec![RestrictionError::ConflictingValues(vec![
+                    "no_left_turn".to_owned(),
+                    "no_right_turn".to_owned()
+                ])]
+            );
+        }
+    }
+
+    #[test]
+    fn reports_invalid_relations() {
+        let errors = TurnRestriction::from_relation(&relation(
+            &[("restriction", "no_left_turn"), ("except", "rocket")],
+            vec![
+                member(item_type::WAY, 1, role::FROM),
+                member(item_type::WAY, 3, role::FROM),
+                member(item_type::NODE, 5, role::VIA),
+                member(item_type::NODE, 6, role::VIA),
+            ],
+        ))
+        .unwrap_err();
+
+        for error in &[
+            RestrictionError::UnknownMode("rocket".into()),
+            RestrictionError::MissingMember(role::TO),
+            RestrictionError::MultipleMembers(role::FROM),
+            RestrictionError::InvalidVia,
+        ] {
+            assert!(errors.contains(error), "{}", error);
+        }
+    }
+
+    #[test]
+    fn validates_connections() {
+        use crate::{node::Node, way::Way};
+
+        let nodes: Vec<Node> = (1..=4)
+            .map(|id| Node {
+                id,
+                ..Default::default()
+            })
+            .collect();
+        let mut area = AreaData::default();
+        for (id, a, b) in &[(1, 0, 1), (2, 1, 2), (3, 2, 3)] {
+            area.ways.insert(
+                *id,
+                Way {
+                    id: *id,
+                    nodes: vec![&nodes[*a], &nodes[*b]],
+                    ..Default::default()
+                },
+            );
+        }
+        let restriction = |via_node| {
+            TurnRestriction::from_relation(&relation(
+                &[("restriction", "no_u_turn")],
+                vec![
+                    member(item_type::WAY, 1, role::FROM),
+                    member(item_type::NODE, via_node, role::VIA),
+                    member(item_type::WAY, 2, role::TO),
+                ],
+            ))
+            .unwrap()
+        };
+
+        assert!(restriction(2).validate(&area).is_empty());
+        assert_eq!(
+            restriction(4).validate(&area),
+            vec![
+                RestrictionError::NotConnected(1, 4),
+                RestrictionError::NotConnected(4, 2)
+            ]
+        );
+    }
+}
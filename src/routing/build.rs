@@ -0,0 +1,923 @@
+use super::{
+    profile::Profile, spatial::SpatialIndex, Edge, Graph, GraphNode, WayInfo,
+};
+use crate::{
+    access::{oneway, resolve, DefaultAccess},
+    elevation::{ElevationProfile, Terrain},
+    geo::haversine,
+    node::Node,
+    restriction::{Kind, TurnRestriction, Via},
+    tag::{
+        highway::Highway,
+        maxspeed::{self, Zones},
+        travel_by::Mode,
+    },
+    way::Way,
+    AreaData, ElementID,
+};
+use hashbrown::HashMap;
+
+/// Builds the routing graph of an area for one mode of transport.
+///
+/// Ways with a `highway` tag that the mode may access are split into edges
+/// at every node they share with another routable way, with an edge in each
+/// direction the way may be travelled. Unconditional turn restrictions for
+/// the mode, or for any vehicle when they name no modes, become forbidden
+/// transitions between edges. Restrictions with via ways are enforced by
+/// giving the via path its own copy of edges that can only be entered from
+/// the `from` way.
+///
+/// Edges are weighted by their length unless a profile is given, in which
+/// case only ways the profile rates are used and edges are weighted by the
+/// profile's travel time. With elevation data, the profile's travel time
+/// follows the grade between each pair of nodes rather than the way's
+/// `incline`.
+pub struct GraphBuilder<'d, 'a> {
+    area: &'d AreaData<'a>,
+    mode: Mode,
+    defaults: &'d DefaultAccess,
+    profile: Option<&'d Profile>,
+    elevation: bool,
+    terrain: Option<&'d dyn Terrain>,
+}
+
+impl<'d, 'a> GraphBuilder<'d, 'a> {
+    pub fn new(area: &'d AreaData<'a>, mode: Mode) -> Self {
+        GraphBuilder {
+            area,
+            mode,
+            defaults: DefaultAccess::world(),
+            profile: None,
+            elevation: false,
+            terrain: None,
+        }
+    }
+
+    /// Build for the profile's mode, using only the ways it rates and
+    /// weighting edges by its travel time
+    pub fn with_profile(mut self, profile: &'d Profile) -> Self {
+        self.mode = profile.mode;
+        self.profile = Some(profile);
+        self
+    }
+
+    /// Use access defaults other than the worldwide ones, such as those of a
+    /// country
+    pub fn with_defaults(mut self, defaults: &'d DefaultAccess) -> Self {
+        self.defaults = defaults;
+        self
+    }
+
+    /// Weight edges by the grades between nodes from their `ele`
+    pub fn with_elevation(mut self) -> Self {
+        self.elevation = true;
+        self
+    }
+
+    /// Weight edges by the grades between nodes from their `ele`, or from
+    /// the terrain for nodes without one
+    pub fn with_terrain(mut self, terrain: &'d dyn Terrain) -> Self {
+        self.elevation = true;
+        self.terrain = Some(terrain);
+        self
+    }
+
+    /// Whether the mode may travel along the way in at least one direction
+    pub fn is_routable(&self, way: &Way) -> bool {
+        let [forward, backward] = self.costs(way);
+        way.nodes.len() > 1 && (forward.is_some() || backward.is_some())
+    }
+
+    /// Weight of a meter of the way in and against the order of its nodes,
+    /// or `None` for directions that can't be travelled
+    fn costs(&self, way: &Way) -> [Option<f32>; 2] {
+        if Highway::of(way).is_none()
+            || !resolve(way, self.mode, self.defaults).is_allowed()
+        {
+            return [None, None];
+        }
+        let direction = oneway::direction(way, self.mode);
+        let cost = |allowed: bool, reverse: bool| match self.profile {
+            _ if !allowed => None,
+            Some(profile) => profile
+                .rate(way, self.defaults, reverse)
+                .map(|rating| rating.cost_per_meter()),
+            None => Some(1.0),
+        };
+        [
+            cost(direction.allows_forward(), false),
+            cost(direction.allows_backward(), true),
+        ]
+    }
+
+    /// Weight of a meter of part of a way in each direction, from the grades
+    /// between its nodes where elevations are known
+    fn part_costs(
+        &self,
+        way: &Way,
+        nodes: &[&Node],
+        costs: [Option<f32>; 2],
+    ) -> [Option<f32>; 2] {
+        let profile = match self.profile {
+            Some(profile) if self.elevation => profile,
+            _ => return costs,
+        };
+        // a single known elevation would make the part look level
+        let elevation = match ElevationProfile::measured(nodes, self.terrain) {
+            Some(elevation) => elevation,
+            None => return costs,
+        };
+        let level = match profile.rate_level(way, self.defaults) {
+            Some(rating) => rating,
+            None => return costs,
+        };
+        let distance = elevation.points[elevation.points.len() - 1].distance;
+        if distance <= 0.0 {
+            return costs;
+        }
+        let grades = elevation.grades();
+        let cost = |reverse: bool| {
+            let mut weight = 0.0;
+            for (pair, grade) in elevation.points.windows(2).zip(&grades) {
+                let grade = if reverse { -grade } else { *grade };
+                let rating = profile.climb(level, grade)?;
+                let length = pair[1].distance - pair[0].distance;
+                weight += length * f64::from(rating.cost_per_meter());
+            }
+            Some((weight / distance) as f32)
+        };
+        [
+            costs[0].and_then(|_| cost(false)),
+            costs[1].and_then(|_| cost(true)),
+        ]
+    }
+
+    pub fn build(&self) -> Graph {
+        let mut ways: Vec<&Way> = self
+            .area
+            .ways
+            .values()
+            .filter(|way| self.is_routable(way))
+            .collect();
+        ways.sort_by_key(|way| way.id);
+
+        // ends of ways count twice so that they always become graph nodes
+        let mut uses: HashMap<ElementID, u32> = HashMap::new();
+        for way in &ways {
+            for node in &way.nodes {
+                *uses.entry(node.id).or_insert(0) += 1;
+            }
+            for node in way.first_node().iter().chain(&way.last_node()) {
+                *uses.entry(node.id).or_insert(0) += 1;
+            }
+        }
+
+        let mut parts = Parts::default();
+        for way in &ways {
+            let costs = self.costs(way);
+            let way_index = parts.ways.len() as u32;
+            parts.ways.push(WayInfo {
+                id: way.id,
+                highway: Highway::of(*way),
+                maxspeed: maxspeed::effective(*way, Zones::standard())
+                    .and_then(|speed| speed.kmh()),
+            });
+
+            let mut segments = Vec::new();
+            let mut start = 0;
+            for i in 1..way.nodes.len() {
+                if i == way.nodes.len() - 1 || uses[&way.nodes[i].id] > 1 {
+                    let part = &way.nodes[start..=i];
+                    segments.push(parts.add_segment(
+                        way_index,
+                        part,
+                        self.part_costs(way, part, costs),
+                    ));
+                    start = i;
+                }
+            }
+            parts.segments.insert(way.id, segments);
+        }
+
+        // restrictions without modes only bind vehicles
+        let is_vehicle = self.mode.ancestors().any(|m| m == Mode::Vehicle);
+        let mut restrictions: Vec<_> = self
+            .area
+            .relations
+            .iter()
+            .filter_map(|relation| {
+                TurnRestriction::from_relation(relation).ok()
+            })
+            .filter(|r| {
+                (is_vehicle || !r.modes.is_empty())
+                    && r.applies(self.mode, None)
+                    && r.validate(self.area).is_empty()
+            })
+            .collect();
+        restrictions.sort_by_key(|r| r.id);
+
+        // every copy has to exist before forbidding turns, so that turns
+        // from or onto a way also cover its copies
+        let mut chains = Vec::with_capacity(restrictions.len());
+        for restriction in &restrictions {
+            chains.push(match &restriction.via {
+                Via::Node(_) => None,
+                Via::Ways(via) => {
+                    parts.copy_via_ways(restriction, via, self.area)
+                }
+            });
+        }
+        for (restriction, chain) in restrictions.iter().zip(&chains) {
+            match (&restriction.via, chain) {
+                (Via::Node(node), _) => {
+                    parts.restrict_via_node(restriction, *node)
+                }
+                (Via::Ways(_), Some(chain)) => {
+                    parts.restrict_via_ways(restriction, chain)
+                }
+                (Via::Ways(_), None) => {}
+            }
+        }
+        parts.finish()
+    }
+}
+
+/// Edges of one part of a way between junctions, in each direction it may
+/// be travelled
+struct Segment {
+    forward: Option<u32>,
+    backward: Option<u32>,
+}
+
+/// Copies of the edges along a restriction's via ways, starting at the node
+/// where the `from` way joins them
+struct Chain {
+    start: u32,
+    /// Original and copied edge for each step along the via ways
+    steps: Vec<(u32, u32)>,
+}
+
+/// Graph under construction, with edges in the order they were added
+#[derive(Default)]
+struct Parts {
+    nodes: Vec<GraphNode>,
+    index: HashMap<ElementID, u32>,
+    edges: Vec<Edge>,
+    shapes: Vec<(ElementID, (f32, f32))>,
+    ways: Vec<WayInfo>,
+    /// Segments of each way in node order
+    segments: HashMap<ElementID, Vec<Segment>>,
+    out: HashMap<u32, Vec<u32>>,
+    incoming: HashMap<u32, Vec<u32>>,
+    forbidden: Vec<(u32, u32)>,
+    /// Copies of via paths with the only edges they may be entered from
+    exclusive: Vec<(u32, Vec<u32>)>,
+    /// Copy of an edge entered from the `from` ways of restrictions
+    entries: HashMap<(Vec<ElementID>, u32), u32>,
+    /// Copy of an edge entered from the previous copy along a via path
+    copies: HashMap<(u32, u32), u32>,
+}
+
+impl Parts {
+    fn node(&mut self, node: &Node) -> u32 {
+        let nodes = &mut self.nodes;
+        *self.index.entry(node.id).or_insert_with(|| {
+            nodes.push(GraphNode {
+                id: node.id,
+                point: node.point(),
+            });
+            nodes.len() as u32 - 1
+        })
+    }
+
+    fn add_segment(
+        &mut self,
+        way: u32,
+        nodes: &[&Node],
+        [forward, backward]: [Option<f32>; 2],
+    ) -> Segment {
+        let source = self.node(nodes[0]);
+        let target = self.node(nodes[nodes.len() - 1]);
+        let distance = nodes
+            .windows(2)
+            .map(|pair| haversine(pair[0].point(), pair[1].point()))
+            .sum::<f64>() as f32;
+
+        let add = |parts: &mut Parts, reverse: bool, cost: f32| {
+            let start = parts.shapes.len() as u32;
+            let shape = nodes.iter().map(|node| (node.id, node.point()));
+            if reverse {
+                parts.shapes.extend(shape.rev());
+            } else {
+                parts.shapes.extend(shape);
+            }
+            let (source, target) = if reverse {
+                (target, source)
+            } else {
+                (source, target)
+            };
+            parts.push(Edge {
+                source,
+                target,
+                way,
+                distance,
+                weight: distance * cost,
+                reverse,
+                shape: (start, parts.shapes.len() as u32),
+            })
+        };
+        Segment {
+            forward: forward.map(|cost| add(self, false, cost)),
+            backward: backward.map(|cost| add(self, true, cost)),
+        }
+    }
+
+    fn push(&mut self, edge: Edge) -> u32 {
+        let index = self.edges.len() as u32;
+        self.out.entry(edge.source).or_default().push(index);
+        self.incoming.entry(edge.target).or_default().push(index);
+        self.edges.push(edge);
+        index
+    }
+
+    fn out_edges(&self, node: u32) -> &[u32] {
+        self.out.get(&node).map_or(&[], Vec::as_slice)
+    }
+
+    /// Edges of the ways arriving at or leaving the node
+    fn way_edges(
+        &self,
+        ways: &[ElementID],
+        node: u32,
+        arriving: bool,
+    ) -> Vec<u32> {
+        let edges = if arriving {
+            self.incoming.get(&node)
+        } else {
+            self.out.get(&node)
+        };
+        edges
+            .into_iter()
+            .flatten()
+            .copied()
+            .filter(|e| {
+                let way = self.edges[*e as usize].way as usize;
+                ways.contains(&self.ways[way].id)
+            })
+            .collect()
+    }
+
+    /// Forbid each of the `from` edges continuing anywhere but the `allowed`
+    /// edges, or onto the `to` edges for prohibitory restrictions
+    fn restrict(&mut self, kind: &Kind, from: &[u32], to: &[u32], node: u32) {
+        for &f in from {
+            match kind {
+                Kind::Prohibitory => {
+                    self.forbidden.extend(to.iter().map(|t| (f, *t)))
+                }
+                Kind::Mandatory => {
+                    let others = self.out_edges(node).iter().copied();
+                    let others: Vec<_> =
+                        others.filter(|e| !to.contains(e)).collect();
+                    self.forbidden.extend(others.into_iter().map(|t| (f, t)));
+                }
+            }
+        }
+    }
+
+    fn restrict_via_node(&mut self, r: &TurnRestriction, node: ElementID) {
+        let node = match self.index.get(&node) {
+            Some(node) => *node,
+            None => return,
+        };
+        let from = self.way_edges(&r.from, node, true);
+        let to = self.way_edges(&r.to, node, false);
+        self.restrict(&r.kind, &from, &to, node);
+    }
+
+    /// Copy the edges along the via ways so that traffic from the `from` way
+    /// can take the copy, while other traffic keeps using the original
+    /// edges. Restrictions from the same ways share copies along the via
+    /// ways they have in common, so that each copy carries every one of
+    /// their turns.
+    fn copy_via_ways(
+        &mut self,
+        r: &TurnRestriction,
+        via: &[ElementID],
+        area: &AreaData,
+    ) -> Option<Chain> {
+        let ends = |id| {
+            let way = &area.ways[&id];
+            (way.nodes[0].id, way.nodes[way.nodes.len() - 1].id)
+        };
+        let (first, last) = ends(via[0]);
+        let mut current =
+            r.from.iter().map(|id| ends(*id)).find_map(|(a, b)| {
+                [a, b].iter().copied().find(|n| *n == first || *n == last)
+            })?;
+        let start = *self.index.get(&current)?;
+
+        let mut path = Vec::new();
+        for id in via {
+            let segments = self.segments.get(id)?;
+            let (first, last) = ends(*id);
+            let edges: Option<Vec<u32>> = if first == current {
+                current = last;
+                segments.iter().map(|s| s.forward).collect()
+            } else if last == current {
+                current = first;
+                segments.iter().rev().map(|s| s.backward).collect()
+            } else {
+                return None;
+            };
+            // a via way that can't be travelled makes the restriction moot
+            path.extend(edges?);
+        }
+        if self.way_edges(&r.from, start, true).is_empty() {
+            return None;
+        }
+
+        let mut from = r.from.clone();
+        from.sort_unstable();
+        let mut previous = None;
+        let mut steps = Vec::with_capacity(path.len());
+        for original in path {
+            let existing = match previous {
+                None => self.entries.get(&(from.clone(), original)),
+                Some(copy) => self.copies.get(&(copy, original)),
+            };
+            let copy = match existing {
+                Some(copy) => *copy,
+                None => {
+                    let copy = self.push(self.edges[original as usize].clone());
+                    match previous {
+                        None => {
+                            self.entries.insert((from.clone(), original), copy)
+                        }
+                        Some(p) => self.copies.insert((p, original), copy),
+                    };
+                    copy
+                }
+            };
+            steps.push((original, copy));
+            previous = Some(copy);
+        }
+        Some(Chain { start, steps })
+    }
+
+    /// Send traffic from the `from` way along the chain of copies, which
+    /// doesn't lead to the `to` way (or only leads there)
+    fn restrict_via_ways(&mut self, r: &TurnRestriction, chain: &Chain) {
+        let mut previous = self.way_edges(&r.from, chain.start, true);
+        for &(original, copy) in &chain.steps {
+            self.forbidden
+                .extend(previous.iter().map(|p| (*p, original)));
+            if r.kind == Kind::Mandatory {
+                let node = self.edges[copy as usize].source;
+                self.restrict(&r.kind, &previous, &[copy], node);
+            }
+            self.exclusive.push((copy, previous));
+            previous = vec![copy];
+        }
+
+        let end = self.edges[previous[0] as usize].target;
+        let to = self.way_edges(&r.to, end, false);
+        self.restrict(&r.kind, &previous, &to, end);
+    }
+
+    /// Sort edges by source node into the final graph
+    fn finish(mut self) -> Graph {
+        for (copy, allowed) in std::mem::take(&mut self.exclusive) {
+            let source = self.edges[copy as usize].source;
+            let entering = self.incoming.get(&source).into_iter().flatten();
+            self.forbidden.extend(
+                entering
+                    .filter(|e| !allowed.contains(e))
+                    .map(|e| (*e, copy)),
+            );
+        }
+
+        let mut order: Vec<u32> = (0..self.edges.len() as u32).collect();
+        order.sort_by_key(|e| self.edges[*e as usize].source);
+        let mut position = vec![0; order.len()];
+        for (i, e) in order.iter().enumerate() {
+            position[*e as usize] = i as u32;
+        }
+        let mut forbidden: Vec<_> = self
+            .forbidden
+            .iter()
+            .map(|(a, b)| (position[*a as usize], position[*b as usize]))
+            .collect();
+        forbidden.sort_unstable();
+        forbidden.dedup();
+
+        let edges: Vec<Edge> = order
+            .iter()
+            .map(|e| self.edges[*e as usize].clone())
+            .collect();
+        let mut first_out = vec![0; self.nodes.len() + 1];
+        for edge in &edges {
+            first_out[edge.source as usize + 1] += 1;
+        }
+        for i in 1..first_out.len() {
+            first_out[i] += first_out[i - 1];
+        }
+
+        Graph {
+            nodes: self.nodes,
+            first_out,
+            spatial: SpatialIndex::new(&edges, &self.shapes),
+            edges,
+            shapes: self.shapes,
+            ways: self.ways,
+            forbidden,
+            index: self.index,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        geo::Point,
+        item_type,
+        relation::{role, Member, Relation},
+        TagMap,
+    };
+
+    fn nodes() -> Vec<Node> {
+        // a 3x2 grid, 1 2 3 along the bottom and 4 5 6 along the top
+        (1..=6)
+            .map(|id| Node {
+                id,
+                lat: if id > 3 { 0.001 } else { 0.0 },
+                lon: ((id - 1) % 3) as f32 * 0.001,
+                ..Default::default()
+            })
+            .collect()
+    }
+
+    fn way<'a>(
+        id: ElementID,
+        nodes: &'a [Node],
+        ids: &[ElementID],
+        tags: &[(&'static str, &'static str)],
+    ) -> Way<'a> {
+        Way {
+            id,
+            nodes: ids.iter().map(|id| &nodes[*id as usize - 1]).collect(),
+            tags: Some(tags.iter().copied().collect::<TagMap>()),
+            ..Default::default()
+        }
+    }
+
+    fn restriction(
+        value: &'static str,
+        members: &[(&'static str, ElementID, &'static str)],
+    ) -> Relation<'static> {
+        let tags: TagMap =
+            vec![("type", "restriction"), ("restriction", value)]
+                .into_iter()
+                .collect();
+        Relation {
+            id: 1,
+            members: members
+                .iter()
+                .map(|(member_type, id, role)| Member {
+                    id: *id,
+                    member_type,
+                    role,
+                    ..Default::default()
+                })
+                .collect(),
+            tags,
+            ..Default::default()
+        }
+    }
+
+    fn area<'a>(
+        ways: Vec<Way<'a>>,
+        relations: Vec<Relation<'a>>,
+    ) -> AreaData<'a> {
+        AreaData {
+            ways: ways.into_iter().map(|way| (way.id, way)).collect(),
+            relations,
+            ..Default::default()
+        }
+    }
+
+    /// Whether the transition between the ways' edges at the node is allowed
+    fn can_turn(
+        graph: &Graph,
+        from: ElementID,
+        node: ElementID,
+        to: ElementID,
+    ) -> bool {
+        let node = graph.node_index(node).unwrap();
+        graph.edges().iter().enumerate().any(|(a, edge)| {
+            edge.target == node
+                && graph.way(edge).id == from
+                && graph.out_edges(node).any(|b| {
+                    graph.way(graph.edge(b)).id == to
+                        && !graph.is_forbidden(a as u32, b)
+                })
+        })
+    }
+
+    /// Whether the last of the ways can be reached by following edges from
+    /// the node along each of them in turn
+    fn reachable(graph: &Graph, start: ElementID, ways: &[ElementID]) -> bool {
+        let start = graph.node_index(start).unwrap();
+        let mut current: Vec<u32> = graph
+            .out_edges(start)
+            .filter(|e| graph.way(graph.edge(*e)).id == ways[0])
+            .collect();
+        for id in &ways[1..] {
+            current = current
+                .iter()
+                .flat_map(|a| {
+                    let node = graph.edge(*a).target;
+                    graph
+                        .out_edges(node)
+                        .filter(|b| {
+                            graph.way(graph.edge(*b)).id == *id
+                                && !graph.is_forbidden(*a, *b)
+                        })
+                        .collect::<Vec<_>>()
+                })
+                .collect();
+        }
+        !current.is_empty()
+    }
+
+    #[test]
+    fn splits_ways_at_junctions() {
+        let nodes = nodes();
+        let area = area(
+            vec![
+                way(1, &nodes, &[1, 2, 3], &[("highway", "residential")]),
+                way(
+                    2,
+                    &nodes,
+                    &[2, 5],
+                    &[("highway", "service"), ("oneway", "yes")],
+                ),
+                way(3, &nodes, &[4, 5, 6], &[("highway", "footway")]),
+            ],
+            vec![],
+        );
+        let graph = GraphBuilder::new(&area, Mode::Motorcar).build();
+
+        // way 1 is split at node 2, the footway is left out
+        assert_eq!(graph.node_count(), 4);
+        assert_eq!(graph.edge_count(), 5);
+        assert_eq!(graph.node_index(4), None);
+        let junction = graph.node_index(2).unwrap();
+        assert_eq!(graph.out_edges(junction).len(), 3);
+
+        let oneway = graph.node_index(5).unwrap();
+        assert_eq!(graph.out_edges(oneway).len(), 0);
+        let edge = graph.edge(
+            graph
+                .out_edges(junction)
+                .find(|e| graph.edge(*e).target == oneway)
+                .unwrap(),
+        );
+        assert!((edge.distance - 111.2).abs() < 0.5);
+        assert_eq!(graph.shape(edge).len(), 2);
+
+        let foot = GraphBuilder::new(&area, Mode::Foot).build();
+        assert_eq!(foot.node_count(), 6);
+        assert_eq!(foot.out_edges(foot.node_index(5).unwrap()).len(), 3);
+    }
+
+    #[test]
+    fn weights_by_profile() {
+        let nodes = nodes();
+        let area = area(
+            vec![
+                way(1, &nodes, &[1, 2], &[("highway", "residential")]),
+                way(2, &nodes, &[2, 3], &[("highway", "footway")]),
+                way(3, &nodes, &[2, 5], &[("highway", "steps")]),
+            ],
+            vec![],
+        );
+        let car = Profile::car();
+        let graph = GraphBuilder::new(&area, Mode::Foot)
+            .with_profile(&car)
+            .build();
+        assert_eq!(graph.edge_count(), 2);
+        let edge = graph.edge(0);
+        // 30 km/h on a residential street
+        assert!((edge.weight - edge.distance * 0.12).abs() < 0.01);
+
+        let wheelchair = Profile::wheelchair();
+        let graph = GraphBuilder::new(&area, Mode::Motorcar)
+            .with_profile(&wheelchair)
+            .build();
+        assert_eq!(graph.edge_count(), 4);
+        assert_eq!(graph.node_index(5), None);
+    }
+
+    #[test]
+    fn weights_by_elevation() {
+        // 5% up from node 1 to 2, then level to 3
+        let mut nodes = nodes();
+        nodes[0].ele = Some(0.0);
+        nodes[1].ele = Some(5.56);
+        nodes[2].ele = Some(5.56);
+        let road = [("highway", "residential")];
+        {
+            let hill = area(vec![way(1, &nodes, &[1, 2, 3], &road)], vec![]);
+            let bicycle = Profile::bicycle();
+            let graph = GraphBuilder::new(&hill, Mode::Bicycle)
+                .with_profile(&bicycle)
+                .with_elevation()
+                .build();
+            let weight = |reverse: bool| {
+                graph
+                    .edges()
+                    .iter()
+                    .find(|e| e.reverse == reverse)
+                    .unwrap()
+                    .weight
+            };
+            // 6% of the speed lost for each percent uphill on the first half
+            let slower = (1.0 / 0.7 + 1.0) / 2.0;
+            assert!((weight(false) / weight(true) - slower).abs() < 0.01);
+        }
+
+        // too steep for a wheelchair either way, from the terrain where
+        // nodes have no elevation
+        nodes[1].ele = None;
+        {
+            let area = area(vec![way(1, &nodes, &[1, 2, 3], &road)], vec![]);
+            let wheelchair = Profile::wheelchair();
+            let build = |terrain: Option<&dyn Terrain>| {
+                let builder = GraphBuilder::new(&area, Mode::Foot)
+                    .with_profile(&wheelchair);
+                match terrain {
+                    Some(terrain) => builder.with_terrain(terrain),
+                    None => builder,
+                }
+                .build()
+            };
+            let terrain = |_: Point| Some(11.12);
+            assert_eq!(build(None).edge_count(), 2);
+            assert_eq!(build(Some(&terrain)).edge_count(), 0);
+        }
+
+        // one known elevation leaves the way's own incline in charge
+        nodes[2].ele = None;
+        let incline = [("highway", "residential"), ("incline", "5%")];
+        let tagged = area(vec![way(1, &nodes, &[1, 2, 3], &incline)], vec![]);
+        let bicycle = Profile::bicycle();
+        let graph = GraphBuilder::new(&tagged, Mode::Bicycle)
+            .with_profile(&bicycle)
+            .with_elevation()
+            .build();
+        let weights: Vec<f32> =
+            graph.edges().iter().map(|edge| edge.weight).collect();
+        assert_eq!(weights.len(), 2);
+        assert!((weights[0] - weights[1]).abs() > 1.0);
+    }
+
+    #[test]
+    fn via_node_restrictions() {
+        let nodes = nodes();
+        let road = [("highway", "residential")];
+        let ways = || {
+            vec![
+                way(1, &nodes, &[1, 2], &road),
+                way(2, &nodes, &[2, 3], &road),
+                way(3, &nodes, &[2, 5], &road),
+            ]
+        };
+
+        let no_left = restriction(
+            "no_left_turn",
+            &[
+                (item_type::WAY, 1, role::FROM),
+                (item_type::NODE, 2, role::VIA),
+                (item_type::WAY, 3, role::TO),
+            ],
+        );
+        let area = area(ways(), vec![no_left]);
+        let graph = GraphBuilder::new(&area, Mode::Motorcar).build();
+        assert!(!can_turn(&graph, 1, 2, 3));
+        assert!(can_turn(&graph, 1, 2, 2));
+        assert!(can_turn(&graph, 2, 2, 3));
+        // pedestrians ignore restrictions on the roads they may use
+        let foot = GraphBuilder::new(&area, Mode::Foot).build();
+        assert!(foot.forbidden_turns().is_empty());
+
+        let only_straight = restriction(
+            "only_straight_on",
+            &[
+                (item_type::WAY, 1, role::FROM),
+                (item_type::NODE, 2, role::VIA),
+                (item_type::WAY, 2, role::TO),
+            ],
+        );
+        let area = super::tests::area(ways(), vec![only_straight]);
+        let graph = GraphBuilder::new(&area, Mode::Motorcar).build();
+        assert!(!can_turn(&graph, 1, 2, 3));
+        assert!(!can_turn(&graph, 1, 2, 1));
+        assert!(can_turn(&graph, 1, 2, 2));
+    }
+
+    #[test]
+    fn via_way_restrictions() {
+        let nodes = nodes();
+        let road = [("highway", "residential")];
+        // no u-turn from way 1 through ways 2 and 3 back onto way 4
+        let no_u_turn = restriction(
+            "no_u_turn",
+            &[
+                (item_type::WAY, 1, role::FROM),
+                (item_type::WAY, 2, role::VIA),
+                (item_type::WAY, 3, role::VIA),
+                (item_type::WAY, 4, role::TO),
+            ],
+        );
+        let area = area(
+            vec![
+                way(1, &nodes, &[1, 2], &road),
+                way(2, &nodes, &[2, 5], &road),
+                way(3, &nodes, &[5, 4], &road),
+                way(4, &nodes, &[4, 1], &road),
+                way(5, &nodes, &[5, 6], &road),
+            ],
+            vec![no_u_turn],
+        );
+        let graph = GraphBuilder::new(&area, Mode::Motorcar).build();
+
+        assert!(!reachable(&graph, 1, &[1, 2, 3, 4]));
+        assert!(reachable(&graph, 1, &[1, 2, 3]));
+        assert!(reachable(&graph, 1, &[1, 2, 5]));
+        // only the path coming from way 1 is restricted
+        assert!(can_turn(&graph, 3, 4, 4));
+        assert_eq!(graph.edge_count(), 12);
+    }
+    #[test]
+    fn via_way_restrictions_combine() {
+        let nodes = nodes();
+        let road = [("highway", "residential")];
+        let ways = || {
+            vec![
+                way(1, &nodes, &[1, 2], &road),
+                way(2, &nodes, &[2, 5], &road),
+                way(3, &nodes, &[5, 4], &road),
+                way(5, &nodes, &[5, 6], &road),
+            ]
+        };
+        let via_way = |id, value, to| {
+            let mut r = restriction(
+                value,
+                &[
+                    (item_type::WAY, 1, role::FROM),
+                    (item_type::WAY, 2, role::VIA),
+                    (item_type::WAY, to, role::TO),
+                ],
+            );
+            r.id = id;
+            r
+        };
+
+        // both turns at the end of way 2 are forbidden coming from way 1
+        let area = area(
+            ways(),
+            vec![
+                via_way(1, "no_left_turn", 3),
+                via_way(2, "no_right_turn", 5),
+            ],
+        );
+        let graph = GraphBuilder::new(&area, Mode::Motorcar).build();
+        assert!(reachable(&graph, 1, &[1, 2]));
+        assert!(!reachable(&graph, 1, &[1, 2, 3]));
+        assert!(!reachable(&graph, 1, &[1, 2, 5]));
+        assert!(can_turn(&graph, 2, 5, 3));
+        assert!(can_turn(&graph, 2, 5, 5));
+
+        // a turn at a node binds copies of its `from` way made later
+        let mut via_node = restriction(
+            "no_right_turn",
+            &[
+                (item_type::WAY, 2, role::FROM),
+                (item_type::NODE, 5, role::VIA),
+                (item_type::WAY, 5, role::TO),
+            ],
+        );
+        via_node.id = 1;
+        let area = super::tests::area(
+            ways(),
+            vec![via_node, via_way(2, "no_left_turn", 3)],
+        );
+        let graph = GraphBuilder::new(&area, Mode::Motorcar).build();
+        assert!(!reachable(&graph, 1, &[1, 2, 3]));
+        assert!(!reachable(&graph, 1, &[1, 2, 5]));
+        assert!(!can_turn(&graph, 2, 5, 5));
+        assert!(can_turn(&graph, 2, 5, 3));
+    }
+}
@@ -0,0 +1,1078 @@
+//! [Contraction hierarchies](https://en.wikipedia.org/wiki/Contraction_hierarchies)
+//! for answering many route queries on the same graph quickly.
+//!
+//! The hierarchy is built over the edges of a `Graph` rather than its nodes,
+//! with an arc for every allowed turn from one edge to the next, so that
+//! forbidden turns hold in the shortcuts too. Edges are contracted from the
+//! least to the most important, adding shortcuts wherever a path through a
+//! contracted edge has no detour of equal cost. Queries search upwards from
+//! both ends and meet at the most important edge of the route.
+//!
+//! A built hierarchy owns its graph and can be written to and read back
+//! from a compact binary file, so that it's built once and loaded by every
+//! process that queries it.
+use super::{
+    path::{self, Cost, Entry, Position, Route, Snap},
+    spatial::SpatialIndex,
+    Edge, Graph, GraphNode, WayInfo,
+};
+use crate::geo::Point;
+use hashbrown::HashMap;
+use std::{
+    collections::BinaryHeap,
+    io::{self, Read, Write},
+};
+
+/// Start of a serialized hierarchy, ending in the format version
+static MAGIC: &[u8; 8] = b"OSMCH\0\0\x02";
+
+/// Edges settled by a witness search before giving up and adding the
+/// shortcut
+static WITNESS_LIMIT: usize = 500;
+
+/// Turn from one edge onto the next, or a shortcut standing for a chain of
+/// them
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct Arc {
+    from: u32,
+    to: u32,
+    /// Cost of the edges entered, `to` included and `from` excluded
+    weight: f64,
+    /// The two arcs a shortcut replaces
+    children: Option<(u32, u32)>,
+}
+
+/// Graph preprocessed for fast queries with one cost function
+#[derive(Clone, Debug)]
+pub struct ContractionHierarchy {
+    graph: Graph,
+    /// Cost of each edge of the graph
+    costs: Vec<f64>,
+    arcs: Vec<Arc>,
+    /// Arcs to more important edges, grouped by the edge they leave
+    up_first: Vec<u32>,
+    up: Vec<u32>,
+    /// Arcs from more important edges, grouped by the edge they enter
+    down_first: Vec<u32>,
+    down: Vec<u32>,
+}
+
+impl ContractionHierarchy {
+    /// Contract the graph's edges for routing by the cost function
+    pub fn build<C: Cost>(graph: Graph, cost: &C) -> Self {
+        let costs: Vec<f64> = graph
+            .edges()
+            .iter()
+            .map(|edge| cost.cost(&graph, edge))
+            .collect();
+        let mut contraction = Contraction::new(&graph, &costs);
+
+        let count = graph.edge_count();
+        let mut queue: BinaryHeap<Entry> = (0..count as u32)
+            .map(|v| Entry(contraction.priority(v), v))
+            .collect();
+        while let Some(Entry(_, v)) = queue.pop() {
+            // priorities change as neighbours are contracted, so they're
+            // only brought up to date when an edge comes up
+            let priority = contraction.priority(v);
+            if queue.peek().is_some_and(|next| priority > next.0) {
+                queue.push(Entry(priority, v));
+                continue;
+            }
+            contraction.contract(v);
+        }
+
+        let mut up = vec![Vec::new(); count];
+        let mut down = vec![Vec::new(); count];
+        for (v, list) in contraction.out.iter().enumerate() {
+            for (w, arc) in list {
+                let w = *w as usize;
+                if contraction.rank[v] <= contraction.rank[w] {
+                    up[v].push(*arc);
+                } else {
+                    down[w].push(*arc);
+                }
+            }
+        }
+        let (up_first, up) = flatten(up);
+        let (down_first, down) = flatten(down);
+
+        ContractionHierarchy {
+            graph,
+            costs,
+            arcs: contraction.arcs,
+            up_first,
+            up,
+            down_first,
+            down,
+        }
+    }
+
+    pub fn graph(&self) -> &Graph {
+        &self.graph
+    }
+
+    /// Number of shortcuts kept in the hierarchy
+    pub fn shortcut_count(&self) -> usize {
+        self.up
+            .iter()
+            .chain(&self.down)
+            .filter(|arc| self.arcs[**arc as usize].children.is_some())
+            .count()
+    }
+
+    /// Best route between two coordinates after snapping them to the graph,
+    /// or `None` if there is no route
+    pub fn route(&self, from: Point, to: Point) -> Option<Route> {
+        self.route_between(
+            &path::snap(&self.graph, from)?,
+            &path::snap(&self.graph, to)?,
+        )
+    }
+
+    /// Best route between two snapped places
+    pub fn route_between(&self, from: &Snap, to: &Snap) -> Option<Route> {
+        let mut best = f64::INFINITY;
+        let mut meeting = None;
+
+        for start in &from.positions {
+            for end in to.positions.iter().filter(|p| p.edge == start.edge) {
+                let cost = self.partial(end) - self.partial(start);
+                if end.offset >= start.offset && cost < best {
+                    best = cost;
+                    meeting = Some(Meeting::Direct(start.edge));
+                }
+            }
+        }
+
+        // backward costs are raised so that none is negative, which keeps
+        // the stopping rule sound
+        let shift = to
+            .positions
+            .iter()
+            .map(|p| self.costs[p.edge as usize])
+            .fold(0.0, f64::max);
+        let mut forward = Search::default();
+        for start in &from.positions {
+            let cost = self.costs[start.edge as usize] - self.partial(start);
+            forward.seed(start.edge, cost.max(0.0));
+        }
+        let mut backward = Search::default();
+        for end in &to.positions {
+            let cost =
+                self.partial(end) - self.costs[end.edge as usize] + shift;
+            backward.seed(end.edge, cost.max(0.0));
+        }
+
+        loop {
+            let limit = best + shift;
+            let f = forward.peek().filter(|key| *key <= limit);
+            let b = backward.peek().filter(|key| *key <= limit);
+            let is_forward = match (f, b) {
+                (Some(f), Some(b)) => f <= b,
+                (Some(_), None) => true,
+                (None, Some(_)) => false,
+                (None, None) => break,
+            };
+            let (search, other) = if is_forward {
+                (&mut forward, &backward)
+            } else {
+                (&mut backward, &forward)
+            };
+            let (v, cost) = match search.pop() {
+                Some(settled) => settled,
+                None => continue,
+            };
+
+            if let Some(&(other_cost, other_parent)) = other.labels.get(&v) {
+                // two seeds meeting is the direct route handled above
+                let seeds =
+                    other_parent.is_none() && search.labels[&v].1.is_none();
+                if !seeds && cost + other_cost - shift < best {
+                    best = cost + other_cost - shift;
+                    meeting = Some(Meeting::At(v));
+                }
+            }
+            if is_forward {
+                for arc in self.up_arcs(v) {
+                    let Arc { to: w, weight, .. } = self.arcs[arc as usize];
+                    // loops back onto a target edge are only found here, as
+                    // the edge's own label is never improved
+                    if let Some(&(other_cost, _)) = other.labels.get(&w) {
+                        if cost + weight + other_cost - shift < best {
+                            best = cost + weight + other_cost - shift;
+                            meeting = Some(Meeting::Across(arc));
+                        }
+                    }
+                    search.relax(w, cost + weight, arc);
+                }
+            } else {
+                for arc in self.down_arcs(v) {
+                    let Arc {
+                        from: u, weight, ..
+                    } = self.arcs[arc as usize];
+                    search.relax(u, cost + weight, arc);
+                }
+            }
+        }
+
+        let edges = match meeting? {
+            Meeting::Direct(edge) => vec![edge],
+            Meeting::At(v) => {
+                let mut edges = self.forward_edges(&forward, v);
+                edges.extend(self.backward_edges(&backward, v));
+                edges
+            }
+            Meeting::Across(arc) => {
+                let Arc { from: u, to: w, .. } = self.arcs[arc as usize];
+                let mut edges = self.forward_edges(&forward, u);
+                edges.extend(self.unpack(arc));
+                edges.extend(self.backward_edges(&backward, w));
+                edges
+            }
+        };
+
+        let (first, last) = (edges[0], edges[edges.len() - 1]);
+        let start = from.positions.iter().find(|p| p.edge == first)?;
+        let end = to.positions.iter().find(|p| p.edge == last)?;
+        let cost = edges
+            .iter()
+            .map(|edge| self.costs[*edge as usize])
+            .sum::<f64>()
+            - self.partial(start)
+            - (self.costs[last as usize] - self.partial(end));
+        Some(path::assemble(
+            &self.graph,
+            cost,
+            edges,
+            start.offset,
+            end.offset,
+        ))
+    }
+
+    /// Cost of the part of an edge up to the position
+    fn partial(&self, position: &Position) -> f64 {
+        let length = f64::from(self.graph.edge(position.edge).distance);
+        if length > 0.0 {
+            self.costs[position.edge as usize]
+                * (position.offset / length).min(1.0)
+        } else {
+            0.0
+        }
+    }
+
+    fn up_arcs(&self, v: u32) -> impl Iterator<Item = u32> + '_ {
+        let v = v as usize;
+        let (a, b) = (self.up_first[v], self.up_first[v + 1]);
+        self.up[a as usize..b as usize].iter().copied()
+    }
+
+    fn down_arcs(&self, v: u32) -> impl Iterator<Item = u32> + '_ {
+        let v = v as usize;
+        let (a, b) = (self.down_first[v], self.down_first[v + 1]);
+        self.down[a as usize..b as usize].iter().copied()
+    }
+
+    /// Edges from where the forward search started up to `v`
+    fn forward_edges(&self, search: &Search, v: u32) -> Vec<u32> {
+        let mut arcs = Vec::new();
+        let mut start = v;
+        while let Some(arc) = search.labels[&start].1 {
+            arcs.push(arc);
+            start = self.arcs[arc as usize].from;
+        }
+        let mut edges = vec![start];
+        for arc in arcs.into_iter().rev() {
+            edges.extend(self.unpack(arc));
+        }
+        edges
+    }
+
+    /// Edges after `v` up to where the backward search started
+    fn backward_edges(&self, search: &Search, v: u32) -> Vec<u32> {
+        let mut edges = Vec::new();
+        let mut current = v;
+        while let Some(arc) = search.labels[&current].1 {
+            edges.extend(self.unpack(arc));
+            current = self.arcs[arc as usize].to;
+        }
+        edges
+    }
+
+    /// Edges entered along an arc, expanding shortcuts
+    fn unpack(&self, arc: u32) -> Vec<u32> {
+        let mut edges = Vec::new();
+        let mut stack = vec![arc];
+        while let Some(arc) = stack.pop() {
+            let arc = &self.arcs[arc as usize];
+            match arc.children {
+                Some((first, second)) => {
+                    stack.push(second);
+                    stack.push(first);
+                }
+                None => edges.push(arc.to),
+            }
+        }
+        edges
+    }
+
+    /// Write the hierarchy and its graph in a compact binary form
+    pub fn write<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        writer.write_all(MAGIC)?;
+        let graph = &self.graph;
+        graph.nodes.encode(writer)?;
+        graph.first_out.encode(writer)?;
+        graph.edges.encode(writer)?;
+        graph.shapes.encode(writer)?;
+        graph.ways.encode(writer)?;
+        graph.forbidden.encode(writer)?;
+        graph.spatial.encode(writer)?;
+        self.costs.encode(writer)?;
+        self.arcs.encode(writer)?;
+        self.up_first.encode(writer)?;
+        self.up.encode(writer)?;
+        self.down_first.encode(writer)?;
+        self.down.encode(writer)
+    }
+
+    /// Read a hierarchy written by `write`
+    pub fn read<R: Read>(reader: &mut R) -> io::Result<Self> {
+        let mut magic = [0; 8];
+        reader.read_exact(&mut magic)?;
+        if &magic != MAGIC {
+            return Err(invalid("not a contraction hierarchy"));
+        }
+        let nodes: Vec<GraphNode> = Vec::decode(reader)?;
+        let index = nodes
+            .iter()
+            .enumerate()
+            .map(|(i, node)| (node.id, i as u32))
+            .collect();
+        let graph = Graph {
+            nodes,
+            first_out: Vec::decode(reader)?,
+            edges: Vec::decode(reader)?,
+            shapes: Vec::decode(reader)?,
+            ways: Vec::decode(reader)?,
+            forbidden: Vec::decode(reader)?,
+            index,
+            spatial: SpatialIndex::decode(reader)?,
+        };
+        let hierarchy = ContractionHierarchy {
+            graph,
+            costs: Vec::decode(reader)?,
+            arcs: Vec::decode(reader)?,
+            up_first: Vec::decode(reader)?,
+            up: Vec::decode(reader)?,
+            down_first: Vec::decode(reader)?,
+            down: Vec::decode(reader)?,
+        };
+        hierarchy.check()?;
+        Ok(hierarchy)
+    }
+
+    /// Check that the indexes read from a file are in range
+    fn check(&self) -> io::Result<()> {
+        let graph = &self.graph;
+        let (nodes, edges) = (graph.nodes.len(), graph.edges.len());
+        let arcs = self.arcs.len() as u32;
+        // shortcuts are added after the arcs they replace, so unpacking one
+        // always ends
+        let ok = graph.first_out.len() == nodes + 1
+            && graph.first_out.last().is_some_and(|n| *n as usize == edges)
+            && graph.first_out.windows(2).all(|w| w[0] <= w[1])
+            && graph.edges.iter().all(|e| {
+                (e.source as usize) < nodes
+                    && (e.target as usize) < nodes
+                    && (e.way as usize) < graph.ways.len()
+                    && e.shape.0 <= e.shape.1
+                    && e.shape.1 as usize <= graph.shapes.len()
+            })
+            && graph
+                .forbidden
+                .iter()
+                .all(|(a, b)| (*a as usize) < edges && (*b as usize) < edges)
+            && graph.forbidden.windows(2).all(|w| w[0] < w[1])
+            && check_spatial(graph)
+            && self.costs.len() == edges
+            && self.arcs.iter().zip(0..).all(|(arc, i)| {
+                (arc.from as usize) < edges
+                    && (arc.to as usize) < edges
+                    && arc.children.is_none_or(|(a, b)| a < i && b < i)
+            })
+            && [(&self.up_first, &self.up), (&self.down_first, &self.down)]
+                .iter()
+                .all(|(first, list)| {
+                    first.len() == edges + 1
+                        && first.windows(2).all(|w| w[0] <= w[1])
+                        && first
+                            .last()
+                            .is_some_and(|n| *n as usize == list.len())
+                        && list.iter().all(|arc| *arc < arcs)
+                });
+        if ok {
+            Ok(())
+        } else {
+            Err(invalid("inconsistent contraction hierarchy"))
+        }
+    }
+}
+
+/// Where the two searches of a query met
+#[derive(Clone, Copy, Debug)]
+enum Meeting {
+    /// Start and end lie along the same edge
+    Direct(u32),
+    /// Both searches reached the edge
+    At(u32),
+    /// The forward search reached an edge the backward search started from
+    /// along the arc
+    Across(u32),
+}
+
+/// One direction of a query
+#[derive(Default)]
+struct Search {
+    /// Cost of each edge reached and the arc it was reached along
+    labels: HashMap<u32, (f64, Option<u32>)>,
+    heap: BinaryHeap<Entry>,
+}
+
+impl Search {
+    fn seed(&mut self, edge: u32, cost: f64) {
+        if self.labels.get(&edge).is_none_or(|(c, _)| cost < *c) {
+            self.labels.insert(edge, (cost, None));
+            self.heap.push(Entry(cost, edge));
+        }
+    }
+
+    fn relax(&mut self, edge: u32, cost: f64, arc: u32) {
+        if self.labels.get(&edge).is_none_or(|(c, _)| cost < *c) {
+            self.labels.insert(edge, (cost, Some(arc)));
+            self.heap.push(Entry(cost, edge));
+        }
+    }
+
+    fn peek(&self) -> Option<f64> {
+        self.heap.peek().map(|entry| entry.0)
+    }
+
+    /// Next edge whose cost is final, skipping outdated heap entries
+    fn pop(&mut self) -> Option<(u32, f64)> {
+        while let Some(Entry(cost, edge)) = self.heap.pop() {
+            if cost <= self.labels[&edge].0 {
+                return Some((edge, cost));
+            }
+        }
+        None
+    }
+}
+
+/// Arcs joining an edge to its neighbours, as pairs of the neighbouring
+/// edge and the arc
+type Links = Vec<(u32, u32)>;
+
+/// Edges and arcs while contraction is under way
+struct Contraction {
+    arcs: Vec<Arc>,
+    /// Arcs leaving each edge, with the edge they enter
+    out: Vec<Links>,
+    /// Arcs entering each edge, with the edge they leave
+    incoming: Vec<Links>,
+    contracted: Vec<bool>,
+    /// Order in which edges were contracted
+    rank: Vec<u32>,
+    /// Contracted neighbours of each edge, which spreads contraction evenly
+    /// over the graph
+    deleted: Vec<u32>,
+    contracted_count: u32,
+}
+
+impl Contraction {
+    fn new(graph: &Graph, costs: &[f64]) -> Self {
+        let count = graph.edge_count();
+        let mut contraction = Contraction {
+            arcs: Vec::new(),
+            out: vec![Vec::new(); count],
+            incoming: vec![Vec::new(); count],
+            contracted: vec![false; count],
+            rank: vec![0; count],
+            deleted: vec![0; count],
+            contracted_count: 0,
+        };
+        for (a, edge) in graph.edges().iter().enumerate() {
+            let a = a as u32;
+            for b in graph.out_edges(edge.target) {
+                if !graph.is_forbidden(a, b) {
+                    contraction.add_arc(a, b, costs[b as usize], None);
+                }
+            }
+        }
+        contraction
+    }
+
+    /// Add an arc unless a cheaper one already joins the edges
+    fn add_arc(
+        &mut self,
+        from: u32,
+        to: u32,
+        weight: f64,
+        children: Option<(u32, u32)>,
+    ) {
+        let index = self.arcs.len() as u32;
+        let existing =
+            self.out[from as usize].iter().position(|(w, _)| *w == to);
+        if let Some(i) = existing {
+            let old = self.out[from as usize][i].1;
+            if self.arcs[old as usize].weight <= weight {
+                return;
+            }
+            self.out[from as usize][i].1 = index;
+            for entry in &mut self.incoming[to as usize] {
+                if entry.1 == old {
+                    entry.1 = index;
+                }
+            }
+        } else {
+            self.out[from as usize].push((to, index));
+            self.incoming[to as usize].push((from, index));
+        }
+        self.arcs.push(Arc {
+            from,
+            to,
+            weight,
+            children,
+        });
+    }
+
+    /// Uncontracted neighbours of the edge and the arcs to or from them
+    fn neighbours(&self, v: u32) -> (Links, Links) {
+        let live = |list: &Links| {
+            list.iter()
+                .copied()
+                .filter(|(n, _)| *n != v && !self.contracted[*n as usize])
+                .collect()
+        };
+        (
+            live(&self.incoming[v as usize]),
+            live(&self.out[v as usize]),
+        )
+    }
+
+    /// Shortcuts needed to contract the edge, as the arcs they replace and
+    /// their weight
+    fn shortcuts(&self, v: u32) -> Vec<(u32, u32, f64)> {
+        let (incoming, out) = self.neighbours(v);
+        let mut shortcuts = Vec::new();
+
+        for (u, in_arc) in &incoming {
+            let in_weight = self.arcs[*in_arc as usize].weight;
+            let limit = out
+                .iter()
+                .map(|(_, arc)| in_weight + self.arcs[*arc as usize].weight)
+                .fold(0.0, f64::max);
+            let (costs, round_trip) = self.witness_search(*u, v, limit);
+
+            for (w, out_arc) in &out {
+                let weight = in_weight + self.arcs[*out_arc as usize].weight;
+                let witness = if w == u {
+                    round_trip
+                } else {
+                    costs.get(w).copied()
+                };
+                if witness.is_none_or(|cost| cost > weight) {
+                    shortcuts.push((*in_arc, *out_arc, weight));
+                }
+            }
+        }
+        shortcuts
+    }
+
+    /// Costs of the cheapest paths from `source` avoiding `skip` up to the
+    /// limit, and of the cheapest way back to `source`
+    fn witness_search(
+        &self,
+        source: u32,
+        skip: u32,
+        limit: f64,
+    ) -> (HashMap<u32, f64>, Option<f64>) {
+        let mut costs = HashMap::new();
+        let mut round_trip: Option<f64> = None;
+        let mut heap = BinaryHeap::new();
+        costs.insert(source, 0.0);
+        heap.push(Entry(0.0, source));
+
+        let mut settled = 0;
+        while let Some(Entry(cost, x)) = heap.pop() {
+            if cost > costs[&x] {
+                continue;
+            }
+            settled += 1;
+            if cost > limit || settled > WITNESS_LIMIT {
+                break;
+            }
+            for (y, arc) in &self.out[x as usize] {
+                if *y == skip || self.contracted[*y as usize] {
+                    continue;
+                }
+                let next = cost + self.arcs[*arc as usize].weight;
+                if *y == source {
+                    round_trip = Some(round_trip.map_or(next, |c| c.min(next)));
+                } else if costs.get(y).is_none_or(|c| next < *c) {
+                    costs.insert(*y, next);
+                    heap.push(Entry(next, *y));
+                }
+            }
+        }
+        (costs, round_trip)
+    }
+
+    /// Edge difference: shortcuts added less arcs removed, plus contracted
+    /// neighbours
+    fn priority(&self, v: u32) -> f64 {
+        let (incoming, out) = self.neighbours(v);
+        let shortcuts = self.shortcuts(v).len() as f64;
+        shortcuts - (incoming.len() + out.len()) as f64
+            + f64::from(self.deleted[v as usize])
+    }
+
+    fn contract(&mut self, v: u32) {
+        for (in_arc, out_arc, weight) in self.shortcuts(v) {
+            let from = self.arcs[in_arc as usize].from;
+            let to = self.arcs[out_arc as usize].to;
+            self.add_arc(from, to, weight, Some((in_arc, out_arc)));
+        }
+        let (incoming, out) = self.neighbours(v);
+        for (n, _) in incoming.iter().chain(&out) {
+            self.deleted[*n as usize] += 1;
+        }
+        self.contracted[v as usize] = true;
+        self.rank[v as usize] = self.contracted_count;
+        self.contracted_count += 1;
+    }
+}
+
+/// Offsets of each list in the concatenation of all lists, followed by the
+/// concatenation
+fn flatten(lists: Vec<Vec<u32>>) -> (Vec<u32>, Vec<u32>) {
+    let mut first = Vec::with_capacity(lists.len() + 1);
+    let mut all = Vec::new();
+    first.push(0);
+    for list in lists {
+        all.extend(list);
+        first.push(all.len() as u32);
+    }
+    (first, all)
+}
+
+/// Whether the spatial index read from a file fits the graph
+fn check_spatial(graph: &Graph) -> bool {
+    let index = &graph.spatial;
+    let cells = u64::from(index.rows) * u64::from(index.cols);
+    let fits = |(edge, start): &(u32, u32)| {
+        graph.edges.get(*edge as usize).is_some_and(|e| {
+            *start >= e.shape.0 && start.saturating_add(1) < e.shape.1
+        })
+    };
+    if index.first.is_empty() {
+        return cells == 0 && index.segments.is_empty();
+    }
+    index.first.len() as u64 == cells + 1
+        && index.first[0] == 0
+        && index.first.windows(2).all(|w| w[0] <= w[1])
+        && index
+            .first
+            .last()
+            .is_some_and(|n| *n as usize == index.segments.len())
+        && index.segments.iter().all(fits)
+        && index.cell.0 > 0.0
+        && index.cell.1 > 0.0
+}
+
+fn invalid(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+/// Little-endian binary form of the values in a serialized hierarchy
+trait Encode: Sized {
+    fn encode<W: Write>(&self, writer: &mut W) -> io::Result<()>;
+    fn decode<R: Read>(reader: &mut R) -> io::Result<Self>;
+}
+
+macro_rules! encode_number {
+    ($($t:ty),*) => {$(
+        impl Encode for $t {
+            fn encode<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+                writer.write_all(&self.to_le_bytes())
+            }
+
+            fn decode<R: Read>(reader: &mut R) -> io::Result<Self> {
+                let mut bytes = [0; std::mem::size_of::<$t>()];
+                reader.read_exact(&mut bytes)?;
+                Ok(<$t>::from_le_bytes(bytes))
+            }
+        }
+    )*};
+}
+
+encode_number!(u8, u32, u64, i64, f32, f64);
+
+impl Encode for bool {
+    fn encode<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        (*self as u8).encode(writer)
+    }
+
+    fn decode<R: Read>(reader: &mut R) -> io::Result<Self> {
+        Ok(u8::decode(reader)? != 0)
+    }
+}
+
+impl<T: Encode> Encode for Option<T> {
+    fn encode<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        self.is_some().encode(writer)?;
+        match self {
+            Some(value) => value.encode(writer),
+            None => Ok(()),
+        }
+    }
+
+    fn decode<R: Read>(reader: &mut R) -> io::Result<Self> {
+        if bool::decode(reader)? {
+            T::decode(reader).map(Some)
+        } else {
+            Ok(None)
+        }
+    }
+}
+
+impl<A: Encode, B: Encode> Encode for (A, B) {
+    fn encode<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        self.0.encode(writer)?;
+        self.1.encode(writer)
+    }
+
+    fn decode<R: Read>(reader: &mut R) -> io::Result<Self> {
+        Ok((A::decode(reader)?, B::decode(reader)?))
+    }
+}
+
+impl<T: Encode> Encode for Vec<T> {
+    fn encode<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        (self.len() as u64).encode(writer)?;
+        self.iter().try_for_each(|value| value.encode(writer))
+    }
+
+    fn decode<R: Read>(reader: &mut R) -> io::Result<Self> {
+        let len = u64::decode(reader)?;
+        // a corrupt length mustn't allocate more than the data can fill
+        let mut values = Vec::with_capacity(len.min(1 << 16) as usize);
+        for _ in 0..len {
+            values.push(T::decode(reader)?);
+        }
+        Ok(values)
+    }
+}
+
+impl Encode for String {
+    fn encode<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        self.as_bytes().to_vec().encode(writer)
+    }
+
+    fn decode<R: Read>(reader: &mut R) -> io::Result<Self> {
+        String::from_utf8(Vec::decode(reader)?)
+            .map_err(|_| invalid("way tag isn't UTF-8"))
+    }
+}
+
+impl Encode for GraphNode {
+    fn encode<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        self.id.encode(writer)?;
+        self.point.encode(writer)
+    }
+
+    fn decode<R: Read>(reader: &mut R) -> io::Result<Self> {
+        Ok(GraphNode {
+            id: Encode::decode(reader)?,
+            point: Encode::decode(reader)?,
+        })
+    }
+}
+
+impl Encode for Edge {
+    fn encode<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        self.source.encode(writer)?;
+        self.target.encode(writer)?;
+        self.way.encode(writer)?;
+        self.distance.encode(writer)?;
+        self.weight.encode(writer)?;
+        self.reverse.encode(writer)?;
+        self.shape.encode(writer)
+    }
+
+    fn decode<R: Read>(reader: &mut R) -> io::Result<Self> {
+        Ok(Edge {
+            source: Encode::decode(reader)?,
+            target: Encode::decode(reader)?,
+            way: Encode::decode(reader)?,
+            distance: Encode::decode(reader)?,
+            weight: Encode::decode(reader)?,
+            reverse: Encode::decode(reader)?,
+            shape: Encode::decode(reader)?,
+        })
+    }
+}
+
+impl Encode for SpatialIndex {
+    fn encode<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        self.origin.encode(writer)?;
+        self.cell.encode(writer)?;
+        self.rows.encode(writer)?;
+        self.cols.encode(writer)?;
+        self.first.encode(writer)?;
+        self.segments.encode(writer)
+    }
+
+    fn decode<R: Read>(reader: &mut R) -> io::Result<Self> {
+        Ok(SpatialIndex {
+            origin: Encode::decode(reader)?,
+            cell: Encode::decode(reader)?,
+            rows: Encode::decode(reader)?,
+            cols: Encode::decode(reader)?,
+            first: Encode::decode(reader)?,
+            segments: Encode::decode(reader)?,
+        })
+    }
+}
+
+impl Encode for WayInfo {
+    fn encode<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        self.id.encode(writer)?;
+        self.highway
+            .as_ref()
+            .map(|highway| highway.as_str().to_owned())
+            .encode(writer)?;
+        self.maxspeed.encode(writer)
+    }
+
+    fn decode<R: Read>(reader: &mut R) -> io::Result<Self> {
+        let id = Encode::decode(reader)?;
+        let highway: Option<String> = Encode::decode(reader)?;
+        Ok(WayInfo {
+            id,
+            highway: highway.map(|value| value.parse().unwrap()),
+            maxspeed: Encode::decode(reader)?,
+        })
+    }
+}
+
+impl Encode for Arc {
+    fn encode<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        self.from.encode(writer)?;
+        self.to.encode(writer)?;
+        self.weight.encode(writer)?;
+        self.children.encode(writer)
+    }
+
+    fn decode<R: Read>(reader: &mut R) -> io::Result<Self> {
+        Ok(Arc {
+            from: Encode::decode(reader)?,
+            to: Encode::decode(reader)?,
+            weight: Encode::decode(reader)?,
+            children: Encode::decode(reader)?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        item_type,
+        node::Node,
+        relation::{role, Member, Relation},
+        routing::{
+            path::{Distance, Router, Time},
+            GraphBuilder,
+        },
+        tag::travel_by::Mode,
+        way::Way,
+        AreaData, ElementID, TagMap,
+    };
+
+    /// A 4x4 grid of streets 100 m apart with a faster avenue along the
+    /// second row, a oneway street north along the third column and no left
+    /// turn from the first row into the last column
+    fn grid(nodes: &[Node]) -> AreaData<'_> {
+        let mut ways = Vec::new();
+        let mut add =
+            |ids: Vec<usize>, tags: &[(&'static str, &'static str)]| {
+                let id = ways.len() as ElementID + 1;
+                ways.push(Way {
+                    id,
+                    nodes: ids.iter().map(|i| &nodes[*i]).collect(),
+                    tags: Some(tags.iter().copied().collect::<TagMap>()),
+                    ..Default::default()
+                });
+            };
+        for row in 0..4 {
+            let tags: &[_] = if row == 1 {
+                &[("highway", "primary"), ("maxspeed", "60")]
+            } else {
+                &[("highway", "residential")]
+            };
+            add((0..4).map(|col| row * 4 + col).collect(), tags);
+        }
+        for col in 0..4 {
+            let tags: &[_] = if col == 2 {
+                &[("highway", "residential"), ("oneway", "yes")]
+            } else {
+                &[("highway", "residential")]
+            };
+            add((0..4).map(|row| row * 4 + col).collect(), tags);
+        }
+
+        let tags: TagMap =
+            vec![("type", "restriction"), ("restriction", "no_left_turn")]
+                .into_iter()
+                .collect();
+        let member = |member_type, id, role| Member {
+            id,
+            member_type,
+            role,
+            ..Default::default()
+        };
+        let restriction = Relation {
+            id: 1,
+            members: vec![
+                member(item_type::WAY, 1, role::FROM),
+                member(item_type::NODE, 4, role::VIA),
+                member(item_type::WAY, 8, role::TO),
+            ],
+            tags,
+            ..Default::default()
+        };
+        AreaData {
+            ways: ways.into_iter().map(|way| (way.id, way)).collect(),
+            relations: vec![restriction],
+            ..Default::default()
+        }
+    }
+
+    fn nodes() -> Vec<Node> {
+        (0..16)
+            .map(|i| Node {
+                id: i + 1,
+                lat: (i / 4) as f32 * 0.0009,
+                lon: (i % 4) as f32 * 0.0009,
+                ..Default::default()
+            })
+            .collect()
+    }
+
+    fn points() -> Vec<Point> {
+        (0..7)
+            .flat_map(|i| (0..7).map(move |j| (i, j)))
+            .map(|(i, j)| (i as f32 * 0.00045 + 0.00001, j as f32 * 0.00045))
+            .collect()
+    }
+
+    #[test]
+    fn matches_plain_search() {
+        let nodes = nodes();
+        let area = grid(&nodes);
+        let graph = GraphBuilder::new(&area, Mode::Motorcar).build();
+        let router = Router::new(&graph, Time);
+        let hierarchy = ContractionHierarchy::build(graph.clone(), &Time);
+        assert!(hierarchy.shortcut_count() > 0);
+
+        for from in points() {
+            for to in points() {
+                let expected = router.route(from, to);
+                let found = hierarchy.route(from, to);
+                match (expected, found) {
+                    (Some(expected), Some(found)) => {
+                        assert!(
+                            (expected.cost - found.cost).abs() < 1e-6,
+                            "{:?} to {:?}: {} != {}",
+                            from,
+                            to,
+                            expected.cost,
+                            found.cost
+                        );
+                        assert!(found.edges.windows(2).all(|pair| {
+                            graph.edge(pair[0]).target
+                                == graph.edge(pair[1]).source
+                                && !graph.is_forbidden(pair[0], pair[1])
+                        }));
+                    }
+                    (expected, found) => {
+                        assert_eq!(expected.is_some(), found.is_some())
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn turns_back_along_oneway() {
+        let nodes = nodes();
+        let area = grid(&nodes);
+        let graph = GraphBuilder::new(&area, Mode::Motorcar).build();
+        let hierarchy = ContractionHierarchy::build(graph, &Distance);
+
+        // the end is behind the start on the same edge of the oneway column
+        let route =
+            hierarchy.route((0.0015, 0.0018), (0.0012, 0.0018)).unwrap();
+        assert!(route.distance > 300.0);
+        assert_eq!(route.ways.first(), Some(&7));
+        assert_eq!(route.ways.last(), Some(&7));
+    }
+
+    #[test]
+    fn reads_what_it_writes() {
+        let nodes = nodes();
+        let area = grid(&nodes);
+        let graph = GraphBuilder::new(&area, Mode::Motorcar).build();
+        let hierarchy = ContractionHierarchy::build(graph, &Distance);
+
+        let mut bytes = Vec::new();
+        hierarchy.write(&mut bytes).unwrap();
+        let read = ContractionHierarchy::read(&mut bytes.as_slice()).unwrap();
+        let (from, to) = ((0.0, 0.0001), (0.0027, 0.0026));
+        assert_eq!(read.route(from, to), hierarchy.route(from, to));
+        assert_eq!(
+            read.graph().node_index(16),
+            Some(hierarchy.graph().node_index(16).unwrap())
+        );
+        assert_eq!(read.graph().spatial, hierarchy.graph().spatial);
+
+        bytes.truncate(bytes.len() - 3);
+        assert!(ContractionHierarchy::read(&mut bytes.as_slice()).is_err());
+        let newer = b"OSMCH\0\0\x03";
+        assert!(ContractionHierarchy::read(&mut &newer[..]).is_err());
+    }
+
+    #[test]
+    fn rejects_shortcut_cycles_and_unsorted_turns() {
+        let nodes = nodes();
+        let area = grid(&nodes);
+        let graph = GraphBuilder::new(&area, Mode::Motorcar).build();
+        let hierarchy = ContractionHierarchy::build(graph, &Distance);
+        assert!(hierarchy.check().is_ok());
+
+        // a shortcut replacing itself would be unpacked forever
+        let mut cyclic = hierarchy.clone();
+        let (i, shortcut) = cyclic
+            .arcs
+            .iter_mut()
+            .enumerate()
+            .find(|(_, arc)| arc.children.is_some())
+            .unwrap();
+        shortcut.children = Some((i as u32, i as u32));
+        assert!(cyclic.check().is_err());
+
+        let mut unsorted = hierarchy;
+        unsorted.graph.forbidden = vec![(1, 0), (0, 1)];
+        assert!(unsorted.check().is_err());
+    }
+}
@@ -0,0 +1,514 @@
+//! Areas reachable from a start within time or cost limits.
+//!
+//! A bounded search from the start finds the cost of reaching every node
+//! and every point along the edges within the largest limit. For each limit,
+//! or band, the reachable points are gathered into square grid cells and the
+//! outline of the cells becomes the band's polygons, with holes where
+//! unreachable blocks are surrounded by reachable streets.
+use super::{
+    path::{Cost, Entry, Snap},
+    Graph,
+};
+use crate::geo::{haversine, ring_contains, signed_area, Point, EARTH_RADIUS};
+use hashbrown::{HashMap, HashSet};
+use serde_json::json;
+use std::{collections::BinaryHeap, error, fmt};
+
+/// Smallest grid cell in meters, well below the accuracy of map data
+pub static MIN_CELL_SIZE: f64 = 1.0;
+
+/// Grid cell size that isn't a number of meters of at least
+/// `MIN_CELL_SIZE`
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct InvalidCellSize(pub f64);
+
+impl fmt::Display for InvalidCellSize {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "isochrone cell size {} isn't at least {} meters",
+            self.0, MIN_CELL_SIZE
+        )
+    }
+}
+
+impl error::Error for InvalidCellSize {}
+
+/// Polygons of the area reachable within a limit
+#[derive(Clone, Debug, PartialEq)]
+pub struct Band {
+    pub limit: f64,
+    /// Polygons as closed rings, the outer ring first and then any holes
+    pub polygons: Vec<Vec<Vec<Point>>>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Isochrones {
+    /// Cost of reaching each graph node within the largest limit
+    pub reached: HashMap<u32, f64>,
+    /// Bands in order of increasing limit, each including the smaller ones
+    pub bands: Vec<Band>,
+}
+
+impl Isochrones {
+    /// The bands as a GeoJSON `FeatureCollection` of `MultiPolygon`s with
+    /// a `limit` property
+    pub fn to_geojson(&self) -> String {
+        let features: Vec<_> = self
+            .bands
+            .iter()
+            .map(|band| {
+                let polygons: Vec<Vec<Vec<[f32; 2]>>> = band
+                    .polygons
+                    .iter()
+                    .map(|rings| {
+                        rings
+                            .iter()
+                            .map(|ring| {
+                                ring.iter()
+                                    .map(|(lat, lon)| [*lon, *lat])
+                                    .collect()
+                            })
+                            .collect()
+                    })
+                    .collect();
+                json!({
+                    "type": "Feature",
+                    "properties": { "limit": band.limit },
+                    "geometry": {
+                        "type": "MultiPolygon",
+                        "coordinates": polygons,
+                    },
+                })
+            })
+            .collect();
+        json!({ "type": "FeatureCollection", "features": features }).to_string()
+    }
+}
+
+/// Part of an edge reached by the search
+#[derive(Clone, Copy, Debug)]
+struct Entered {
+    /// Cost at `offset`
+    cost: f64,
+    /// Meters along the edge where it was entered, zero unless it's where
+    /// the search started
+    offset: f64,
+}
+
+/// Cost of reaching each graph node from the start, up to the limit
+pub fn reachable<C: Cost>(
+    graph: &Graph,
+    cost: &C,
+    from: &Snap,
+    limit: f64,
+) -> HashMap<u32, f64> {
+    search(graph, cost, from, limit).0
+}
+
+/// Search outwards from the start, returning the cost of reaching each node
+/// and where each edge was entered
+fn search<C: Cost>(
+    graph: &Graph,
+    cost: &C,
+    from: &Snap,
+    limit: f64,
+) -> (HashMap<u32, f64>, HashMap<u32, Entered>) {
+    let end_cost = |edge: u32, entered: &Entered| {
+        let e = graph.edge(edge);
+        let length = f64::from(e.distance);
+        let rest = if length > 0.0 {
+            1.0 - (entered.offset / length).min(1.0)
+        } else {
+            1.0
+        };
+        entered.cost + cost.cost(graph, e) * rest
+    };
+
+    let mut nodes: HashMap<u32, f64> = HashMap::new();
+    let mut edges: HashMap<u32, Entered> = HashMap::new();
+    let mut heap = BinaryHeap::new();
+    for position in &from.positions {
+        let entered = Entered {
+            cost: 0.0,
+            offset: position.offset,
+        };
+        edges.insert(position.edge, entered);
+        heap.push(Entry(end_cost(position.edge, &entered), position.edge));
+    }
+
+    while let Some(Entry(end, a)) = heap.pop() {
+        if end > end_cost(a, &edges[&a]) || end > limit {
+            continue;
+        }
+        let node = graph.edge(a).target;
+        let best = nodes.entry(node).or_insert(end);
+        *best = best.min(end);
+
+        for b in graph.out_edges(node) {
+            if graph.is_forbidden(a, b) {
+                continue;
+            }
+            let entered = Entered {
+                cost: end,
+                offset: 0.0,
+            };
+            let next = end_cost(b, &entered);
+            if edges.get(&b).is_none_or(|e| next < end_cost(b, e)) {
+                edges.insert(b, entered);
+                heap.push(Entry(next, b));
+            }
+        }
+    }
+    (nodes, edges)
+}
+
+/// Reachable area from the start for each limit, outlined with grid cells
+/// of the given size in meters
+pub fn isochrones<C: Cost>(
+    graph: &Graph,
+    cost: &C,
+    from: &Snap,
+    limits: &[f64],
+    cell_size: f64,
+) -> Result<Isochrones, InvalidCellSize> {
+    if !(cell_size >= MIN_CELL_SIZE && cell_size.is_finite()) {
+        return Err(InvalidCellSize(cell_size));
+    }
+    let mut limits = limits.to_vec();
+    limits.sort_by(f64::total_cmp);
+    let largest = limits.last().copied().unwrap_or(0.0);
+    let (reached, edges) = search(graph, cost, from, largest);
+
+    let grid = Grid::new(from.point, cell_size);
+    // lowest cost of any point reached in each cell
+    let mut cells: HashMap<(i32, i32), f64> = HashMap::new();
+    let mut mark = |point: Point, c: f64| {
+        let best = cells.entry(grid.cell(point)).or_insert(c);
+        *best = best.min(c);
+    };
+    mark(from.point, 0.0);
+
+    for (edge, entered) in &edges {
+        let e = graph.edge(*edge);
+        let edge_cost = cost.cost(graph, e);
+        let length = f64::from(e.distance);
+        let per_meter = if length > 0.0 {
+            edge_cost / length
+        } else {
+            0.0
+        };
+
+        // sample the edge at half the cell size from where it was entered
+        let mut offset = 0.0;
+        for pair in graph.shape(e).windows(2) {
+            let (a, b) = (pair[0].1, pair[1].1);
+            let segment = haversine(a, b);
+            let steps = (segment / (cell_size / 2.0)).ceil().max(1.0) as usize;
+            for step in 0..=steps {
+                let along = offset + segment * step as f64 / steps as f64;
+                if along < entered.offset {
+                    continue;
+                }
+                let c = entered.cost + (along - entered.offset) * per_meter;
+                if c > largest {
+                    break;
+                }
+                let t = (step as f64 / steps as f64) as f32;
+                mark((a.0 + (b.0 - a.0) * t, a.1 + (b.1 - a.1) * t), c);
+            }
+            offset += segment;
+        }
+    }
+
+    let bands = limits
+        .iter()
+        .map(|limit| {
+            let inside: HashSet<(i32, i32)> = cells
+                .iter()
+                .filter(|(_, c)| **c <= *limit)
+                .map(|(cell, _)| *cell)
+                .collect();
+            Band {
+                limit: *limit,
+                polygons: grid.polygons(&inside),
+            }
+        })
+        .collect();
+    Ok(Isochrones { reached, bands })
+}
+
+/// Square cells of equal size in meters around an origin
+struct Grid {
+    origin: Point,
+    /// Cell size in degrees of latitude and longitude
+    size: (f64, f64),
+}
+
+impl Grid {
+    fn new(origin: Point, cell_size: f64) -> Self {
+        let lat = (cell_size / EARTH_RADIUS).to_degrees();
+        let lon = lat / f64::from(origin.0).to_radians().cos();
+        Grid {
+            origin,
+            size: (lat, lon),
+        }
+    }
+
+    fn cell(&self, (lat, lon): Point) -> (i32, i32) {
+        let x = (f64::from(lon) - f64::from(self.origin.1)) / self.size.1;
+        let y = (f64::from(lat) - f64::from(self.origin.0)) / self.size.0;
+        (x.floor() as i32, y.floor() as i32)
+    }
+
+    fn point(&self, (x, y): (i32, i32)) -> Point {
+        (
+            (f64::from(self.origin.0) + f64::from(y) * self.size.0) as f32,
+            (f64::from(self.origin.1) + f64::from(x) * self.size.1) as f32,
+        )
+    }
+
+    /// Outline of the cells as polygons with holes
+    fn polygons(&self, cells: &HashSet<(i32, i32)>) -> Vec<Vec<Vec<Point>>> {
+        let mut outers = Vec::new();
+        let mut holes = Vec::new();
+        for ring in trace(cells) {
+            let ring: Vec<Point> =
+                ring.iter().map(|v| self.point(*v)).collect();
+            if signed_area(&ring) > 0.0 {
+                outers.push(vec![ring]);
+            } else {
+                holes.push(ring);
+            }
+        }
+        for hole in holes {
+            // the middle of a hole's first side is inside its polygon and any
+            // larger one around it, so take the smallest
+            let (a, b) = (hole[0], hole[1]);
+            let middle = ((a.0 + b.0) / 2.0, (a.1 + b.1) / 2.0);
+            if let Some(polygon) = outers
+                .iter_mut()
+                .filter(|polygon| ring_contains(&polygon[0], middle))
+                .min_by(|a, b| {
+                    signed_area(&a[0]).total_cmp(&signed_area(&b[0]))
+                })
+            {
+                polygon.push(hole);
+            }
+        }
+        outers
+    }
+}
+
+/// Closed rings of grid vertices around the cells, counter-clockwise around
+/// reachable cells and clockwise around holes
+fn trace(cells: &HashSet<(i32, i32)>) -> Vec<Vec<(i32, i32)>> {
+    // sides with no reachable cell beyond them, directed to keep the cell on
+    // their left
+    let mut sides: HashMap<(i32, i32), Vec<(i32, i32)>> = HashMap::new();
+    for &(x, y) in cells {
+        let corners = [(x, y), (x + 1, y), (x + 1, y + 1), (x, y + 1)];
+        let beyond = [(x, y - 1), (x + 1, y), (x, y + 1), (x - 1, y)];
+        for i in 0..4 {
+            if !cells.contains(&beyond[i]) {
+                sides
+                    .entry(corners[i])
+                    .or_default()
+                    .push(corners[(i + 1) % 4]);
+            }
+        }
+    }
+
+    let mut starts: Vec<(i32, i32)> = sides.keys().copied().collect();
+    starts.sort_unstable();
+    let mut rings = Vec::new();
+    for start in starts {
+        while sides.get(&start).is_some_and(|next| !next.is_empty()) {
+            let mut ring = vec![start];
+            let mut current = start;
+            let mut direction = None;
+            loop {
+                let next = sides.get_mut(&current).unwrap();
+                // where cells touch only at a corner, turn left to keep
+                // each cell's outline separate
+                let i = match direction {
+                    Some(d) if next.len() > 1 => (0..next.len())
+                        .max_by_key(|i| turn(d, step(current, next[*i])))
+                        .unwrap(),
+                    _ => 0,
+                };
+                let to = next.swap_remove(i);
+                direction = Some(step(current, to));
+                current = to;
+                ring.push(current);
+                if current == start {
+                    break;
+                }
+            }
+            rings.push(simplify(ring));
+        }
+    }
+    rings
+}
+
+fn step(from: (i32, i32), to: (i32, i32)) -> (i32, i32) {
+    (to.0 - from.0, to.1 - from.1)
+}
+
+/// Cross product of two directions: positive for a left turn
+fn turn(a: (i32, i32), b: (i32, i32)) -> i32 {
+    a.0 * b.1 - a.1 * b.0
+}
+
+/// Drop vertices in the middle of straight runs of a closed ring
+fn simplify(ring: Vec<(i32, i32)>) -> Vec<(i32, i32)> {
+    let n = ring.len() - 1;
+    let mut kept: Vec<(i32, i32)> = (0..n)
+        .filter(|i| {
+            let previous = ring[(i + n - 1) % n];
+            let next = ring[i + 1];
+            turn(step(previous, ring[*i]), step(ring[*i], next)) != 0
+        })
+        .map(|i| ring[i])
+        .collect();
+    kept.push(kept[0]);
+    kept
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        node::Node,
+        routing::{path::snap, path::Distance, GraphBuilder},
+        tag::travel_by::Mode,
+        way::Way,
+        AreaData, TagMap,
+    };
+
+    /// Streets forming the outline of a square block about 330 m across,
+    /// with a spur leading away from one corner
+    fn nodes() -> Vec<Node> {
+        [
+            (0.0, 0.0),
+            (0.0, 0.003),
+            (0.003, 0.003),
+            (0.003, 0.0),
+            (0.0, -0.003),
+        ]
+        .iter()
+        .enumerate()
+        .map(|(i, (lat, lon))| Node {
+            id: i as i64 + 1,
+            lat: *lat,
+            lon: *lon,
+            ..Default::default()
+        })
+        .collect()
+    }
+
+    fn area(nodes: &[Node]) -> AreaData<'_> {
+        let tags: TagMap =
+            vec![("highway", "residential")].into_iter().collect();
+        let way = |id, ids: &[usize]| Way {
+            id,
+            nodes: ids.iter().map(|i| &nodes[i - 1]).collect(),
+            tags: Some(tags.clone()),
+            ..Default::default()
+        };
+        let ways =
+            vec![way(1, &[1, 2, 3]), way(2, &[3, 4, 1]), way(3, &[1, 5])];
+        AreaData {
+            ways: ways.into_iter().map(|way| (way.id, way)).collect(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn bounded_search() {
+        let nodes = nodes();
+        let area = area(&nodes);
+        let graph = GraphBuilder::new(&area, Mode::Foot).build();
+        let start = snap(&graph, (0.0, 0.0)).unwrap();
+
+        let reached = reachable(&graph, &Distance, &start, 400.0);
+        let cost = |id| reached.get(&graph.node_index(id).unwrap()).copied();
+        assert_eq!(cost(1), Some(0.0));
+        assert!((cost(5).unwrap() - 333.6).abs() < 1.0);
+        assert_eq!(cost(3), None);
+
+        let reached = reachable(&graph, &Distance, &start, 700.0);
+        let corner = reached[&graph.node_index(3).unwrap()];
+        assert!((corner - 667.2).abs() < 1.0);
+    }
+
+    #[test]
+    fn banded_polygons() {
+        let nodes = nodes();
+        let area = area(&nodes);
+        let graph = GraphBuilder::new(&area, Mode::Foot).build();
+        let start = snap(&graph, (0.0, 0.0)).unwrap();
+
+        let result =
+            isochrones(&graph, &Distance, &start, &[2000.0, 200.0], 50.0)
+                .unwrap();
+        assert_eq!(result.bands.len(), 2);
+        assert_eq!(result.bands[0].limit, 200.0);
+
+        // the short band is a single shape around the start
+        let near = &result.bands[0].polygons;
+        assert_eq!(near.len(), 1);
+        assert_eq!(near[0].len(), 1);
+        assert!(ring_contains(&near[0][0], (0.0001, 0.0001)));
+        assert!(!ring_contains(&near[0][0], (0.0015, 0.0015)));
+
+        // the whole block is reached, leaving its middle as a hole
+        let far = &result.bands[1].polygons;
+        assert_eq!(far.len(), 1);
+        assert_eq!(far[0].len(), 2);
+        assert!(ring_contains(&far[0][1], (0.0015, 0.0015)));
+        for ring in &far[0] {
+            assert_eq!(ring.first(), ring.last());
+        }
+
+        let geojson: serde_json::Value =
+            serde_json::from_str(&result.to_geojson()).unwrap();
+        assert_eq!(geojson["features"].as_array().unwrap().len(), 2);
+        assert_eq!(geojson["features"][1]["geometry"]["type"], "MultiPolygon");
+        assert_eq!(geojson["features"][0]["properties"]["limit"], 200.0);
+    }
+
+    #[test]
+    fn rejects_tiny_cells() {
+        let nodes = nodes();
+        let area = area(&nodes);
+        let graph = GraphBuilder::new(&area, Mode::Foot).build();
+        let start = snap(&graph, (0.0, 0.0)).unwrap();
+
+        for size in &[0.0, 1e-9, f64::NAN, f64::INFINITY] {
+            assert!(
+                isochrones(&graph, &Distance, &start, &[200.0], *size).is_err()
+            );
+        }
+    }
+
+    #[test]
+    fn holes_join_the_innermost_polygon() {
+        // a square frame of cells around an island that has its own hole
+        let frame = |x: i32, y: i32, low: i32, high: i32| {
+            (low..=high).contains(&x)
+                && (low..=high).contains(&y)
+                && (x == low || x == high || y == low || y == high)
+        };
+        let cells: HashSet<(i32, i32)> = (0..7)
+            .flat_map(|x| (0..7).map(move |y| (x, y)))
+            .filter(|&(x, y)| frame(x, y, 0, 6) || frame(x, y, 2, 4))
+            .collect();
+        let polygons = Grid::new((0.0, 0.0), 10.0).polygons(&cells);
+
+        assert_eq!(polygons.len(), 2);
+        for polygon in &polygons {
+            assert_eq!(polygon.len(), 2);
+        }
+    }
+}
@@ -0,0 +1,576 @@
+//! Matching GPS traces to the edges travelled, with a hidden Markov model.
+//!
+//! Follows [Newson and Krumm](https://www.microsoft.com/en-us/research/publication/hidden-markov-map-matching-noise-sparseness/):
+//! every edge near a sample is a candidate position, more likely the closer
+//! it is, and moving between candidates of consecutive samples is more
+//! likely the closer the route distance is to the straight-line distance.
+//! Routes between candidates follow the graph's directed edges, so oneway
+//! streets and turn restrictions are respected. The most likely sequence of
+//! candidates is found with the Viterbi algorithm, and each match's
+//! confidence is its probability given the whole trace.
+use super::{
+    matrix::{search, Cell, Ends},
+    path::{
+        assemble, segment_position, Distance, Position, Route, Router, Snap,
+    },
+    Graph,
+};
+use crate::geo::{haversine, Point};
+use chrono::NaiveDateTime;
+use hashbrown::HashMap;
+
+/// Position recorded by a GPS receiver
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Sample {
+    pub point: Point,
+    pub time: Option<NaiveDateTime>,
+}
+
+/// Where a sample was matched to
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Match {
+    pub position: Position,
+    /// Point on the edge
+    pub point: Point,
+    /// Meters from the sample to `point`
+    pub distance: f64,
+    /// Probability between 0 and 1 that the sample was on this edge
+    pub confidence: f64,
+}
+
+/// Result of matching a trace
+#[derive(Clone, Debug, PartialEq)]
+pub struct Matched {
+    /// Match for each sample, or `None` for samples with no edge nearby
+    pub matches: Vec<Option<Match>>,
+    /// Routes travelled between the matches. The trace is split into more
+    /// than one route where no route joins consecutive samples.
+    pub routes: Vec<Route>,
+}
+
+/// Matches GPS traces to a graph
+pub struct Matcher<'g> {
+    graph: &'g Graph,
+    radius: f64,
+    sigma: f64,
+    beta: f64,
+    candidates: usize,
+    max_speed: f64,
+}
+
+/// Longest route between samples, as a multiple of the straight-line
+/// distance, that the search for transitions looks for
+static MAX_DETOUR: f64 = 4.0;
+
+/// Candidate position for a sample
+#[derive(Clone, Copy, Debug)]
+struct Candidate {
+    position: Position,
+    point: Point,
+    distance: f64,
+}
+
+/// Probabilities of a sample's candidates, as logarithms
+struct Step {
+    sample: usize,
+    candidates: Vec<Candidate>,
+    emission: Vec<f64>,
+    /// Transitions from each candidate of the previous step to each of
+    /// this step's
+    transition: Vec<Vec<f64>>,
+    /// Probability of the likeliest sequence of candidates ending at each
+    /// candidate, and the previous candidate in that sequence
+    score: Vec<f64>,
+    previous: Vec<usize>,
+}
+
+impl<'g> Matcher<'g> {
+    pub fn new(graph: &'g Graph) -> Self {
+        Matcher {
+            graph,
+            radius: 50.0,
+            sigma: 10.0,
+            beta: 5.0,
+            candidates: 8,
+            max_speed: 200.0,
+        }
+    }
+
+    /// Meters around a sample to look for candidate edges, 50 by default
+    pub fn with_radius(mut self, radius: f64) -> Self {
+        self.radius = radius;
+        self
+    }
+
+    /// Standard deviation of GPS error in meters, 10 by default
+    pub fn with_sigma(mut self, sigma: f64) -> Self {
+        self.sigma = sigma;
+        self
+    }
+
+    /// Meters by which route distances between samples typically differ
+    /// from straight-line distances, 5 by default. Larger values accept
+    /// more roundabout routes.
+    pub fn with_beta(mut self, beta: f64) -> Self {
+        self.beta = beta;
+        self
+    }
+
+    /// Most candidate edges to consider for a sample, 8 by default
+    pub fn with_candidates(mut self, candidates: usize) -> Self {
+        self.candidates = candidates;
+        self
+    }
+
+    /// Fastest plausible speed in km/h between timed samples, 200 by default
+    pub fn with_max_speed(mut self, max_speed: f64) -> Self {
+        self.max_speed = max_speed;
+        self
+    }
+
+    /// Most likely edges travelled for the samples, in order of time
+    pub fn match_trace(&self, samples: &[Sample]) -> Matched {
+        let mut matched = Matched {
+            matches: vec![None; samples.len()],
+            routes: Vec::new(),
+        };
+        let mut segment: Vec<Step> = Vec::new();
+        for (i, sample) in samples.iter().enumerate() {
+            let candidates = self.candidates(sample.point);
+            if candidates.is_empty() {
+                continue;
+            }
+            let emission: Vec<f64> = candidates
+                .iter()
+                .map(|c| -0.5 * (c.distance / self.sigma).powi(2))
+                .collect();
+            let mut step = Step {
+                sample: i,
+                candidates,
+                score: emission.clone(),
+                previous: Vec::new(),
+                emission,
+                transition: Vec::new(),
+            };
+            if let Some(before) = segment.last() {
+                step.transition = self.transitions(
+                    &samples[before.sample],
+                    sample,
+                    &before.candidates,
+                    &step.candidates,
+                );
+                let (score, previous) = viterbi(before, &step);
+                if score.iter().any(|s| s.is_finite()) {
+                    step.score = score;
+                    step.previous = previous;
+                } else {
+                    // no route from the likely candidates so far
+                    self.finish(&mut matched, &segment);
+                    segment.clear();
+                    step.transition.clear();
+                }
+            }
+            segment.push(step);
+        }
+        self.finish(&mut matched, &segment);
+        matched
+    }
+
+    /// Edges near the point, nearest first
+    fn candidates(&self, point: Point) -> Vec<Candidate> {
+        let graph = self.graph;
+        // nearest point of each edge within the radius
+        let mut nearest: HashMap<u32, Candidate> = HashMap::new();
+        for (edge, start) in graph.spatial.within(point, self.radius) {
+            let (distance, projected, position) =
+                segment_position(graph, point, edge, start);
+            if distance <= self.radius
+                && nearest.get(&edge).is_none_or(|n| distance < n.distance)
+            {
+                nearest.insert(
+                    edge,
+                    Candidate {
+                        position,
+                        point: projected,
+                        distance,
+                    },
+                );
+            }
+        }
+        let mut candidates: Vec<Candidate> =
+            nearest.into_iter().map(|(_, c)| c).collect();
+        candidates.sort_by(|a, b| {
+            a.distance
+                .total_cmp(&b.distance)
+                .then(a.position.edge.cmp(&b.position.edge))
+        });
+        candidates.truncate(self.candidates);
+        candidates
+    }
+
+    /// Transition probabilities between the candidates of two samples
+    fn transitions(
+        &self,
+        from: &Sample,
+        to: &Sample,
+        sources: &[Candidate],
+        targets: &[Candidate],
+    ) -> Vec<Vec<f64>> {
+        let straight = haversine(from.point, to.point);
+        let mut limit = straight * MAX_DETOUR + 2.0 * self.radius;
+        if let (Some(a), Some(b)) = (from.time, to.time) {
+            let seconds = (b - a).num_milliseconds() as f64 / 1000.0;
+            let reach = self.max_speed / 3.6 * seconds.max(0.0);
+            limit = limit.min(reach + 2.0 * self.radius);
+        }
+
+        let mut ends: Ends = HashMap::new();
+        for (i, target) in targets.iter().enumerate() {
+            let position = target.position;
+            ends.entry(position.edge)
+                .or_default()
+                .push((i, position.offset));
+        }
+
+        sources
+            .iter()
+            .map(|source| {
+                let snap = Snap {
+                    point: source.point,
+                    distance: 0.0,
+                    positions: vec![source.position],
+                };
+                let mut row = vec![None; targets.len()];
+                search(self.graph, &Distance, &snap, &ends, &mut row, limit);
+                row.iter()
+                    .zip(targets)
+                    .map(|(cell, target)| {
+                        let distance = match cell {
+                            _ if self.stays(source, target) => 0.0,
+                            Some(Cell { distance, .. })
+                                if *distance <= limit =>
+                            {
+                                *distance
+                            }
+                            _ => return f64::NEG_INFINITY,
+                        };
+                        -(distance - straight).abs() / self.beta
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+
+    /// Whether the target is so little behind the source on the same edge
+    /// that it's more likely GPS error than turning back
+    fn stays(&self, source: &Candidate, target: &Candidate) -> bool {
+        let (a, b) = (source.position, target.position);
+        a.edge == b.edge
+            && b.offset < a.offset
+            && a.offset - b.offset < self.sigma
+    }
+
+    /// Pick the most likely candidates of a segment of the trace joined by
+    /// routes, and add them and their route to the result
+    fn finish(&self, matched: &mut Matched, segment: &[Step]) {
+        let last = match segment.last() {
+            Some(last) => last,
+            None => return,
+        };
+
+        let best = |scores: &[f64]| {
+            (0..scores.len())
+                .max_by(|a, b| scores[*a].total_cmp(&scores[*b]))
+                .unwrap()
+        };
+        let mut chosen = vec![best(&last.score); segment.len()];
+        for t in (1..segment.len()).rev() {
+            chosen[t - 1] = segment[t].previous[chosen[t]];
+        }
+
+        // forward-backward for the probability of each candidate
+        let mut forward = vec![segment[0].emission.clone()];
+        for step in &segment[1..] {
+            let before = forward.last().unwrap();
+            let next = step
+                .emission
+                .iter()
+                .enumerate()
+                .map(|(j, emission)| {
+                    let into = before
+                        .iter()
+                        .enumerate()
+                        .map(|(i, f)| f + step.transition[i][j]);
+                    log_sum_exp(into) + emission
+                })
+                .collect();
+            forward.push(next);
+        }
+        let mut backward = vec![vec![0.0; last.candidates.len()]];
+        for t in (1..segment.len()).rev() {
+            let step = &segment[t];
+            let after = backward.last().unwrap();
+            let next = (0..segment[t - 1].candidates.len())
+                .map(|i| {
+                    log_sum_exp((0..step.candidates.len()).map(|j| {
+                        step.transition[i][j] + step.emission[j] + after[j]
+                    }))
+                })
+                .collect();
+            backward.push(next);
+        }
+        backward.reverse();
+        let total = log_sum_exp(forward[segment.len() - 1].iter().copied());
+
+        for (t, step) in segment.iter().enumerate() {
+            let i = chosen[t];
+            let candidate = step.candidates[i];
+            let probability = (forward[t][i] + backward[t][i] - total).exp();
+            matched.matches[step.sample] = Some(Match {
+                position: candidate.position,
+                point: candidate.point,
+                distance: candidate.distance,
+                confidence: probability.clamp(0.0, 1.0),
+            });
+        }
+
+        // join the routes between the matched positions
+        let router = Router::new(self.graph, Distance);
+        let snap = |candidate: &Candidate| Snap {
+            point: candidate.point,
+            distance: 0.0,
+            positions: vec![candidate.position],
+        };
+        let join = |start: Candidate, end: Candidate, edges| {
+            let (from, to) = (start.position.offset, end.position.offset);
+            let mut route = assemble(self.graph, 0.0, edges, from, to);
+            route.cost = route.distance;
+            route
+        };
+        let mut start = segment[0].candidates[chosen[0]];
+        let mut edges = vec![start.position.edge];
+        let mut end = start;
+        for (t, step) in segment.iter().enumerate().skip(1) {
+            let source = segment[t - 1].candidates[chosen[t - 1]];
+            let target = step.candidates[chosen[t]];
+            if self.stays(&end, &target) {
+                continue;
+            }
+            // after staying put, the route found for the transition starts
+            // behind where the trace has got to
+            let route =
+                router.route_between(&snap(&end), &snap(&target)).or_else(
+                    || router.route_between(&snap(&source), &snap(&target)),
+                );
+            match route {
+                Some(route) => {
+                    edges.extend(route.edges.into_iter().skip(1));
+                    end = target;
+                }
+                // drifting back along the edge, step by step, from where the
+                // trace has got to
+                None if target.position.edge == end.position.edge
+                    && target.position.offset < end.position.offset => {}
+                None => {
+                    let done = std::mem::replace(
+                        &mut edges,
+                        vec![target.position.edge],
+                    );
+                    matched.routes.push(join(start, end, done));
+                    start = target;
+                    end = target;
+                }
+            }
+        }
+        matched.routes.push(join(start, end, edges));
+    }
+}
+
+/// Likeliest sequences ending at each candidate of a step, given those
+/// ending at the step before
+fn viterbi(before: &Step, step: &Step) -> (Vec<f64>, Vec<usize>) {
+    step.emission
+        .iter()
+        .enumerate()
+        .map(|(j, emission)| {
+            let (i, score) = before
+                .score
+                .iter()
+                .enumerate()
+                .map(|(i, s)| (i, s + step.transition[i][j]))
+                .max_by(|a, b| a.1.total_cmp(&b.1))
+                .unwrap();
+            (score + emission, i)
+        })
+        .unzip()
+}
+
+/// Logarithm of the sum of the numbers whose logarithms are given
+fn log_sum_exp<I: Iterator<Item = f64>>(values: I) -> f64 {
+    let values: Vec<f64> = values.collect();
+    let max = values.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+    if max == f64::NEG_INFINITY {
+        return max;
+    }
+    max + values.iter().map(|v| (v - max).exp()).sum::<f64>().ln()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        node::Node, routing::GraphBuilder, tag::travel_by::Mode, way::Way,
+        AreaData, TagMap,
+    };
+    use chrono::NaiveDate;
+
+    /// Two parallel streets 33 m apart joined at both ends: the southern
+    /// one (1 2 3) oneway to the east, the northern one (4 5 6) two-way
+    fn nodes() -> Vec<Node> {
+        (1..=6)
+            .map(|id| Node {
+                id,
+                lat: if id > 3 { 0.0003 } else { 0.0 },
+                lon: ((id - 1) % 3) as f32 * 0.002,
+                ..Default::default()
+            })
+            .collect()
+    }
+
+    fn area(nodes: &[Node]) -> AreaData<'_> {
+        let way =
+            |id, ids: &[usize], tags: &[(&'static str, &'static str)]| Way {
+                id,
+                nodes: ids.iter().map(|i| &nodes[i - 1]).collect(),
+                tags: Some(tags.iter().copied().collect::<TagMap>()),
+                ..Default::default()
+            };
+        let street = [("highway", "residential")];
+        let ways = vec![
+            way(
+                1,
+                &[1, 2, 3],
+                &[("highway", "residential"), ("oneway", "yes")],
+            ),
+            way(2, &[4, 5, 6], &street),
+            way(3, &[1, 4], &street),
+            way(4, &[3, 6], &street),
+        ];
+        AreaData {
+            ways: ways.into_iter().map(|way| (way.id, way)).collect(),
+            ..Default::default()
+        }
+    }
+
+    /// Samples 11 m north of the southern street every 55 m, a second apart
+    fn trace(lons: impl Iterator<Item = f32>) -> Vec<Sample> {
+        let start = NaiveDate::from_ymd_opt(2020, 6, 1)
+            .unwrap()
+            .and_hms_opt(12, 0, 0)
+            .unwrap();
+        lons.enumerate()
+            .map(|(i, lon)| Sample {
+                point: (0.0001, lon),
+                time: Some(start + chrono::Duration::seconds(i as i64)),
+            })
+            .collect()
+    }
+
+    fn ways(graph: &Graph, matched: &Matched) -> Vec<i64> {
+        matched
+            .matches
+            .iter()
+            .map(|m| graph.way(graph.edge(m.unwrap().position.edge)).id)
+            .collect()
+    }
+
+    #[test]
+    fn follows_oneway_streets() {
+        let nodes = nodes();
+        let area = area(&nodes);
+        let graph = GraphBuilder::new(&area, Mode::Motorcar).build();
+        let matcher = Matcher::new(&graph);
+
+        let east = trace((1..8).map(|i| i as f32 * 0.0005));
+        let matched = matcher.match_trace(&east);
+        assert_eq!(ways(&graph, &matched), vec![1; 7]);
+        assert_eq!(matched.routes.len(), 1);
+        assert_eq!(matched.routes[0].ways, vec![1]);
+        assert!((matched.routes[0].distance - 333.6).abs() < 1.0);
+        for m in matched.matches.iter().flatten() {
+            assert!((m.distance - 11.1).abs() < 0.1);
+            assert!(m.confidence > 0.9);
+        }
+
+        // the southern street is nearer, but can't be travelled westwards
+        let west = trace((1..8).rev().map(|i| i as f32 * 0.0005));
+        let matched = matcher.match_trace(&west);
+        assert_eq!(ways(&graph, &matched), vec![2; 7]);
+        assert_eq!(matched.routes[0].ways, vec![2]);
+    }
+
+    #[test]
+    fn splits_at_gaps() {
+        let nodes = nodes();
+        let area = area(&nodes);
+        let graph = GraphBuilder::new(&area, Mode::Motorcar).build();
+        let matcher = Matcher::new(&graph).with_max_speed(50.0);
+
+        // a sample far from any street, and a jump too far to travel in a
+        // second
+        let mut samples =
+            trace([0.0005, 0.001, 0.0015, 0.0035].iter().copied());
+        samples.insert(
+            2,
+            Sample {
+                point: (0.01, 0.001),
+                time: None,
+            },
+        );
+        let matched = matcher.match_trace(&samples);
+        assert!(matched.matches[2].is_none());
+        assert_eq!(matched.matches.iter().flatten().count(), 4);
+        assert_eq!(matched.routes.len(), 2);
+        assert!((matched.routes[0].distance - 111.2).abs() < 1.0);
+        assert_eq!(matched.routes[1].distance, 0.0);
+    }
+
+    #[test]
+    fn drifts_back_along_oneway() {
+        let nodes: Vec<Node> = (1..=2)
+            .map(|id| Node {
+                id,
+                lon: (id - 1) as f32 * 0.01,
+                ..Default::default()
+            })
+            .collect();
+        let tags: TagMap = vec![("highway", "residential"), ("oneway", "yes")]
+            .into_iter()
+            .collect();
+        let way = Way {
+            id: 1,
+            nodes: nodes.iter().collect(),
+            tags: Some(tags),
+            ..Default::default()
+        };
+        let area = AreaData {
+            ways: vec![(1, way)].into_iter().collect(),
+            ..Default::default()
+        };
+        let graph = GraphBuilder::new(&area, Mode::Motorcar).build();
+
+        // each sample a little behind the one before, the last further
+        // behind the first than the trace is trusted to stay put
+        let samples: Vec<Sample> = [0.0009, 0.000855, 0.0008]
+            .iter()
+            .map(|lon| Sample {
+                point: (0.0, *lon),
+                time: None,
+            })
+            .collect();
+        let matched = Matcher::new(&graph).match_trace(&samples);
+        assert_eq!(matched.matches.iter().flatten().count(), 3);
+        assert_eq!(matched.routes.len(), 1);
+        assert_eq!(matched.routes[0].ways, vec![1]);
+    }
+}
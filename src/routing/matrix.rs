@@ -0,0 +1,330 @@
+//! Travel costs between every pair of many sources and targets.
+//!
+//! Each row of the matrix comes from one search outwards from its source
+//! that stops once every target is settled, and the rows are shared out
+//! between threads.
+use super::{
+    path::{snap, Cost, Entry, Position, Snap},
+    Graph,
+};
+use crate::geo::Point;
+use hashbrown::HashMap;
+use std::{collections::BinaryHeap, num::NonZeroUsize, thread};
+
+/// Best route from a source to a target
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Cell {
+    /// Total cost by the search's cost function
+    pub cost: f64,
+    /// Meters
+    pub distance: f64,
+}
+
+/// Routes from each source to each target, with `None` marking targets
+/// that can't be reached
+#[derive(Clone, Debug, PartialEq)]
+pub struct Matrix {
+    sources: usize,
+    targets: usize,
+    cells: Vec<Option<Cell>>,
+}
+
+impl Matrix {
+    pub fn sources(&self) -> usize {
+        self.sources
+    }
+
+    pub fn targets(&self) -> usize {
+        self.targets
+    }
+
+    pub fn get(&self, source: usize, target: usize) -> Option<Cell> {
+        assert!(source < self.sources && target < self.targets);
+        self.cells[source * self.targets + target]
+    }
+
+    /// Routes from a source to every target
+    pub fn row(&self, source: usize) -> &[Option<Cell>] {
+        &self.cells[source * self.targets..(source + 1) * self.targets]
+    }
+
+    pub fn rows(&self) -> impl Iterator<Item = &[Option<Cell>]> {
+        (0..self.sources).map(move |source| self.row(source))
+    }
+}
+
+/// Matrix between coordinates after snapping them to the graph
+pub fn matrix<C: Cost + Sync>(
+    graph: &Graph,
+    cost: &C,
+    sources: &[Point],
+    targets: &[Point],
+) -> Matrix {
+    let snaps = |points: &[Point]| -> Vec<Option<Snap>> {
+        points.iter().map(|point| snap(graph, *point)).collect()
+    };
+    compute(graph, cost, &snaps(sources), &snaps(targets))
+}
+
+/// Matrix between snapped places
+pub fn matrix_between<C: Cost + Sync>(
+    graph: &Graph,
+    cost: &C,
+    sources: &[Snap],
+    targets: &[Snap],
+) -> Matrix {
+    let snaps = |snaps: &[Snap]| -> Vec<Option<Snap>> {
+        snaps.iter().cloned().map(Some).collect()
+    };
+    compute(graph, cost, &snaps(sources), &snaps(targets))
+}
+
+fn compute<C: Cost + Sync>(
+    graph: &Graph,
+    cost: &C,
+    sources: &[Option<Snap>],
+    targets: &[Option<Snap>],
+) -> Matrix {
+    let mut matrix = Matrix {
+        sources: sources.len(),
+        targets: targets.len(),
+        cells: vec![None; sources.len() * targets.len()],
+    };
+    if matrix.cells.is_empty() {
+        return matrix;
+    }
+
+    // where the targets are along each edge
+    let mut ends: Ends = HashMap::new();
+    for (i, target) in targets.iter().enumerate() {
+        for position in target.iter().flat_map(|snap| &snap.positions) {
+            ends.entry(position.edge)
+                .or_default()
+                .push((i, position.offset));
+        }
+    }
+
+    let threads = thread::available_parallelism()
+        .map_or(1, NonZeroUsize::get)
+        .min(sources.len());
+    let rows_per_thread = sources.len().div_ceil(threads);
+    let ends = &ends;
+    thread::scope(|scope| {
+        let chunks = matrix.cells.chunks_mut(rows_per_thread * targets.len());
+        for (chunk, sources) in chunks.zip(sources.chunks(rows_per_thread)) {
+            scope.spawn(move || {
+                let rows = chunk.chunks_mut(targets.len());
+                for (row, source) in rows.zip(sources) {
+                    if let Some(source) = source {
+                        search(graph, cost, source, ends, row, f64::INFINITY);
+                    }
+                }
+            });
+        }
+    });
+    matrix
+}
+
+/// Targets along each edge, by their index and offset
+pub(super) type Ends = HashMap<u32, Vec<(usize, f64)>>;
+
+/// Fill a row of the matrix with the best routes from the source, leaving
+/// targets that cost more than the limit to reach unless they're on the
+/// way to others
+pub(super) fn search<C: Cost>(
+    graph: &Graph,
+    cost: &C,
+    from: &Snap,
+    ends: &Ends,
+    row: &mut [Option<Cell>],
+    limit: f64,
+) {
+    let cost = |edge: u32| cost.cost(graph, graph.edge(edge));
+    // cost of the part of an edge up to the offset
+    let partial = |edge: u32, offset: f64| {
+        let length = f64::from(graph.edge(edge).distance);
+        if length > 0.0 {
+            cost(edge) * (offset / length).min(1.0)
+        } else {
+            0.0
+        }
+    };
+
+    // cost and distance on reaching the end of each edge
+    let mut labels: HashMap<u32, Cell> = HashMap::new();
+    let mut heap = BinaryHeap::new();
+
+    for &Position { edge, offset } in &from.positions {
+        let done = partial(edge, offset);
+        for (target, end) in ends.get(&edge).into_iter().flatten() {
+            if *end >= offset {
+                consider(
+                    row,
+                    *target,
+                    Cell {
+                        cost: partial(edge, *end) - done,
+                        distance: end - offset,
+                    },
+                );
+            }
+        }
+        let label = Cell {
+            cost: cost(edge) - done,
+            distance: f64::from(graph.edge(edge).distance) - offset,
+        };
+        if labels.get(&edge).is_none_or(|c| label.cost < c.cost) {
+            labels.insert(edge, label);
+            heap.push(Entry(label.cost, edge));
+        }
+    }
+
+    while let Some(Entry(g, a)) = heap.pop() {
+        let label = labels[&a];
+        if g > label.cost {
+            // stale entry
+            continue;
+        }
+        if g > limit {
+            return;
+        }
+        // later completions all cost at least `g`, so stop once no target
+        // could improve
+        if row.iter().all(|cell| cell.is_some_and(|c| c.cost <= g)) {
+            return;
+        }
+        let node = graph.edge(a).target;
+        for b in graph.out_edges(node) {
+            if graph.is_forbidden(a, b) {
+                continue;
+            }
+            for (target, end) in ends.get(&b).into_iter().flatten() {
+                let cell = Cell {
+                    cost: g + partial(b, *end),
+                    distance: label.distance + end,
+                };
+                consider(row, *target, cell);
+            }
+            let next = Cell {
+                cost: g + cost(b),
+                distance: label.distance + f64::from(graph.edge(b).distance),
+            };
+            if labels.get(&b).is_none_or(|c| next.cost < c.cost) {
+                labels.insert(b, next);
+                heap.push(Entry(next.cost, b));
+            }
+        }
+    }
+}
+
+/// Keep the cheaper of the target's best route so far and a new one
+fn consider(row: &mut [Option<Cell>], target: usize, cell: Cell) {
+    if row[target].is_none_or(|best| cell.cost < best.cost) {
+        row[target] = Some(cell);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        node::Node,
+        routing::{
+            path::{Algorithm, Router, Time},
+            GraphBuilder,
+        },
+        tag::travel_by::Mode,
+        way::Way,
+        AreaData, TagMap,
+    };
+
+    /// A 3x3 grid of residential streets with a separate street to the east
+    /// that no other street joins
+    fn nodes() -> Vec<Node> {
+        let mut nodes: Vec<Node> = (0..9)
+            .map(|i| Node {
+                id: i + 1,
+                lat: (i / 3) as f32 * 0.002,
+                lon: (i % 3) as f32 * 0.002,
+                ..Default::default()
+            })
+            .collect();
+        for (id, lat) in [(10, 0.0), (11, 0.004)].iter() {
+            nodes.push(Node {
+                id: *id,
+                lat: *lat,
+                lon: 0.01,
+                ..Default::default()
+            });
+        }
+        nodes
+    }
+
+    fn area(nodes: &[Node]) -> AreaData<'_> {
+        let tags: TagMap =
+            vec![("highway", "residential")].into_iter().collect();
+        let way = |id, ids: &[usize]| Way {
+            id,
+            nodes: ids.iter().map(|i| &nodes[i - 1]).collect(),
+            tags: Some(tags.clone()),
+            ..Default::default()
+        };
+        let ways = vec![
+            way(1, &[1, 2, 3]),
+            way(2, &[4, 5, 6]),
+            way(3, &[7, 8, 9]),
+            way(4, &[1, 4, 7]),
+            way(5, &[2, 5, 8]),
+            way(6, &[3, 6, 9]),
+            way(7, &[10, 11]),
+        ];
+        AreaData {
+            ways: ways.into_iter().map(|way| (way.id, way)).collect(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn matches_single_routes() {
+        let nodes = nodes();
+        let area = area(&nodes);
+        let graph = GraphBuilder::new(&area, Mode::Motorcar).build();
+        let router =
+            Router::new(&graph, Time).with_algorithm(Algorithm::Dijkstra);
+
+        let points: Vec<Point> = vec![
+            (0.0, 0.0005),
+            (0.0021, 0.002),
+            (0.004, 0.0033),
+            (0.0013, 0.004),
+            (0.0, 0.0015),
+        ];
+        let matrix = matrix(&graph, &Time, &points[..3], &points);
+        assert_eq!((matrix.sources(), matrix.targets()), (3, 5));
+        for (i, row) in matrix.rows().enumerate() {
+            for (j, cell) in row.iter().enumerate() {
+                let route = router.route(points[i], points[j]).unwrap();
+                let cell = cell.unwrap();
+                assert!((cell.cost - route.cost).abs() < 1e-6);
+                assert!((cell.distance - route.distance).abs() < 1e-3);
+            }
+        }
+        assert_eq!(matrix.get(1, 1).unwrap().cost, 0.0);
+    }
+
+    #[test]
+    fn marks_unreachable_targets() {
+        let nodes = nodes();
+        let area = area(&nodes);
+        let graph = GraphBuilder::new(&area, Mode::Motorcar).build();
+
+        let points = vec![(0.0, 0.0), (0.002, 0.01)];
+        let matrix = matrix(&graph, &Time, &points, &points);
+        assert!(matrix.get(0, 0).is_some());
+        assert_eq!(matrix.get(0, 1), None);
+        assert_eq!(matrix.get(1, 0), None);
+        assert!((matrix.get(1, 1).unwrap().distance).abs() < 1e-9);
+
+        let empty = super::matrix(&graph, &Time, &points, &[]);
+        assert_eq!(empty.row(1), &[]);
+    }
+}
@@ -0,0 +1,140 @@
+//! Routing over the ways of `AreaData`.
+//!
+//! `GraphBuilder` turns the ways a mode of transport may use into a compact
+//! graph in [compressed sparse row](https://en.wikipedia.org/wiki/Sparse_matrix#Compressed_sparse_row_(CSR,_CRS_or_Yale_format))
+//! form: ways are split into edges at every node shared with another way, and
+//! the outgoing edges of each node are stored contiguously. Turn restrictions
+//! become forbidden transitions from one edge to the next. A `Profile` may
+//! narrow the ways used and weight edges by travel time. Routes are found
+//! with the searches in `path`, or with a contraction hierarchy from `ch`
+//! when there are many queries on the same graph. `matrix` gives the costs
+//! between many sources and targets at once, `isochrone` the area reachable
+//! within a limit, and `matching` the edges travelled along a GPS trace.
+mod build;
+pub mod ch;
+pub mod isochrone;
+pub mod matching;
+pub mod matrix;
+pub mod path;
+pub mod profile;
+mod spatial;
+
+pub use build::GraphBuilder;
+
+use crate::{geo::Point, tag::highway::Highway, ElementID};
+use hashbrown::HashMap;
+use spatial::SpatialIndex;
+use std::ops::Range;
+
+/// Junction or end of a way in the graph
+#[derive(Clone, Debug, PartialEq)]
+pub struct GraphNode {
+    /// ID of the OSM node
+    pub id: ElementID,
+    pub point: Point,
+}
+
+/// Details of a way that edges were built from
+#[derive(Clone, Debug, PartialEq)]
+pub struct WayInfo {
+    pub id: ElementID,
+    pub highway: Option<Highway>,
+    /// Effective speed limit in km/h
+    pub maxspeed: Option<f32>,
+}
+
+/// Part of a way between two junctions, travelled in one direction
+#[derive(Clone, Debug, PartialEq)]
+pub struct Edge {
+    pub source: u32,
+    pub target: u32,
+    /// Index of the way in `Graph::ways`
+    pub way: u32,
+    /// Meters
+    pub distance: f32,
+    /// Cost of travelling the edge, equal to the distance unless a profile
+    /// assigns it
+    pub weight: f32,
+    /// Whether the edge runs against the node order of its way
+    pub reverse: bool,
+    /// Range of the edge's nodes in `Graph::shapes`
+    shape: (u32, u32),
+}
+
+/// Routing graph for one mode of transport
+#[derive(Clone, Debug, Default)]
+pub struct Graph {
+    nodes: Vec<GraphNode>,
+    /// Index in `edges` of the first outgoing edge of each node, followed by
+    /// the number of edges
+    first_out: Vec<u32>,
+    edges: Vec<Edge>,
+    /// Nodes along each edge, including both ends, in travel order
+    shapes: Vec<(ElementID, Point)>,
+    ways: Vec<WayInfo>,
+    /// Sorted pairs of edges that mustn't be travelled one after the other
+    forbidden: Vec<(u32, u32)>,
+    index: HashMap<ElementID, u32>,
+    /// Segments of the edges' shapes by where they lie
+    spatial: SpatialIndex,
+}
+
+impl Graph {
+    pub fn node_count(&self) -> usize {
+        self.nodes.len()
+    }
+
+    pub fn edge_count(&self) -> usize {
+        self.edges.len()
+    }
+
+    pub fn node(&self, node: u32) -> &GraphNode {
+        &self.nodes[node as usize]
+    }
+
+    pub fn nodes(&self) -> &[GraphNode] {
+        &self.nodes
+    }
+
+    /// Index of the graph node for an OSM node ID, if it's a junction or the
+    /// end of a routable way
+    pub fn node_index(&self, id: ElementID) -> Option<u32> {
+        self.index.get(&id).copied()
+    }
+
+    pub fn edge(&self, edge: u32) -> &Edge {
+        &self.edges[edge as usize]
+    }
+
+    pub fn edges(&self) -> &[Edge] {
+        &self.edges
+    }
+
+    /// Indexes of the edges leaving the node
+    pub fn out_edges(&self, node: u32) -> Range<u32> {
+        self.first_out[node as usize]..self.first_out[node as usize + 1]
+    }
+
+    pub fn way(&self, edge: &Edge) -> &WayInfo {
+        &self.ways[edge.way as usize]
+    }
+
+    pub fn ways(&self) -> &[WayInfo] {
+        &self.ways
+    }
+
+    /// OSM node IDs and positions along the edge in travel order
+    pub fn shape(&self, edge: &Edge) -> &[(ElementID, Point)] {
+        &self.shapes[edge.shape.0 as usize..edge.shape.1 as usize]
+    }
+
+    /// Whether a turn restriction forbids travelling edge `to` directly
+    /// after edge `from`
+    pub fn is_forbidden(&self, from: u32, to: u32) -> bool {
+        self.forbidden.binary_search(&(from, to)).is_ok()
+    }
+
+    pub fn forbidden_turns(&self) -> &[(u32, u32)] {
+        &self.forbidden
+    }
+}
@@ -0,0 +1,595 @@
+//! Point-to-point routing on a `Graph` with Dijkstra's algorithm or A*.
+//!
+//! Searches run over edges rather than nodes so that forbidden turns are
+//! respected, and start and end part way along the edges nearest to the
+//! requested coordinates.
+use super::{Edge, Graph};
+use crate::{
+    geo::{haversine, Point},
+    tag::highway::Highway,
+    ElementID,
+};
+use hashbrown::HashMap;
+use std::{cmp::Ordering, collections::BinaryHeap};
+
+/// Cost of travelling an edge, such as its length or travel time
+pub trait Cost {
+    fn cost(&self, graph: &Graph, edge: &Edge) -> f64;
+
+    /// Lowest cost of a meter of travel anywhere in the graph, which scales
+    /// the straight-line distance used as the A* heuristic. Zero turns A*
+    /// into Dijkstra's algorithm.
+    fn min_cost_per_meter(&self, _graph: &Graph) -> f64 {
+        0.0
+    }
+}
+
+/// Custom costs from a function of the edge
+impl<F: Fn(&Graph, &Edge) -> f64> Cost for F {
+    fn cost(&self, graph: &Graph, edge: &Edge) -> f64 {
+        self(graph, edge)
+    }
+}
+
+/// Length in meters
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Distance;
+
+impl Cost for Distance {
+    fn cost(&self, _graph: &Graph, edge: &Edge) -> f64 {
+        f64::from(edge.distance)
+    }
+
+    fn min_cost_per_meter(&self, _graph: &Graph) -> f64 {
+        1.0
+    }
+}
+
+/// Driving time in seconds at the speed limit, or at the typical speed of
+/// the highway class where there is none
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Time;
+
+impl Time {
+    /// Speed in km/h along the edge
+    pub fn speed(graph: &Graph, edge: &Edge) -> f32 {
+        let way = graph.way(edge);
+        way.maxspeed
+            .filter(|speed| speed.is_finite() && *speed > 0.0)
+            .or_else(|| way.highway.as_ref().map(default_speed))
+            .unwrap_or(DEFAULT_SPEED)
+    }
+}
+
+impl Cost for Time {
+    fn cost(&self, graph: &Graph, edge: &Edge) -> f64 {
+        f64::from(edge.distance) * 3.6 / f64::from(Time::speed(graph, edge))
+    }
+
+    fn min_cost_per_meter(&self, graph: &Graph) -> f64 {
+        let fastest = graph
+            .edges()
+            .iter()
+            .map(|edge| Time::speed(graph, edge))
+            .fold(DEFAULT_SPEED, f32::max);
+        3.6 / f64::from(fastest)
+    }
+}
+
+/// The weights stored on the edges, as assigned by a routing profile
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Weight;
+
+impl Cost for Weight {
+    fn cost(&self, _graph: &Graph, edge: &Edge) -> f64 {
+        f64::from(edge.weight)
+    }
+
+    fn min_cost_per_meter(&self, graph: &Graph) -> f64 {
+        graph
+            .edges()
+            .iter()
+            .filter(|edge| edge.distance > 0.0)
+            .map(|edge| f64::from(edge.weight / edge.distance))
+            .reduce(f64::min)
+            .map_or(0.0, |min| min.max(0.0))
+    }
+}
+
+/// Speed in km/h for edges of an unknown highway class
+pub static DEFAULT_SPEED: f32 = 30.0;
+
+/// Typical driving speed in km/h on a highway class without a speed limit
+pub fn default_speed(highway: &Highway) -> f32 {
+    match highway {
+        Highway::Motorway => 110.0,
+        Highway::Trunk => 90.0,
+        Highway::Primary => 70.0,
+        Highway::Secondary => 60.0,
+        Highway::Tertiary => 50.0,
+        Highway::MotorwayLink | Highway::TrunkLink => 60.0,
+        Highway::PrimaryLink
+        | Highway::SecondaryLink
+        | Highway::TertiaryLink => 40.0,
+        Highway::Unclassified | Highway::Residential | Highway::Road => 30.0,
+        Highway::Service | Highway::Track => 20.0,
+        Highway::LivingStreet => 10.0,
+        Highway::Pedestrian
+        | Highway::Footway
+        | Highway::Steps
+        | Highway::Corridor
+        | Highway::Path
+        | Highway::Platform => 5.0,
+        Highway::Cycleway | Highway::Bridleway => 15.0,
+        _ => DEFAULT_SPEED,
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Algorithm {
+    Dijkstra,
+    /// Dijkstra's algorithm guided towards the destination by the
+    /// straight-line distance
+    AStar,
+}
+
+/// Place along an edge
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Position {
+    pub edge: u32,
+    /// Meters from the start of the edge
+    pub offset: f64,
+}
+
+/// Coordinates moved onto the nearest edges of the graph
+#[derive(Clone, Debug, PartialEq)]
+pub struct Snap {
+    /// Nearest point on the graph
+    pub point: Point,
+    /// Meters from the requested coordinates to `point`
+    pub distance: f64,
+    /// Every edge passing through `point`, such as both directions of a
+    /// two-way street or all the edges at a junction
+    pub positions: Vec<Position>,
+}
+
+/// Snap the point to the nearest edge of the graph, or `None` if the graph
+/// has no edges
+pub fn snap(graph: &Graph, point: Point) -> Option<Snap> {
+    let index = &graph.spatial;
+    if index.segments.is_empty() {
+        return None;
+    }
+    let mut best: Option<Snap> = None;
+
+    // search outwards until no unsearched segment can be nearer
+    for ring in 0.. {
+        for (edge, start) in index.ring(point, ring) {
+            let (distance, projected, position) =
+                segment_position(graph, point, edge, start);
+
+            match &mut best {
+                Some(snap) if (distance - snap.distance).abs() < 0.01 => {
+                    if snap.positions.iter().all(|p| p.edge != edge) {
+                        snap.positions.push(position);
+                    }
+                }
+                Some(snap) if distance > snap.distance => (),
+                _ => {
+                    best = Some(Snap {
+                        point: projected,
+                        distance,
+                        positions: vec![position],
+                    })
+                }
+            }
+        }
+        let done = best
+            .as_ref()
+            .is_some_and(|b| b.distance + 0.01 < index.reach(point, ring));
+        if done || index.covers(point, ring) {
+            break;
+        }
+    }
+    best
+}
+
+/// Distance from the point to the segment of the edge starting at the shape
+/// index, with the nearest point on the segment and its place along the edge
+pub(super) fn segment_position(
+    graph: &Graph,
+    point: Point,
+    edge: u32,
+    start: u32,
+) -> (f64, Point, Position) {
+    let shape = graph.shape(graph.edge(edge));
+    let start = (start - graph.edge(edge).shape.0) as usize;
+    let offset: f64 = shape[..=start]
+        .windows(2)
+        .map(|pair| haversine(pair[0].1, pair[1].1))
+        .sum();
+    let (a, b) = (shape[start].1, shape[start + 1].1);
+    let t = project(point, a, b);
+    let projected = interpolate(a, b, t);
+    let position = Position {
+        edge,
+        offset: offset + t * haversine(a, b),
+    };
+    (haversine(point, projected), projected, position)
+}
+
+/// Fraction of the way from `a` to `b` of the point nearest to `p`, on a
+/// plane scaled for the latitude
+pub(super) fn project(p: Point, a: Point, b: Point) -> f64 {
+    let scale = f64::from(a.0).to_radians().cos();
+    let (ax, ay) = (f64::from(a.1) * scale, f64::from(a.0));
+    let (bx, by) = (f64::from(b.1) * scale, f64::from(b.0));
+    let (px, py) = (f64::from(p.1) * scale, f64::from(p.0));
+    let (dx, dy) = (bx - ax, by - ay);
+    let length = dx * dx + dy * dy;
+    if length == 0.0 {
+        return 0.0;
+    }
+    (((px - ax) * dx + (py - ay) * dy) / length).clamp(0.0, 1.0)
+}
+
+pub(super) fn interpolate(a: Point, b: Point, t: f64) -> Point {
+    let t = t as f32;
+    (a.0 + (b.0 - a.0) * t, a.1 + (b.1 - a.1) * t)
+}
+
+/// Path found between two places
+#[derive(Clone, Debug, PartialEq)]
+pub struct Route {
+    /// Total cost by the search's cost function
+    pub cost: f64,
+    /// Meters
+    pub distance: f64,
+    /// Graph edges in travel order, including the partly travelled first and
+    /// last ones
+    pub edges: Vec<u32>,
+    /// IDs of the ways travelled, listing a way again only when the route
+    /// leaves and returns to it
+    pub ways: Vec<ElementID>,
+    /// IDs of the OSM nodes passed
+    pub nodes: Vec<ElementID>,
+    /// Line from the start to the end of the route
+    pub geometry: Vec<Point>,
+}
+
+/// Finds routes in a graph by a cost function
+pub struct Router<'g, C> {
+    graph: &'g Graph,
+    cost: C,
+    algorithm: Algorithm,
+}
+
+impl<'g, C: Cost> Router<'g, C> {
+    /// Router using A*
+    pub fn new(graph: &'g Graph, cost: C) -> Self {
+        Router {
+            graph,
+            cost,
+            algorithm: Algorithm::AStar,
+        }
+    }
+
+    pub fn with_algorithm(mut self, algorithm: Algorithm) -> Self {
+        self.algorithm = algorithm;
+        self
+    }
+
+    /// Best route between two coordinates after snapping them to the graph,
+    /// or `None` if there is no route
+    pub fn route(&self, from: Point, to: Point) -> Option<Route> {
+        self.route_between(&snap(self.graph, from)?, &snap(self.graph, to)?)
+    }
+
+    /// Best route between two snapped places
+    pub fn route_between(&self, from: &Snap, to: &Snap) -> Option<Route> {
+        let graph = self.graph;
+        let factor = match self.algorithm {
+            Algorithm::Dijkstra => 0.0,
+            Algorithm::AStar => self.cost.min_cost_per_meter(graph),
+        };
+        let heuristic =
+            |node: u32| factor * haversine(graph.node(node).point, to.point);
+        let cost = |edge: u32| self.cost.cost(graph, graph.edge(edge));
+        // cost of the part of an edge up to the offset
+        let partial = |position: &Position| {
+            let length = f64::from(graph.edge(position.edge).distance);
+            if length > 0.0 {
+                cost(position.edge) * (position.offset / length).min(1.0)
+            } else {
+                0.0
+            }
+        };
+
+        // cost on reaching the end of each edge and the edge before it
+        let mut labels: HashMap<u32, (f64, Option<u32>)> = HashMap::new();
+        let mut heap = BinaryHeap::new();
+        let mut best = None;
+
+        for start in &from.positions {
+            let edge = graph.edge(start.edge);
+            let done = partial(start);
+            let g = cost(start.edge) - done;
+            for end in to.positions.iter().filter(|p| p.edge == start.edge) {
+                if end.offset >= start.offset {
+                    consider(&mut best, partial(end) - done, None, end);
+                }
+            }
+            if labels.get(&start.edge).is_none_or(|(c, _)| g < *c) {
+                labels.insert(start.edge, (g, None));
+                heap.push(Entry(g + heuristic(edge.target), start.edge));
+            }
+        }
+
+        while let Some(Entry(estimate, a)) = heap.pop() {
+            if best.is_some_and(|(b, _, _)| b <= estimate) {
+                break;
+            }
+            let g = labels[&a].0;
+            let node = graph.edge(a).target;
+            if estimate > g + heuristic(node) {
+                // stale entry
+                continue;
+            }
+            for b in graph.out_edges(node) {
+                if graph.is_forbidden(a, b) {
+                    continue;
+                }
+                for end in to.positions.iter().filter(|p| p.edge == b) {
+                    consider(&mut best, g + partial(end), Some(a), end);
+                }
+                let next = g + cost(b);
+                if labels.get(&b).is_none_or(|(c, _)| next < *c) {
+                    labels.insert(b, (next, Some(a)));
+                    let target = graph.edge(b).target;
+                    heap.push(Entry(next + heuristic(target), b));
+                }
+            }
+        }
+
+        let (total, mut previous, end) = best?;
+        let mut edges = vec![end.edge];
+        while let Some(edge) = previous {
+            edges.push(edge);
+            previous = labels[&edge].1;
+        }
+        edges.reverse();
+
+        let first = edges[0];
+        let start = from
+            .positions
+            .iter()
+            .find(|p| p.edge == first)
+            .map_or(0.0, |p| p.offset);
+        Some(assemble(graph, total, edges, start, end.offset))
+    }
+}
+
+/// Route along the edges, starting and ending at offsets of the first
+/// and last edge
+pub(crate) fn assemble(
+    graph: &Graph,
+    cost: f64,
+    edges: Vec<u32>,
+    start: f64,
+    end: f64,
+) -> Route {
+    let mut route = Route {
+        cost,
+        distance: 0.0,
+        edges: Vec::new(),
+        ways: Vec::new(),
+        nodes: Vec::new(),
+        geometry: Vec::new(),
+    };
+
+    for (i, e) in edges.iter().enumerate() {
+        let edge = graph.edge(*e);
+        let from = if i == 0 { start } else { 0.0 };
+        let to = if i == edges.len() - 1 {
+            end
+        } else {
+            f64::from(edge.distance)
+        };
+        route.distance += to - from;
+
+        let way = graph.way(edge).id;
+        if route.ways.last() != Some(&way) {
+            route.ways.push(way);
+        }
+
+        let mut offset = 0.0;
+        for pair in graph.shape(edge).windows(2) {
+            let ((a_id, a), (b_id, b)) = (pair[0], pair[1]);
+            let length = haversine(a, b);
+            let (a_offset, b_offset) = (offset, offset + length);
+            offset = b_offset;
+            if b_offset < from || a_offset > to {
+                continue;
+            }
+            if a_offset >= from {
+                push_node(&mut route.nodes, a_id);
+                push_point(&mut route.geometry, a);
+            } else {
+                let t = (from - a_offset) / length;
+                push_point(&mut route.geometry, interpolate(a, b, t));
+            }
+            if b_offset <= to {
+                push_node(&mut route.nodes, b_id);
+                push_point(&mut route.geometry, b);
+            } else {
+                let t = (to - a_offset) / length;
+                push_point(&mut route.geometry, interpolate(a, b, t));
+            }
+        }
+    }
+    route.edges = edges;
+    route
+}
+
+/// Keep the cheaper of two complete routes, given by their cost, the edge
+/// before the last one and where they end
+fn consider(
+    best: &mut Option<(f64, Option<u32>, Position)>,
+    cost: f64,
+    previous: Option<u32>,
+    end: &Position,
+) {
+    if best.is_none_or(|(b, _, _)| cost < b) {
+        *best = Some((cost, previous, *end));
+    }
+}
+
+fn push_node(nodes: &mut Vec<ElementID>, id: ElementID) {
+    if nodes.last() != Some(&id) {
+        nodes.push(id);
+    }
+}
+
+fn push_point(points: &mut Vec<Point>, point: Point) {
+    if points.last() != Some(&point) {
+        points.push(point);
+    }
+}
+
+/// Heap entry ordered with the lowest key first
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) struct Entry(pub f64, pub u32);
+
+impl Eq for Entry {}
+
+impl Ord for Entry {
+    fn cmp(&self, other: &Self) -> Ordering {
+        other.0.total_cmp(&self.0).then(other.1.cmp(&self.1))
+    }
+}
+
+impl PartialOrd for Entry {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        node::Node, routing::GraphBuilder, tag::travel_by::Mode, way::Way,
+        AreaData, TagMap,
+    };
+
+    /// A ladder of two parallel streets joined at both ends: the bottom one
+    /// residential (1 2 3), the top a primary road (4 5 6) with a 90 km/h
+    /// limit
+    fn nodes() -> Vec<Node> {
+        (1..=6)
+            .map(|id| Node {
+                id,
+                lat: if id > 3 { 0.002 } else { 0.0 },
+                lon: ((id - 1) % 3) as f32 * 0.005,
+                ..Default::default()
+            })
+            .collect()
+    }
+
+    fn area(nodes: &[Node]) -> AreaData<'_> {
+        let way =
+            |id, ids: &[usize], tags: &[(&'static str, &'static str)]| Way {
+                id,
+                nodes: ids.iter().map(|i| &nodes[i - 1]).collect(),
+                tags: Some(tags.iter().copied().collect::<TagMap>()),
+                ..Default::default()
+            };
+        let ways = vec![
+            way(1, &[1, 2, 3], &[("highway", "residential")]),
+            way(2, &[4, 5, 6], &[("highway", "primary"), ("maxspeed", "90")]),
+            way(3, &[1, 4], &[("highway", "residential")]),
+            way(4, &[3, 6], &[("highway", "residential")]),
+        ];
+        AreaData {
+            ways: ways.into_iter().map(|way| (way.id, way)).collect(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn snaps_to_nearest_edges() {
+        let nodes = nodes();
+        let area = area(&nodes);
+        let graph = GraphBuilder::new(&area, Mode::Motorcar).build();
+
+        let snap = snap(&graph, (-0.0001, 0.0025)).unwrap();
+        assert_eq!(snap.point, (0.0, 0.0025));
+        assert!((snap.distance - 11.1).abs() < 0.1);
+        // both directions of way 1
+        assert_eq!(snap.positions.len(), 2);
+        let mut offsets: Vec<f64> = snap
+            .positions
+            .iter()
+            .inspect(|p| assert_eq!(graph.way(graph.edge(p.edge)).id, 1))
+            .map(|p| p.offset)
+            .collect();
+        offsets.sort_by(f64::total_cmp);
+        assert!((offsets[0] - 278.0).abs() < 1.0);
+        assert!((offsets[1] - 834.0).abs() < 1.0);
+    }
+
+    #[test]
+    fn routes_by_cost() {
+        let nodes = nodes();
+        let area = area(&nodes);
+        let graph = GraphBuilder::new(&area, Mode::Motorcar).build();
+        let (from, to) = ((0.0, 0.0001), (0.0, 0.0099));
+
+        let shortest = Router::new(&graph, Distance).route(from, to).unwrap();
+        assert_eq!(shortest.ways, vec![1]);
+        assert_eq!(shortest.nodes, vec![2]);
+        assert_eq!(shortest.geometry.len(), 3);
+        assert!((shortest.distance - 1090.0).abs() < 2.0);
+        assert!((shortest.cost - shortest.distance).abs() < 1e-6);
+
+        let dijkstra = Router::new(&graph, Distance)
+            .with_algorithm(Algorithm::Dijkstra)
+            .route(from, to)
+            .unwrap();
+        assert_eq!(dijkstra.edges, shortest.edges);
+
+        // the detour over the faster road takes less time
+        let fastest = Router::new(&graph, Time).route(from, to).unwrap();
+        assert_eq!(fastest.ways, vec![1, 3, 2, 4, 1]);
+        assert_eq!(fastest.nodes, vec![1, 4, 5, 6, 3]);
+        assert!(fastest.cost < shortest.distance * 3.6 / 30.0);
+
+        let custom = |graph: &Graph, edge: &Edge| {
+            let primary = graph.way(edge).highway == Some(Highway::Primary);
+            f64::from(edge.distance) * if primary { 10.0 } else { 1.0 }
+        };
+        let avoiding = Router::new(&graph, custom).route(from, to).unwrap();
+        assert_eq!(avoiding.ways, vec![1]);
+    }
+
+    #[test]
+    fn routes_along_one_edge() {
+        let nodes = nodes();
+        let area = area(&nodes);
+        let graph = GraphBuilder::new(&area, Mode::Motorcar).build();
+        let router = Router::new(&graph, Distance);
+
+        let forward = router.route((0.0, 0.001), (0.0, 0.002)).unwrap();
+        assert_eq!(forward.edges.len(), 1);
+        assert!(forward.nodes.is_empty());
+        assert!((forward.distance - 111.2).abs() < 0.5);
+
+        let backward = router.route((0.0, 0.002), (0.0, 0.001)).unwrap();
+        assert_eq!(backward.edges.len(), 1);
+        assert_ne!(backward.edges, forward.edges);
+        assert!(backward.nodes.is_empty());
+        assert_eq!(backward.geometry.len(), 2);
+        assert!((backward.geometry[0].1 - 0.002).abs() < 1e-6);
+        assert!((backward.geometry[1].1 - 0.001).abs() < 1e-6);
+    }
+}
@@ -0,0 +1,737 @@
+//! Routing profiles deciding which ways a traveller may use and how fast.
+//!
+//! A profile gives a speed for each highway type it may use, then adjusts
+//! it for the way's speed limit, surface, incline and access, and applies
+//! penalties that make the way less attractive without changing its speed.
+//! Where elevation data gives the grade of each stretch of a way, `climb`
+//! applies it in place of the way's `incline`.
+//! Profiles for cars, bicycles, pedestrians and wheelchair users are built
+//! in, and others can be loaded from JSON or TOML such as
+//!
+//! ```toml
+//! name = "cargo bike"
+//! mode = "bicycle"
+//! max_speed = 20
+//! incline_factor = 0.08
+//!
+//! [speeds]
+//! cycleway = 18
+//! residential = 16
+//!
+//! [penalties]
+//! residential = 1.2
+//!
+//! [surfaces]
+//! sett = 0.5
+//! ```
+use crate::{
+    access::{resolve, DefaultAccess},
+    tag::{
+        access::AccessLevel,
+        highway::Highway,
+        incline,
+        maxspeed::{self, Zones},
+        surface::{self, Surface, Traveller},
+        travel_by::Mode,
+        Tagged,
+    },
+};
+use hashbrown::HashMap;
+use serde::Deserialize;
+use std::{collections, error, fmt};
+
+/// Key stating whether a way is usable by wheelchair
+pub static WHEELCHAIR: &'static str = "wheelchair";
+/// Key stating which sides of a road have a sidewalk
+pub static SIDEWALK: &'static str = "sidewalk";
+
+/// Grade in percent assumed for `incline=up` or `incline=down`
+pub static UNKNOWN_INCLINE: f32 = 5.0;
+
+/// Speed in km/h of someone pushing a bicycle or walking along a sidewalk
+pub static WALKING_SPEED: f32 = 5.0;
+
+#[derive(Debug)]
+pub enum LoadError {
+    Json(serde_json::Error),
+    Toml(toml::de::Error),
+    /// `mode` isn't a transport mode
+    UnknownMode(String),
+    /// `traveller` isn't one of car, bicycle, wheelchair or foot
+    UnknownTraveller(String),
+    /// Highway or surface in `speeds`, `penalties` or `surfaces` that isn't
+    /// a known value, often a typo such as `cyclway`
+    UnknownKey(String),
+    /// A speed or surface factor that isn't positive, a penalty below 1, or
+    /// another value that's negative or not a number
+    InvalidValue {
+        key: String,
+        value: f32,
+    },
+}
+
+impl fmt::Display for LoadError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            LoadError::Json(e) => write!(f, "invalid profile: {}", e),
+            LoadError::Toml(e) => write!(f, "invalid profile: {}", e),
+            LoadError::UnknownMode(mode) => {
+                write!(f, "unknown transport mode {} in profile", mode)
+            }
+            LoadError::UnknownTraveller(traveller) => {
+                write!(f, "unknown traveller {} in profile", traveller)
+            }
+            LoadError::UnknownKey(key) => {
+                write!(f, "unknown highway or surface {} in profile", key)
+            }
+            LoadError::InvalidValue { key, value } => {
+                write!(f, "invalid value {} for {} in profile", value, key)
+            }
+        }
+    }
+}
+
+impl error::Error for LoadError {}
+
+impl From<serde_json::Error> for LoadError {
+    fn from(e: serde_json::Error) -> Self {
+        LoadError::Json(e)
+    }
+}
+
+impl From<toml::de::Error> for LoadError {
+    fn from(e: toml::de::Error) -> Self {
+        LoadError::Toml(e)
+    }
+}
+
+/// Speed and penalty of travel along a way in one direction
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Rating {
+    /// km/h
+    pub speed: f32,
+    /// Factor of at least `1.0` on the travel time, for ways that should be
+    /// avoided without being slower
+    pub penalty: f32,
+}
+
+impl Rating {
+    /// Weight of a meter of travel: seconds scaled by the penalty
+    pub fn cost_per_meter(&self) -> f32 {
+        3.6 / self.speed * self.penalty
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Profile {
+    pub name: String,
+    /// Mode whose access and oneway rules apply
+    pub mode: Mode,
+    /// Traveller whose surface speed factors apply
+    pub traveller: Traveller,
+    /// Speed in km/h on each highway type that may be used. Pedestrians
+    /// may also use other roads with a sidewalk, and wheelchair users
+    /// those tagged `wheelchair=yes`, at `WALKING_SPEED`.
+    pub speeds: HashMap<Highway, f32>,
+    /// Fastest the traveller goes, in km/h
+    pub max_speed: f32,
+    /// Whether to travel at the speed limit where there is one, rather than
+    /// only being capped by it
+    pub follow_maxspeed: bool,
+    /// Penalty by highway type
+    pub penalties: HashMap<Highway, f32>,
+    /// Speed factors by surface, replacing the traveller's usual factors
+    pub surfaces: HashMap<Surface, f32>,
+    /// Share of speed lost for each percent of uphill grade
+    pub incline_factor: f32,
+    /// Steepest grade in percent the traveller can manage either way
+    pub max_incline: Option<f32>,
+    /// Penalty for roads tagged as having no sidewalk
+    pub sidewalk_penalty: f32,
+    /// Whether ways must not be tagged `wheelchair=no`, with a penalty for
+    /// `wheelchair=limited`
+    pub wheelchair: bool,
+    /// Penalty for ways open only to destination traffic or where the mode
+    /// is discouraged
+    pub access_penalty: f32,
+}
+
+impl Profile {
+    fn new(
+        name: &str,
+        mode: Mode,
+        traveller: Traveller,
+        max_speed: f32,
+    ) -> Self {
+        Profile {
+            name: name.to_owned(),
+            mode,
+            traveller,
+            speeds: HashMap::new(),
+            max_speed,
+            follow_maxspeed: false,
+            penalties: HashMap::new(),
+            surfaces: HashMap::new(),
+            incline_factor: 0.0,
+            max_incline: None,
+            sidewalk_penalty: 1.0,
+            wheelchair: false,
+            access_penalty: 1.0,
+        }
+    }
+
+    pub fn car() -> Self {
+        use Highway::*;
+        let mut profile =
+            Profile::new("car", Mode::Motorcar, Traveller::Car, 130.0);
+        profile.follow_maxspeed = true;
+        profile.access_penalty = 5.0;
+        profile.set_speeds(&[
+            (Motorway, 110.0),
+            (Trunk, 90.0),
+            (Primary, 70.0),
+            (Secondary, 60.0),
+            (Tertiary, 50.0),
+            (MotorwayLink, 60.0),
+            (TrunkLink, 60.0),
+            (PrimaryLink, 40.0),
+            (SecondaryLink, 40.0),
+            (TertiaryLink, 40.0),
+            (Unclassified, 30.0),
+            (Residential, 30.0),
+            (Road, 30.0),
+            (LivingStreet, 10.0),
+            (Service, 20.0),
+            (Track, 15.0),
+        ]);
+        profile.set_penalties(&[
+            (Service, 1.5),
+            (LivingStreet, 1.5),
+            (Track, 2.0),
+        ]);
+        profile
+    }
+
+    pub fn bicycle() -> Self {
+        use Highway::*;
+        let mut profile =
+            Profile::new("bicycle", Mode::Bicycle, Traveller::Bicycle, 25.0);
+        profile.incline_factor = 0.06;
+        profile.access_penalty = 2.0;
+        profile.set_speeds(&[
+            (Trunk, 18.0),
+            (Primary, 18.0),
+            (Secondary, 18.0),
+            (Tertiary, 18.0),
+            (TrunkLink, 18.0),
+            (PrimaryLink, 18.0),
+            (SecondaryLink, 18.0),
+            (TertiaryLink, 18.0),
+            (Unclassified, 18.0),
+            (Residential, 18.0),
+            (Road, 16.0),
+            (LivingStreet, 12.0),
+            (Service, 15.0),
+            (Track, 12.0),
+            (Cycleway, 18.0),
+            (Path, 12.0),
+            (Footway, 6.0),
+            (Pedestrian, 6.0),
+            (Bridleway, 8.0),
+            (Steps, 2.0),
+        ]);
+        profile.set_penalties(&[
+            (Trunk, 3.0),
+            (TrunkLink, 3.0),
+            (Primary, 1.6),
+            (PrimaryLink, 1.6),
+            (Secondary, 1.3),
+            (SecondaryLink, 1.3),
+            (Steps, 3.0),
+        ]);
+        profile
+    }
+
+    pub fn foot() -> Self {
+        use Highway::*;
+        let mut profile =
+            Profile::new("foot", Mode::Foot, Traveller::Foot, 5.0);
+        profile.incline_factor = 0.02;
+        profile.sidewalk_penalty = 1.5;
+        profile.access_penalty = 2.0;
+        for highway in &[
+            Trunk,
+            Primary,
+            Secondary,
+            Tertiary,
+            TrunkLink,
+            PrimaryLink,
+            SecondaryLink,
+            TertiaryLink,
+            Unclassified,
+            Residential,
+            Road,
+            LivingStreet,
+            Service,
+            Track,
+            Pedestrian,
+            Footway,
+            Path,
+            Cycleway,
+            Bridleway,
+            Corridor,
+            Platform,
+        ] {
+            profile.speeds.insert(highway.clone(), 5.0);
+        }
+        profile.speeds.insert(Steps, 3.0);
+        profile.set_penalties(&[
+            (Trunk, 2.0),
+            (TrunkLink, 2.0),
+            (Primary, 1.5),
+            (PrimaryLink, 1.5),
+            (Secondary, 1.2),
+            (SecondaryLink, 1.2),
+        ]);
+        profile
+    }
+
+    pub fn wheelchair() -> Self {
+        use Highway::*;
+        let mut profile =
+            Profile::new("wheelchair", Mode::Foot, Traveller::Wheelchair, 4.0);
+        profile.incline_factor = 0.1;
+        profile.max_incline = Some(6.0);
+        profile.sidewalk_penalty = 2.0;
+        profile.wheelchair = true;
+        profile.access_penalty = 2.0;
+        for highway in &[
+            Tertiary,
+            TertiaryLink,
+            Unclassified,
+            Residential,
+            Road,
+            LivingStreet,
+            Service,
+            Pedestrian,
+            Footway,
+            Path,
+            Cycleway,
+            Corridor,
+            Platform,
+        ] {
+            profile.speeds.insert(highway.clone(), 4.0);
+        }
+        profile.set_penalties(&[(Tertiary, 1.5), (Path, 1.5)]);
+        profile
+    }
+
+    /// Built-in profile by name: `car`, `bicycle`, `foot` or `wheelchair`
+    pub fn builtin(name: &str) -> Option<Profile> {
+        match name {
+            "car" => Some(Profile::car()),
+            "bicycle" => Some(Profile::bicycle()),
+            "foot" => Some(Profile::foot()),
+            "wheelchair" => Some(Profile::wheelchair()),
+            _ => None,
+        }
+    }
+
+    pub fn from_json(json: &str) -> Result<Profile, LoadError> {
+        Profile::from_file(serde_json::from_str(json)?)
+    }
+
+    pub fn from_toml(toml: &str) -> Result<Profile, LoadError> {
+        Profile::from_file(toml::from_str(toml)?)
+    }
+
+    fn from_file(file: ProfileFile) -> Result<Profile, LoadError> {
+        let mode: Mode = file
+            .mode
+            .parse()
+            .map_err(|_| LoadError::UnknownMode(file.mode.clone()))?;
+        let traveller = match file.traveller.as_deref() {
+            Some(name) => traveller(name)
+                .ok_or_else(|| LoadError::UnknownTraveller(name.to_owned()))?,
+            None => match mode {
+                Mode::Foot => Traveller::Foot,
+                Mode::Bicycle => Traveller::Bicycle,
+                _ => Traveller::Car,
+            },
+        };
+        let check = |key: &str, value: f32, valid: fn(f32) -> bool| {
+            if value.is_finite() && valid(value) {
+                Ok(value)
+            } else {
+                Err(LoadError::InvalidValue {
+                    key: key.to_owned(),
+                    value,
+                })
+            }
+        };
+        let positive: fn(f32) -> bool = |value| value > 0.0;
+        let not_negative: fn(f32) -> bool = |value| value >= 0.0;
+        // a penalty below 1 would make a way cheaper than its travel time
+        let penalty: fn(f32) -> bool = |value| value >= 1.0;
+        let highway = |key: String| match key.parse().unwrap() {
+            Highway::Unknown(key) => Err(LoadError::UnknownKey(key)),
+            highway => Ok(highway),
+        };
+
+        let mut profile = Profile::new(
+            &file.name,
+            mode,
+            traveller,
+            check("max_speed", file.max_speed.unwrap_or(f32::MAX), positive)?,
+        );
+        for (key, speed) in file.speeds {
+            let speed = check(&key, speed, positive)?;
+            profile.speeds.insert(highway(key)?, speed);
+        }
+        for (key, value) in file.penalties {
+            let value = check(&key, value, penalty)?;
+            profile.penalties.insert(highway(key)?, value);
+        }
+        for (key, factor) in file.surfaces {
+            let factor = check(&key, factor, positive)?;
+            let surface = match key.parse().unwrap() {
+                Surface::Unknown(key) => {
+                    return Err(LoadError::UnknownKey(key))
+                }
+                surface => surface,
+            };
+            profile.surfaces.insert(surface, factor);
+        }
+        profile.follow_maxspeed = file.follow_maxspeed;
+        profile.incline_factor = check(
+            "incline_factor",
+            file.incline_factor.unwrap_or(0.0),
+            not_negative,
+        )?;
+        profile.max_incline = file
+            .max_incline
+            .map(|grade| check("max_incline", grade, not_negative))
+            .transpose()?;
+        profile.sidewalk_penalty = check(
+            "sidewalk_penalty",
+            file.sidewalk_penalty.unwrap_or(1.0),
+            penalty,
+        )?;
+        profile.wheelchair = file.wheelchair;
+        profile.access_penalty = check(
+            "access_penalty",
+            file.access_penalty.unwrap_or(1.0),
+            penalty,
+        )?;
+        Ok(profile)
+    }
+
+    fn set_speeds(&mut self, speeds: &[(Highway, f32)]) {
+        self.speeds.extend(speeds.iter().cloned());
+    }
+
+    fn set_penalties(&mut self, penalties: &[(Highway, f32)]) {
+        self.penalties.extend(penalties.iter().cloned());
+    }
+
+    /// Speed and penalty of travelling the element, against the order of its
+    /// nodes if `reverse`, or `None` if the profile may not use it
+    pub fn rate<T: Tagged + ?Sized>(
+        &self,
+        element: &T,
+        defaults: &DefaultAccess,
+        reverse: bool,
+    ) -> Option<Rating> {
+        let rating = self.rate_level(element, defaults)?;
+        match grade(element) {
+            Some(grade) if reverse => self.climb(rating, -grade),
+            Some(grade) => self.climb(rating, grade),
+            None => Some(rating),
+        }
+    }
+
+    /// Speed and penalty of travelling the element as if it were level, for
+    /// grades known from elevation data rather than its `incline`
+    pub fn rate_level<T: Tagged + ?Sized>(
+        &self,
+        element: &T,
+        defaults: &DefaultAccess,
+    ) -> Option<Rating> {
+        let highway = Highway::of(element)?;
+        let mut speed = match self.speeds.get(&highway) {
+            Some(speed) => *speed,
+            None if self.walks_beside(element) => WALKING_SPEED,
+            None => return None,
+        };
+        let mut penalty = self.penalties.get(&highway).copied().unwrap_or(1.0);
+
+        match resolve(element, self.mode, defaults) {
+            AccessLevel::Destination
+            | AccessLevel::Delivery
+            | AccessLevel::Customers
+            | AccessLevel::Discouraged => penalty *= self.access_penalty,
+            AccessLevel::Dismount => speed = speed.min(WALKING_SPEED),
+            level if !level.is_allowed() => return None,
+            _ => (),
+        }
+
+        if self.wheelchair {
+            match element.get_tag(WHEELCHAIR) {
+                Some("no") => return None,
+                Some("limited") => penalty *= 1.5,
+                _ => (),
+            }
+        }
+        if matches!(
+            element.get_tag(SIDEWALK),
+            Some("no") | Some("none") | Some("separate")
+        ) {
+            penalty *= self.sidewalk_penalty;
+        }
+
+        if let Some(limit) = maxspeed::effective(element, Zones::standard())
+            .and_then(|speed| speed.kmh())
+            .filter(|limit| limit.is_finite())
+        {
+            speed = if self.follow_maxspeed {
+                limit
+            } else {
+                speed.min(limit)
+            };
+        }
+
+        let factor = match Surface::of(element) {
+            Some(s) if self.surfaces.contains_key(&s) => self.surfaces[&s],
+            _ => surface::speed_factor(element, self.traveller),
+        };
+        speed *= factor;
+
+        speed = speed.min(self.max_speed);
+        if speed > 0.0 {
+            Some(Rating { speed, penalty })
+        } else {
+            None
+        }
+    }
+
+    /// Whether a pedestrian profile may keep to the element's sidewalk or
+    /// wheelchair-accessible path even though it doesn't rate its highway
+    /// type
+    fn walks_beside<T: Tagged + ?Sized>(&self, element: &T) -> bool {
+        let sidewalk = matches!(
+            element.get_tag(SIDEWALK),
+            Some("both") | Some("left") | Some("right") | Some("yes")
+        );
+        let accessible = element.get_tag(WHEELCHAIR) == Some("yes");
+        self.mode == Mode::Foot && (sidewalk || self.wheelchair && accessible)
+    }
+
+    /// Rating on a stretch of the given grade in percent, slower uphill, or
+    /// `None` if it's steeper than the traveller can manage
+    pub fn climb(&self, rating: Rating, grade: f32) -> Option<Rating> {
+        if self.max_incline.is_some_and(|max| grade.abs() > max) {
+            return None;
+        }
+        let mut rating = rating;
+        if grade > 0.0 {
+            rating.speed *= (1.0 - self.incline_factor * grade).max(0.1);
+        }
+        Some(rating)
+    }
+}
+
+/// Grade of the element in percent along the order of its nodes, from its
+/// `incline` as a percentage, in degrees or as `up`/`down`
+pub fn grade<T: Tagged + ?Sized>(element: &T) -> Option<f32> {
+    let value = element.get_tag(incline::KEY)?.trim();
+    if value == incline::UP {
+        Some(UNKNOWN_INCLINE)
+    } else if value == incline::DOWN {
+        Some(-UNKNOWN_INCLINE)
+    } else if let Some(degrees) = value.strip_suffix('°') {
+        let degrees: f32 = degrees.trim().parse().ok()?;
+        Some(degrees.to_radians().tan() * 100.0)
+    } else {
+        value.trim_end_matches('%').trim().parse().ok()
+    }
+}
+
+fn traveller(name: &str) -> Option<Traveller> {
+    match name {
+        "car" => Some(Traveller::Car),
+        "bicycle" => Some(Traveller::Bicycle),
+        "wheelchair" => Some(Traveller::Wheelchair),
+        "foot" => Some(Traveller::Foot),
+        _ => None,
+    }
+}
+
+/// Profile as written in JSON or TOML
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ProfileFile {
+    name: String,
+    mode: String,
+    traveller: Option<String>,
+    #[serde(default)]
+    speeds: collections::HashMap<String, f32>,
+    max_speed: Option<f32>,
+    #[serde(default)]
+    follow_maxspeed: bool,
+    #[serde(default)]
+    penalties: collections::HashMap<String, f32>,
+    #[serde(default)]
+    surfaces: collections::HashMap<String, f32>,
+    incline_factor: Option<f32>,
+    max_incline: Option<f32>,
+    sidewalk_penalty: Option<f32>,
+    #[serde(default)]
+    wheelchair: bool,
+    access_penalty: Option<f32>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::TagMap;
+
+    fn tags(pairs: &[(&'static str, &'static str)]) -> TagMap {
+        pairs.iter().copied().collect()
+    }
+
+    #[test]
+    fn builtin_profiles() {
+        let world = DefaultAccess::world();
+        let primary = tags(&[("highway", "primary"), ("maxspeed", "50")]);
+        let steps = tags(&[("highway", "steps"), ("incline", "up")]);
+        let footway = tags(&[("highway", "footway"), ("surface", "gravel")]);
+
+        let car = Profile::car();
+        assert_eq!(car.rate(&primary, world, false).unwrap().speed, 50.0);
+        assert_eq!(car.rate(&footway, world, false), None);
+
+        let bicycle = Profile::bicycle();
+        let rating = bicycle.rate(&primary, world, false).unwrap();
+        assert_eq!(rating.speed, 18.0);
+        assert!(rating.penalty > 1.0);
+        // footways are closed to bicycles by default
+        assert_eq!(bicycle.rate(&footway, world, false), None);
+
+        let foot = Profile::foot();
+        let up = foot.rate(&steps, world, false).unwrap();
+        let down = foot.rate(&steps, world, true).unwrap();
+        assert!(up.speed < down.speed);
+        assert_eq!(Profile::wheelchair().rate(&steps, world, false), None);
+        assert!(foot.rate(&footway, world, false).is_some());
+    }
+
+    #[test]
+    fn wheelchair_and_sidewalk_tags() {
+        let world = DefaultAccess::world();
+        let wheelchair = Profile::wheelchair();
+        let rate = |pairs| wheelchair.rate(&tags(pairs), world, false);
+
+        assert!(rate(&[("highway", "footway")]).is_some());
+        assert_eq!(rate(&[("highway", "footway"), ("wheelchair", "no")]), None);
+        assert_eq!(rate(&[("highway", "footway"), ("incline", "8%")]), None);
+        assert!(rate(&[("highway", "footway"), ("incline", "3%")]).is_some());
+
+        let plain = rate(&[("highway", "residential")]).unwrap();
+        let without =
+            rate(&[("highway", "residential"), ("sidewalk", "no")]).unwrap();
+        assert!(without.cost_per_meter() > plain.cost_per_meter());
+
+        // roads the profile doesn't rate are usable along their sidewalks
+        let primary = [("highway", "primary"), ("sidewalk", "both")];
+        assert_eq!(rate(&[("highway", "primary")]), None);
+        assert_eq!(rate(&primary).unwrap().speed, 4.0);
+        let accessible = [("highway", "secondary"), ("wheelchair", "yes")];
+        assert!(rate(&accessible).is_some());
+        let mut foot = Profile::foot();
+        foot.speeds.remove(&Highway::Primary);
+        foot.speeds.remove(&Highway::Secondary);
+        let rating = foot.rate(&tags(&primary), world, false).unwrap();
+        assert_eq!(rating.speed, WALKING_SPEED);
+        assert_eq!(foot.rate(&tags(&accessible), world, false), None);
+
+        assert_eq!(grade(&tags(&[("incline", "-10%")])), Some(-10.0));
+        let degrees = grade(&tags(&[("incline", "45°")])).unwrap();
+        assert!((degrees - 100.0).abs() < 0.01);
+    }
+
+    #[test]
+    fn loads_profiles() {
+        let toml = r#"
+            name = "cargo bike"
+            mode = "bicycle"
+            max_speed = 20
+
+            [speeds]
+            cycleway = 18
+            residential = 16
+
+            [surfaces]
+            sett = 0.5
+        "#;
+        let profile = Profile::from_toml(toml).unwrap();
+        assert_eq!(profile.traveller, Traveller::Bicycle);
+        let world = DefaultAccess::world();
+        let sett = tags(&[("highway", "residential"), ("surface", "sett")]);
+        assert_eq!(profile.rate(&sett, world, false).unwrap().speed, 8.0);
+        assert_eq!(
+            profile.rate(&tags(&[("highway", "primary")]), world, false),
+            None
+        );
+
+        let json = r#"{
+            "name": "scooter",
+            "mode": "moped",
+            "speeds": { "residential": 25 },
+            "penalties": { "residential": 1.5 }
+        }"#;
+        let profile = Profile::from_json(json).unwrap();
+        assert_eq!(profile.mode, Mode::Moped);
+        assert_eq!(profile.traveller, Traveller::Car);
+
+        assert!(matches!(
+            Profile::from_json(r#"{ "name": "x", "mode": "rocket" }"#),
+            Err(LoadError::UnknownMode(_))
+        ));
+        assert!(matches!(
+            Profile::from_json(
+                r#"{ "name": "x", "mode": "foot", "speeds": { "path": -1 } }"#
+            ),
+            Err(LoadError::InvalidValue { .. })
+        ));
+        for (key, table) in &[
+            ("speeds", r#"{ "path": 0 }"#),
+            ("surfaces", r#"{ "gravel": 0 }"#),
+            ("penalties", r#"{ "path": 0.5 }"#),
+        ] {
+            let json = format!(
+                r#"{{ "name": "x", "mode": "foot", "{}": {} }}"#,
+                key, table
+            );
+            assert!(matches!(
+                Profile::from_json(&json),
+                Err(LoadError::InvalidValue { .. })
+            ));
+        }
+        assert!(matches!(
+            Profile::from_toml(
+                "name = \"x\"\nmode = \"bicycle\"\n[speeds]\ncyclway = 18"
+            ),
+            Err(LoadError::UnknownKey(key)) if key == "cyclway"
+        ));
+        assert!(matches!(
+            Profile::from_json(
+                r#"{ "name": "x", "mode": "foot", "sidewalk_penalty": 0.5 }"#
+            ),
+            Err(LoadError::InvalidValue { .. })
+        ));
+        assert!(matches!(
+            Profile::from_toml("name = 3"),
+            Err(LoadError::Toml(_))
+        ));
+    }
+}
@@ -0,0 +1,250 @@
+//! Grid over the segments of every edge's shape, so that the edges near a
+//! point are found without looking at the whole graph.
+//!
+//! Cells are roughly square and sized so that there are about as many cells
+//! as segments. A segment is listed in every cell its bounding box touches.
+use super::Edge;
+use crate::{
+    geo::{Point, EARTH_RADIUS},
+    ElementID,
+};
+use std::ops::Range;
+
+/// Smallest cell side in degrees of latitude, about 50 meters
+static MIN_CELL: f64 = 0.0005;
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub(crate) struct SpatialIndex {
+    /// Southwest corner of the grid
+    pub(super) origin: (f64, f64),
+    /// Degrees of latitude and longitude covered by a cell
+    pub(super) cell: (f64, f64),
+    pub(super) rows: u32,
+    pub(super) cols: u32,
+    /// Index in `segments` of the first segment of each cell, row by row,
+    /// followed by the number of segments
+    pub(super) first: Vec<u32>,
+    /// Edge and index in `Graph::shapes` of the start of each segment
+    pub(super) segments: Vec<(u32, u32)>,
+}
+
+impl SpatialIndex {
+    pub(super) fn new(edges: &[Edge], shapes: &[(ElementID, Point)]) -> Self {
+        let segments: Vec<(u32, u32)> = edges
+            .iter()
+            .enumerate()
+            .flat_map(|(i, edge)| {
+                (edge.shape.0..edge.shape.1.saturating_sub(1))
+                    .map(move |shape| (i as u32, shape))
+            })
+            .collect();
+        if segments.is_empty() {
+            return SpatialIndex::default();
+        }
+
+        let (mut south, mut west) = (f64::INFINITY, f64::INFINITY);
+        let (mut north, mut east) = (f64::NEG_INFINITY, f64::NEG_INFINITY);
+        for (_, (lat, lon)) in shapes {
+            south = south.min(f64::from(*lat));
+            north = north.max(f64::from(*lat));
+            west = west.min(f64::from(*lon));
+            east = east.max(f64::from(*lon));
+        }
+        let scale = ((south + north) / 2.0).to_radians().cos().max(0.01);
+        let (height, width) = (north - south, (east - west) * scale);
+        let count = segments.len() as f64;
+        let side = (height * width / count)
+            .sqrt()
+            .max(height.max(width) / count)
+            .max(MIN_CELL);
+
+        let mut index = SpatialIndex {
+            origin: (south, west),
+            cell: (side, side / scale),
+            rows: (height / side) as u32 + 1,
+            cols: (width / side) as u32 + 1,
+            first: Vec::new(),
+            segments: Vec::new(),
+        };
+
+        // count the segments of each cell, then place them
+        let cells = |segment: &(u32, u32)| {
+            let a = shapes[segment.1 as usize].1;
+            let b = shapes[segment.1 as usize + 1].1;
+            let (rows, cols) = index.covering(a, b);
+            rows.flat_map(move |row| cols.clone().map(move |col| (row, col)))
+        };
+        let mut first = vec![0; (index.rows * index.cols) as usize + 1];
+        for segment in &segments {
+            for (row, col) in cells(segment) {
+                first[(row * index.cols + col) as usize + 1] += 1;
+            }
+        }
+        for i in 1..first.len() {
+            first[i] += first[i - 1];
+        }
+        let mut next = first.clone();
+        let mut listed = vec![(0, 0); first[first.len() - 1] as usize];
+        for segment in &segments {
+            for (row, col) in cells(segment) {
+                let cell = (row * index.cols + col) as usize;
+                listed[next[cell] as usize] = *segment;
+                next[cell] += 1;
+            }
+        }
+        index.first = first;
+        index.segments = listed;
+        index
+    }
+
+    /// Row and column of the cell containing the point, which may lie
+    /// outside the grid
+    fn position(&self, (lat, lon): Point) -> (i64, i64) {
+        (
+            ((f64::from(lat) - self.origin.0) / self.cell.0).floor() as i64,
+            ((f64::from(lon) - self.origin.1) / self.cell.1).floor() as i64,
+        )
+    }
+
+    /// Rows and columns of the grid cells that the box around two points
+    /// touches
+    fn covering(&self, a: Point, b: Point) -> (Range<u32>, Range<u32>) {
+        let (a, b) = (self.position(a), self.position(b));
+        let clamp = |low: i64, high: i64, size: u32| -> Range<u32> {
+            let low = low.max(0).min(i64::from(size));
+            let high = (high + 1).max(0).min(i64::from(size));
+            low as u32..high.max(low) as u32
+        };
+        (
+            clamp(a.0.min(b.0), a.0.max(b.0), self.rows),
+            clamp(a.1.min(b.1), a.1.max(b.1), self.cols),
+        )
+    }
+
+    fn cell(&self, row: u32, col: u32) -> &[(u32, u32)] {
+        let cell = (row * self.cols + col) as usize;
+        &self.segments[self.first[cell] as usize..self.first[cell + 1] as usize]
+    }
+
+    /// Segments listed in the cells exactly `ring` cells away from the
+    /// point's cell. A segment may appear more than once.
+    pub(super) fn ring(&self, point: Point, ring: u32) -> Vec<(u32, u32)> {
+        let (row, col) = self.position(point);
+        let ring = i64::from(ring);
+        let mut found = Vec::new();
+        for r in (row - ring).max(0)..=(row + ring).min(self.rows as i64 - 1) {
+            let edge = r == row - ring || r == row + ring;
+            for c in
+                (col - ring).max(0)..=(col + ring).min(self.cols as i64 - 1)
+            {
+                if edge || c == col - ring || c == col + ring {
+                    found.extend_from_slice(self.cell(r as u32, c as u32));
+                }
+            }
+        }
+        found
+    }
+
+    /// Whether the cells up to `ring` cells away from the point's cell take
+    /// in the whole grid
+    pub(super) fn covers(&self, point: Point, ring: u32) -> bool {
+        let (row, col) = self.position(point);
+        let ring = i64::from(ring);
+        row - ring <= 0
+            && col - ring <= 0
+            && row + ring >= self.rows as i64 - 1
+            && col + ring >= self.cols as i64 - 1
+    }
+
+    /// Meters from the point within which every segment is listed in the
+    /// cells up to `ring` cells away from the point's cell
+    pub(super) fn reach(&self, point: Point, ring: u32) -> f64 {
+        let north = self.origin.0 + self.cell.0 * f64::from(self.rows);
+        let lat = f64::from(point.0.abs())
+            .max(self.origin.0.abs())
+            .max(north.abs())
+            .min(90.0);
+        let meters = EARTH_RADIUS.to_radians();
+        let side = (self.cell.0 * meters)
+            .min(self.cell.1 * meters * lat.to_radians().cos());
+        side * f64::from(ring)
+    }
+
+    /// Segments listed in the cells within `radius` meters of the point. A
+    /// segment may appear more than once.
+    pub(super) fn within(&self, point: Point, radius: f64) -> Vec<(u32, u32)> {
+        if self.segments.is_empty() {
+            return Vec::new();
+        }
+        let mut ring = 0;
+        while self.reach(point, ring) < radius && !self.covers(point, ring) {
+            ring += 1;
+        }
+        (0..=ring).flat_map(|r| self.ring(point, r)).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        geo::haversine,
+        node::Node,
+        routing::{path, GraphBuilder},
+        tag::travel_by::Mode,
+        way::Way,
+        AreaData, TagMap,
+    };
+
+    #[test]
+    fn lists_every_segment_near_a_point() {
+        // ten parallel streets zigzagging east
+        let nodes: Vec<Node> = (0..100)
+            .map(|i| Node {
+                id: i + 1,
+                lat: (i / 10) as f32 * 0.001 + (i % 3) as f32 * 0.0001,
+                lon: (i % 10) as f32 * 0.001,
+                ..Default::default()
+            })
+            .collect();
+        let tags: TagMap =
+            vec![("highway", "residential")].into_iter().collect();
+        let mut ways = Vec::new();
+        for i in 0..10 {
+            ways.push(Way {
+                id: i + 1,
+                nodes: nodes[i as usize * 10..(i as usize + 1) * 10]
+                    .iter()
+                    .collect(),
+                tags: Some(tags.clone()),
+                ..Default::default()
+            });
+        }
+        let area = AreaData {
+            ways: ways.into_iter().map(|way| (way.id, way)).collect(),
+            ..Default::default()
+        };
+        let graph = GraphBuilder::new(&area, Mode::Motorcar).build();
+        let index = &graph.spatial;
+        assert!(index.rows * index.cols > 1);
+
+        for point in &[(0.0045, 0.0032), (0.02, -0.01), (-1.0, 1.0)] {
+            // nearest segment by looking at them all
+            let nearest = graph
+                .edges()
+                .iter()
+                .flat_map(|edge| graph.shape(edge).windows(2))
+                .map(|pair| {
+                    let (a, b) = (pair[0].1, pair[1].1);
+                    let t = path::project(*point, a, b);
+                    haversine(*point, path::interpolate(a, b, t))
+                })
+                .fold(f64::INFINITY, f64::min);
+            let snap = path::snap(&graph, *point).unwrap();
+            assert!((snap.distance - nearest).abs() < 1e-6);
+
+            let near = index.within(*point, nearest + 1.0);
+            let edge = snap.positions[0].edge;
+            assert!(near.iter().any(|(e, _)| *e == edge));
+        }
+    }
+}
@@ -15,6 +15,7 @@
 //! described here.
 //!
 //! https://wiki.openstreetmap.org/wiki/Key:access
+use std::{convert::Infallible, fmt, str::FromStr};
 
 pub static KEY: &'static str = "access";
 
@@ -41,3 +42,106 @@ pub static NONE: &'static str = "no";
 pub static PERMISSIVE: &'static str = "permissive";
 /// Accessible only to individuals with permission
 pub static PRIVATE: &'static str = "private";
+/// Preferred route for the mode, like a cycleway for bicycles
+pub static DESIGNATED: &'static str = "designated";
+/// Bicycles must be pushed
+pub static DISMOUNT: &'static str = "dismount";
+/// Mode must use an adjacent, separately mapped way
+pub static USE_SIDEPATH: &'static str = "use_sidepath";
+
+/// Typed access value for a mode of transport
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum AccessLevel {
+    Yes,
+    No,
+    Private,
+    Permissive,
+    Designated,
+    Destination,
+    Delivery,
+    Customers,
+    Agricultural,
+    Forestry,
+    Discouraged,
+    Dismount,
+    UseSidepath,
+    /// Any other value
+    Unknown(String),
+}
+
+impl AccessLevel {
+    pub fn as_str(&self) -> &str {
+        match self {
+            AccessLevel::Yes => ALLOWED,
+            AccessLevel::No => NONE,
+            AccessLevel::Private => PRIVATE,
+            AccessLevel::Permissive => PERMISSIVE,
+            AccessLevel::Designated => DESIGNATED,
+            AccessLevel::Destination => DESTINATION,
+            AccessLevel::Delivery => DELIVERY,
+            AccessLevel::Customers => CUSTOMERS,
+            AccessLevel::Agricultural => AGRICULTURAL,
+            AccessLevel::Forestry => FORESTRY,
+            AccessLevel::Discouraged => DISCOURAGED,
+            AccessLevel::Dismount => DISMOUNT,
+            AccessLevel::UseSidepath => USE_SIDEPATH,
+            AccessLevel::Unknown(value) => value,
+        }
+    }
+
+    /// Whether the general public may travel the way, possibly only to
+    /// reach a destination along it
+    pub fn is_allowed(&self) -> bool {
+        matches!(
+            self,
+            AccessLevel::Yes
+                | AccessLevel::Permissive
+                | AccessLevel::Designated
+                | AccessLevel::Destination
+                | AccessLevel::Delivery
+                | AccessLevel::Customers
+                | AccessLevel::Discouraged
+                | AccessLevel::Dismount
+        )
+    }
+
+    /// Whether the way may only be used to reach a destination along it, so
+    /// routes shouldn't pass through
+    pub fn is_destination_only(&self) -> bool {
+        matches!(
+            self,
+            AccessLevel::Destination
+                | AccessLevel::Delivery
+                | AccessLevel::Customers
+        )
+    }
+}
+
+impl FromStr for AccessLevel {
+    type Err = Infallible;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        Ok(match value {
+            "yes" => AccessLevel::Yes,
+            "no" => AccessLevel::No,
+            "private" => AccessLevel::Private,
+            "permissive" => AccessLevel::Permissive,
+            "designated" => AccessLevel::Designated,
+            "destination" => AccessLevel::Destination,
+            "delivery" => AccessLevel::Delivery,
+            "customers" => AccessLevel::Customers,
+            "agricultural" => AccessLevel::Agricultural,
+            "forestry" => AccessLevel::Forestry,
+            "discouraged" => AccessLevel::Discouraged,
+            "dismount" => AccessLevel::Dismount,
+            "use_sidepath" => AccessLevel::UseSidepath,
+            other => AccessLevel::Unknown(other.to_owned()),
+        })
+    }
+}
+
+impl fmt::Display for AccessLevel {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
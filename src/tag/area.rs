@@ -0,0 +1,229 @@
+//! Rules deciding whether a closed way is an
+//! [area](https://wiki.openstreetmap.org/wiki/Key:area) or a closed polyline.
+//!
+//! An explicit `area=yes` or `area=no` always wins. Otherwise the way is an
+//! area if it has a tag whose key is listed in the rule table and whose value
+//! is accepted by that key's rule. For example, any `building=*` is an area
+//! while `natural=coastline` and `barrier=fence` are lines.
+//!
+//! The standard table follows the rules shared by most renderers
+//! (https://wiki.openstreetmap.org/wiki/Overpass_turbo/Polygon_Features).
+use super::Tagged;
+use hashbrown::HashMap;
+use std::sync::OnceLock;
+
+pub static KEY: &'static str = "area";
+
+pub static YES: &'static str = "yes";
+pub static NO: &'static str = "no";
+
+/// Which values of a key make a closed way an area
+#[derive(Clone, Debug, PartialEq)]
+pub enum Rule {
+    /// Every value except `no`
+    All,
+    /// Only the listed values
+    Only(Vec<&'static str>),
+    /// Every value except `no` and the listed values
+    Except(Vec<&'static str>),
+}
+
+impl Rule {
+    fn accepts(&self, value: &str) -> bool {
+        if value == NO {
+            return false;
+        }
+        match self {
+            Rule::All => true,
+            Rule::Only(values) => values.contains(&value),
+            Rule::Except(values) => !values.contains(&value),
+        }
+    }
+}
+
+/// Table of area rules keyed to tag key
+#[derive(Clone, Debug, Default)]
+pub struct AreaRules {
+    rules: HashMap<&'static str, Rule>,
+}
+
+impl AreaRules {
+    /// Rule table with no keys, so only `area=yes` creates an area
+    pub fn empty() -> Self {
+        AreaRules::default()
+    }
+
+    /// Shared instance of the standard rule table
+    pub fn standard() -> &'static AreaRules {
+        static STANDARD: OnceLock<AreaRules> = OnceLock::new();
+
+        STANDARD.get_or_init(|| {
+            AreaRules::empty()
+                .with("aeroway", Rule::Except(vec!["taxiway"]))
+                .with("amenity", Rule::All)
+                .with("area:highway", Rule::All)
+                .with(
+                    "barrier",
+                    Rule::Only(vec![
+                        "city_wall",
+                        "ditch",
+                        "hedge",
+                        "retaining_wall",
+                        "spikes",
+                    ]),
+                )
+                .with("boundary", Rule::Only(vec!["protected_area"]))
+                .with("building", Rule::All)
+                .with("building:part", Rule::All)
+                .with("craft", Rule::All)
+                .with("golf", Rule::All)
+                .with("historic", Rule::All)
+                .with(
+                    "highway",
+                    Rule::Only(vec!["services", "rest_area", "escape"]),
+                )
+                .with("landuse", Rule::All)
+                .with("leisure", Rule::All)
+                .with(
+                    "man_made",
+                    Rule::Except(vec!["cutline", "embankment", "pipeline"]),
+                )
+                .with("military", Rule::All)
+                .with(
+                    "natural",
+                    Rule::Except(vec![
+                        "coastline",
+                        "cliff",
+                        "ridge",
+                        "arete",
+                        "tree_row",
+                    ]),
+                )
+                .with("office", Rule::All)
+                .with("place", Rule::All)
+                .with(
+                    "power",
+                    Rule::Only(vec![
+                        "plant",
+                        "substation",
+                        "generator",
+                        "transformer",
+                    ]),
+                )
+                .with(
+                    "public_transport",
+                    Rule::Only(vec!["platform", "station"]),
+                )
+                .with(
+                    "railway",
+                    Rule::Only(vec![
+                        "station",
+                        "turntable",
+                        "roundhouse",
+                        "platform",
+                    ]),
+                )
+                .with("shop", Rule::All)
+                .with("tourism", Rule::All)
+                .with(
+                    "waterway",
+                    Rule::Only(vec!["riverbank", "dock", "boatyard", "dam"]),
+                )
+        })
+    }
+
+    /// Add or replace the rule for a key
+    pub fn with(mut self, key: &'static str, rule: Rule) -> Self {
+        self.insert(key, rule);
+        self
+    }
+
+    /// Add or replace the rule for a key
+    pub fn insert(&mut self, key: &'static str, rule: Rule) {
+        self.rules.insert(key, rule);
+    }
+
+    /// Remove the rule for a key so it no longer creates areas
+    pub fn remove(&mut self, key: &str) -> Option<Rule> {
+        self.rules.remove(key)
+    }
+
+    pub fn get(&self, key: &str) -> Option<&Rule> {
+        self.rules.get(key)
+    }
+
+    /// Whether the tags describe an area, assuming the element is closed
+    pub fn is_area<T: Tagged + ?Sized>(&self, element: &T) -> bool {
+        match element.get_tag(KEY) {
+            Some(value) if value == YES => return true,
+            Some(value) if value == NO => return false,
+            _ => (),
+        }
+        self.rules.iter().any(|(key, rule)| {
+            element
+                .get_tag(key)
+                .is_some_and(|value| rule.accepts(value))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{node::Node, way::Way, TagMap};
+
+    fn closed_way<'a>(
+        nodes: &'a [Node],
+        tags: &[(&'static str, &'static str)],
+    ) -> Way<'a> {
+        let mut map = TagMap::new();
+        for &(k, v) in tags {
+            map.insert(k, v);
+        }
+        Way {
+            nodes: vec![&nodes[0], &nodes[1], &nodes[2], &nodes[0]],
+            tags: Some(map),
+            ..Default::default()
+        }
+    }
+
+    fn nodes() -> Vec<Node> {
+        (1..=3)
+            .map(|id| Node {
+                id,
+                lat: id as f32,
+                lon: (id * id) as f32,
+                ..Default::default()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn standard_rules() {
+        let n = nodes();
+
+        assert!(closed_way(&n, &[("building", "yes")]).is_area());
+        assert!(closed_way(&n, &[("landuse", "forest")]).is_area());
+        assert!(!closed_way(&n, &[("barrier", "fence")]).is_area());
+        assert!(closed_way(&n, &[("barrier", "hedge")]).is_area());
+        assert!(!closed_way(&n, &[("natural", "coastline")]).is_area());
+        assert!(!closed_way(&n, &[("highway", "pedestrian")]).is_area());
+        assert!(
+            closed_way(&n, &[("highway", "pedestrian"), ("area", "yes")])
+                .is_area()
+        );
+        assert!(
+            !closed_way(&n, &[("building", "yes"), ("area", "no")]).is_area()
+        );
+    }
+
+    #[test]
+    fn configured_rules() {
+        let n = nodes();
+        let way = closed_way(&n, &[("barrier", "fence")]);
+        let rules = AreaRules::empty().with("barrier", Rule::All);
+
+        assert!(way.is_area_with(&rules));
+        assert!(!closed_way(&n, &[("building", "yes")]).is_area_with(&rules));
+    }
+}
@@ -0,0 +1,285 @@
+//! Values of the [`highway`](https://wiki.openstreetmap.org/wiki/Key:highway)
+//! key describing roads, paths and other ways of travel.
+use super::Tagged;
+use std::{convert::Infallible, fmt, str::FromStr};
+
+pub static KEY: &'static str = "highway";
+
+/// Key that further describes a `highway=footway`
+pub static FOOTWAY_KEY: &'static str = "footway";
+
+/// Value of `footway=*` for a sidewalk along a road
+pub static SIDEWALK: &'static str = "sidewalk";
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Highway {
+    /// A restricted access major divided highway, normally with 2 or more
+    /// running lanes plus emergency hard shoulder. Equivalent to the
+    /// Freeway, Autobahn, etc.
+    Motorway,
+    /// The most important roads in a country's system that aren't
+    /// motorways. (Need not necessarily be a divided highway.)
+    Trunk,
+    /// Links between larger towns
+    Primary,
+    /// Links between towns
+    Secondary,
+    /// Links between smaller towns and villages
+    Tertiary,
+    /// The least important through roads in a country's system — i.e. minor
+    /// roads of a lower classification than tertiary, but which serve a
+    /// purpose other than access to properties. (Often link villages and
+    /// hamlets.)
+    ///
+    /// The word 'unclassified' is a historical artefact of the UK road
+    /// system and does not mean that the classification is unknown; use
+    /// `Road` for that.
+    Unclassified,
+    /// Roads which serve as an access to housing, without function of
+    /// connecting settlements. Often lined with housing.
+    Residential,
+    /// Slip roads and ramps to or from a motorway
+    MotorwayLink,
+    TrunkLink,
+    PrimaryLink,
+    SecondaryLink,
+    TertiaryLink,
+    /// Streets where pedestrians have legal priority over cars
+    LivingStreet,
+    /// For access roads to, or within an industrial estate, camp site,
+    /// business park, car park etc. Can be used in conjunction with
+    /// `service=*` to indicate the type of usage and with `access=*` to
+    /// indicate who can use it and in what circumstances.
+    Service,
+    /// Pedestrianised roads in shopping or residential areas
+    Pedestrian,
+    /// Roads for mostly agricultural or forestry uses. To describe the
+    /// quality of a track, see `tracktype=*`. Note: Although tracks are
+    /// often rough with unpaved surfaces, this tag is not describing the
+    /// quality of a road but its use.
+    Track,
+    BusGuideway,
+    /// Lanes reserved for buses
+    Busway,
+    /// Emergency escape lane for vehicles with failing brakes
+    Escape,
+    Raceway,
+    /// A road of unknown classification
+    Road,
+    /// For designated footpaths; i.e., mainly/exclusively for pedestrians.
+    /// This includes walking tracks and gravel paths. If bicycles are
+    /// allowed as well, you can indicate this by adding a `bicycle=yes` tag.
+    /// Should not be used for paths where the primary or intended usage is
+    /// unknown.
+    Footway,
+    /// For horse riders. Equivalent to `highway=path` + `horse=designated`.
+    Bridleway,
+    /// For flights of steps (stairs) on footways. Use with `step_count=*` to
+    /// indicate the number of steps.
+    Steps,
+    /// Hallway inside a building
+    Corridor,
+    /// Non-specific path for non-motorized travel
+    Path,
+    /// For designated cycleways. Add `foot=*` only if
+    /// default-access-restrictions do not apply.
+    Cycleway,
+    /// Public transport platform
+    Platform,
+    /// Difficult mountain route secured with fixed cables and ladders
+    ViaFerrata,
+    /// Lift between levels, mapped on the way or node where it's entered
+    Elevator,
+    /// Service station with fuel, food and toilets next to a motorway or
+    /// trunk road
+    Services,
+    /// Parking and toilets next to a motorway or trunk road, without fuel
+    RestArea,
+    /// Lay-by where vehicles may stop only in an emergency
+    EmergencyBay,
+    /// Road under construction, with `construction=*` giving its future type
+    Construction,
+    /// Planned road
+    Proposed,
+    /// Any other value
+    Unknown(String),
+}
+
+impl Highway {
+    /// Highway value of the element's `highway` tag
+    pub fn of<T: Tagged + ?Sized>(element: &T) -> Option<Highway> {
+        element.get_tag(KEY).map(|value| value.parse().unwrap())
+    }
+
+    pub fn as_str(&self) -> &str {
+        match self {
+            Highway::Motorway => "motorway",
+            Highway::Trunk => "trunk",
+            Highway::Primary => "primary",
+            Highway::Secondary => "secondary",
+            Highway::Tertiary => "tertiary",
+            Highway::Unclassified => "unclassified",
+            Highway::Residential => "residential",
+            Highway::MotorwayLink => "motorway_link",
+            Highway::TrunkLink => "trunk_link",
+            Highway::PrimaryLink => "primary_link",
+            Highway::SecondaryLink => "secondary_link",
+            Highway::TertiaryLink => "tertiary_link",
+            Highway::LivingStreet => "living_street",
+            Highway::Service => "service",
+            Highway::Pedestrian => "pedestrian",
+            Highway::Track => "track",
+            Highway::BusGuideway => "bus_guideway",
+            Highway::Busway => "busway",
+            Highway::Escape => "escape",
+            Highway::Raceway => "raceway",
+            Highway::Road => "road",
+            Highway::Footway => "footway",
+            Highway::Bridleway => "bridleway",
+            Highway::Steps => "steps",
+            Highway::Corridor => "corridor",
+            Highway::Path => "path",
+            Highway::Cycleway => "cycleway",
+            Highway::Platform => "platform",
+            Highway::ViaFerrata => "via_ferrata",
+            Highway::Elevator => "elevator",
+            Highway::Services => "services",
+            Highway::RestArea => "rest_area",
+            Highway::EmergencyBay => "emergency_bay",
+            Highway::Construction => "construction",
+            Highway::Proposed => "proposed",
+            Highway::Unknown(value) => value,
+        }
+    }
+
+    /// Importance of the road within the road network, from `1` for
+    /// motorways to `12` for ways that aren't roads. Link roads rank with
+    /// the road they connect to.
+    pub fn rank(&self) -> u8 {
+        match self {
+            Highway::Motorway | Highway::MotorwayLink => 1,
+            Highway::Trunk | Highway::TrunkLink => 2,
+            Highway::Primary | Highway::PrimaryLink => 3,
+            Highway::Secondary | Highway::SecondaryLink => 4,
+            Highway::Tertiary | Highway::TertiaryLink => 5,
+            Highway::Unclassified | Highway::Road => 6,
+            Highway::Residential => 7,
+            Highway::LivingStreet => 8,
+            Highway::Service | Highway::Busway | Highway::BusGuideway => 9,
+            Highway::Track => 10,
+            Highway::Pedestrian
+            | Highway::Footway
+            | Highway::Bridleway
+            | Highway::Steps
+            | Highway::Corridor
+            | Highway::Path
+            | Highway::Cycleway
+            | Highway::Platform
+            | Highway::ViaFerrata
+            | Highway::Elevator => 11,
+            _ => 12,
+        }
+    }
+
+    /// Whether the road is a link (slip road) to a road of the same class
+    pub fn is_link(&self) -> bool {
+        matches!(
+            self,
+            Highway::MotorwayLink
+                | Highway::TrunkLink
+                | Highway::PrimaryLink
+                | Highway::SecondaryLink
+                | Highway::TertiaryLink
+        )
+    }
+}
+
+impl FromStr for Highway {
+    type Err = Infallible;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        Ok(match value {
+            "motorway" => Highway::Motorway,
+            "trunk" => Highway::Trunk,
+            "primary" => Highway::Primary,
+            "secondary" => Highway::Secondary,
+            "tertiary" => Highway::Tertiary,
+            "unclassified" => Highway::Unclassified,
+            "residential" => Highway::Residential,
+            "motorway_link" => Highway::MotorwayLink,
+            "trunk_link" => Highway::TrunkLink,
+            "primary_link" => Highway::PrimaryLink,
+            "secondary_link" => Highway::SecondaryLink,
+            "tertiary_link" => Highway::TertiaryLink,
+            "living_street" => Highway::LivingStreet,
+            "service" => Highway::Service,
+            "pedestrian" => Highway::Pedestrian,
+            "track" => Highway::Track,
+            "bus_guideway" => Highway::BusGuideway,
+            "busway" => Highway::Busway,
+            "escape" => Highway::Escape,
+            "raceway" => Highway::Raceway,
+            "road" => Highway::Road,
+            "footway" => Highway::Footway,
+            "bridleway" => Highway::Bridleway,
+            "steps" => Highway::Steps,
+            "corridor" => Highway::Corridor,
+            "path" => Highway::Path,
+            "cycleway" => Highway::Cycleway,
+            "platform" => Highway::Platform,
+            "via_ferrata" => Highway::ViaFerrata,
+            "elevator" => Highway::Elevator,
+            "services" => Highway::Services,
+            "rest_area" => Highway::RestArea,
+            "emergency_bay" => Highway::EmergencyBay,
+            "construction" => Highway::Construction,
+            "proposed" => Highway::Proposed,
+            other => Highway::Unknown(other.to_owned()),
+        })
+    }
+}
+
+impl fmt::Display for Highway {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_and_display() {
+        for value in &["motorway_link", "living_street", "road", "ferry"] {
+            let highway: Highway = value.parse().unwrap();
+            assert_eq!(highway.to_string(), *value);
+        }
+        assert_eq!(
+            "ferry".parse::<Highway>().unwrap(),
+            Highway::Unknown("ferry".to_owned())
+        );
+    }
+
+    #[test]
+    fn parse_roadside_and_mountain_values() {
+        for (value, highway) in &[
+            ("services", Highway::Services),
+            ("rest_area", Highway::RestArea),
+            ("via_ferrata", Highway::ViaFerrata),
+            ("elevator", Highway::Elevator),
+            ("emergency_bay", Highway::EmergencyBay),
+        ] {
+            assert_eq!(value.parse::<Highway>().unwrap(), *highway);
+            assert_eq!(highway.as_str(), *value);
+        }
+    }
+
+    #[test]
+    fn rank_links_with_their_road() {
+        assert_eq!(Highway::MotorwayLink.rank(), Highway::Motorway.rank());
+        assert!(Highway::Primary.rank() < Highway::Residential.rank());
+        assert!(Highway::TrunkLink.is_link());
+        assert!(!Highway::Trunk.is_link());
+    }
+}
@@ -0,0 +1,244 @@
+//! Speed limits of the [`maxspeed`](https://wiki.openstreetmap.org/wiki/Key:maxspeed)
+//! key, such as `50`, `30 mph`, `none`, `walk` or a zone code like
+//! `RU:urban`, normalized to km/h.
+//!
+//! Zone codes are resolved with a table of
+//! [implicit limits](https://wiki.openstreetmap.org/wiki/Default_speed_limits)
+//! by country and road type.
+use super::{
+    measure::{split_unit, ParseError},
+    Tagged,
+};
+use hashbrown::HashMap;
+use std::sync::OnceLock;
+
+pub static KEY: &'static str = "maxspeed";
+pub static FORWARD: &'static str = "maxspeed:forward";
+pub static BACKWARD: &'static str = "maxspeed:backward";
+
+/// Keys giving the zone that an untagged or zone-valued limit comes from
+pub static ZONE_KEY: &'static str = "zone:maxspeed";
+pub static TYPE_KEY: &'static str = "maxspeed:type";
+pub static SOURCE_KEY: &'static str = "source:maxspeed";
+
+/// km/h assumed for `maxspeed=walk`
+pub const WALKING_SPEED: f32 = 6.0;
+
+const MPH: f32 = 1.609_344;
+const KNOT: f32 = 1.852;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Speed {
+    /// km/h
+    Limit(f32),
+    /// No speed limit, as on parts of the German Autobahn
+    Unlimited,
+    /// Walking pace
+    Walk,
+    /// Limit shown on variable message signs
+    Signals,
+    /// Implicit limit of a country's road type, such as `DE:urban`
+    Zone { country: String, zone: String },
+}
+
+impl Speed {
+    /// Speed limit of the element's `maxspeed` tag
+    pub fn of<T: Tagged + ?Sized>(
+        element: &T,
+    ) -> Option<Result<Speed, ParseError>> {
+        element.get_tag(KEY).map(parse)
+    }
+
+    /// km/h of a numeric limit or walking pace
+    pub fn kmh(&self) -> Option<f32> {
+        match self {
+            Speed::Limit(kmh) => Some(*kmh),
+            Speed::Walk => Some(WALKING_SPEED),
+            _ => None,
+        }
+    }
+
+    /// Limit of a zone from the table, or of a numeric zone such as
+    /// `DE:30`. Other speeds are returned as they are.
+    pub fn resolve(&self, zones: &Zones) -> Option<Speed> {
+        match self {
+            Speed::Zone { country, zone } => zones
+                .get(country, zone)
+                .cloned()
+                .or_else(|| zone.parse().ok().map(Speed::Limit)),
+            other => Some(other.clone()),
+        }
+    }
+}
+
+/// Implicit speed limits by country code and zone, such as `urban`,
+/// `rural` or `motorway`
+#[derive(Clone, Debug, Default)]
+pub struct Zones {
+    limits: HashMap<(String, String), Speed>,
+}
+
+impl Zones {
+    /// Shared instance of the built-in table
+    pub fn standard() -> &'static Zones {
+        static STANDARD: OnceLock<Zones> = OnceLock::new();
+
+        STANDARD.get_or_init(|| {
+            use Speed::{Limit, Unlimited, Walk};
+            let mph = |limit: f32| Limit(limit * MPH);
+            let mut zones = Zones::default();
+
+            let countries = [
+                ("AT", Limit(50.0), Limit(100.0), Limit(100.0), Limit(130.0)),
+                ("BE", Limit(50.0), Limit(90.0), Limit(120.0), Limit(120.0)),
+                ("CH", Limit(50.0), Limit(80.0), Limit(100.0), Limit(120.0)),
+                ("CZ", Limit(50.0), Limit(90.0), Limit(110.0), Limit(130.0)),
+                ("DE", Limit(50.0), Limit(100.0), Limit(100.0), Unlimited),
+                ("DK", Limit(50.0), Limit(80.0), Limit(80.0), Limit(130.0)),
+                ("ES", Limit(50.0), Limit(90.0), Limit(100.0), Limit(120.0)),
+                ("FI", Limit(50.0), Limit(80.0), Limit(100.0), Limit(120.0)),
+                ("FR", Limit(50.0), Limit(80.0), Limit(110.0), Limit(130.0)),
+                ("IT", Limit(50.0), Limit(90.0), Limit(110.0), Limit(130.0)),
+                ("NL", Limit(50.0), Limit(80.0), Limit(100.0), Limit(130.0)),
+                ("PL", Limit(50.0), Limit(90.0), Limit(120.0), Limit(140.0)),
+                ("RU", Limit(60.0), Limit(90.0), Limit(90.0), Limit(110.0)),
+            ];
+            for (country, urban, rural, trunk, motorway) in countries {
+                zones.insert(country, "urban", urban);
+                zones.insert(country, "rural", rural);
+                zones.insert(country, "trunk", trunk);
+                zones.insert(country, "motorway", motorway);
+            }
+            for country in &["AT", "CH", "DE"] {
+                zones.insert(country, "living_street", Walk);
+                zones.insert(country, "bicycle_road", Limit(30.0));
+            }
+            for (country, limit) in &[
+                ("BE", 20.0),
+                ("FR", 20.0),
+                ("NL", 15.0),
+                ("PL", 20.0),
+                ("RU", 20.0),
+            ] {
+                zones.insert(country, "living_street", Limit(*limit));
+            }
+
+            // national speed limits of the United Kingdom
+            zones.insert("GB", "nsl_restricted", mph(30.0));
+            zones.insert("GB", "nsl_single", mph(60.0));
+            zones.insert("GB", "nsl_dual", mph(70.0));
+            zones.insert("GB", "motorway", mph(70.0));
+
+            zones
+        })
+    }
+
+    pub fn insert(&mut self, country: &str, zone: &str, speed: Speed) {
+        self.limits
+            .insert((country.to_owned(), zone.to_owned()), speed);
+    }
+
+    pub fn get(&self, country: &str, zone: &str) -> Option<&Speed> {
+        self.limits.get(&(country.to_owned(), zone.to_owned()))
+    }
+}
+
+/// Speed limit of the element, resolving zone codes. When `maxspeed` is
+/// missing or an unknown zone, the zone comes from `zone:maxspeed`,
+/// `maxspeed:type` or `source:maxspeed`.
+pub fn effective<T: Tagged + ?Sized>(
+    element: &T,
+    zones: &Zones,
+) -> Option<Speed> {
+    [KEY, ZONE_KEY, TYPE_KEY, SOURCE_KEY]
+        .iter()
+        .filter_map(|key| element.get_tag(key))
+        .filter_map(|value| parse_with(value, zones).ok())
+        .find(|speed| !matches!(speed, Speed::Zone { .. }))
+}
+
+/// Parse a `maxspeed` value, resolving zone codes found in the table
+pub fn parse_with(value: &str, zones: &Zones) -> Result<Speed, ParseError> {
+    let speed = parse(value)?;
+    Ok(speed.resolve(zones).unwrap_or(speed))
+}
+
+/// Parse a `maxspeed` value
+pub fn parse(value: &str) -> Result<Speed, ParseError> {
+    match value.trim() {
+        "none" => return Ok(Speed::Unlimited),
+        "walk" => return Ok(Speed::Walk),
+        "signals" => return Ok(Speed::Signals),
+        _ => (),
+    }
+    if let Some(i) = value.find(':') {
+        let (country, zone) = (value[..i].trim(), value[i + 1..].trim());
+        // a country code, optionally with a subdivision like `BE-VLG`
+        if country.len() < 2
+            || !country.chars().all(|c| c.is_ascii_uppercase() || c == '-')
+            || zone.is_empty()
+        {
+            return Err(ParseError::new(
+                value,
+                "expected a zone like DE:urban",
+            ));
+        }
+        return Ok(Speed::Zone {
+            country: country.to_owned(),
+            zone: zone.to_owned(),
+        });
+    }
+    let (number, unit) = split_unit(value)?;
+    let factor = match unit {
+        "" | "km/h" | "kmh" | "kph" => 1.0,
+        "mph" => MPH,
+        "knots" => KNOT,
+        _ => return Err(ParseError::new(value, "unknown speed unit")),
+    };
+    Ok(Speed::Limit(number * factor))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{way::Way, TagMap};
+
+    #[test]
+    fn parses_limits() {
+        assert_eq!(parse("50"), Ok(Speed::Limit(50.0)));
+        assert_eq!(parse("50 km/h"), Ok(Speed::Limit(50.0)));
+        assert!((parse("30 mph").unwrap().kmh().unwrap() - 48.28).abs() < 0.01);
+        assert_eq!(parse("none"), Ok(Speed::Unlimited));
+        assert_eq!(parse("walk").unwrap().kmh(), Some(WALKING_SPEED));
+        assert_eq!(
+            parse("RU:urban"),
+            Ok(Speed::Zone {
+                country: "RU".to_owned(),
+                zone: "urban".to_owned()
+            })
+        );
+        assert!(parse("fast").is_err());
+        assert!(parse("50 mps").is_err());
+    }
+
+    #[test]
+    fn resolves_zones() {
+        let zones = Zones::standard();
+        let mut tags = TagMap::new();
+        tags.insert(SOURCE_KEY, "FR:rural");
+        let mut way = Way {
+            tags: Some(tags),
+            ..Default::default()
+        };
+
+        assert_eq!(parse_with("DE:urban", zones), Ok(Speed::Limit(50.0)));
+        assert_eq!(parse_with("DE:motorway", zones), Ok(Speed::Unlimited));
+        assert_eq!(parse_with("DE:30", zones), Ok(Speed::Limit(30.0)));
+        assert_eq!(effective(&way, zones), Some(Speed::Limit(80.0)));
+
+        way.tags.as_mut().unwrap().insert(KEY, "XX:urban");
+        assert_eq!(effective(&way, zones), Some(Speed::Limit(80.0)));
+        way.tags.as_mut().unwrap().insert(KEY, "70");
+        assert_eq!(effective(&way, zones), Some(Speed::Limit(70.0)));
+    }
+}
@@ -0,0 +1,162 @@
+//! Lengths and weights of tags such as `maxheight=11'6"` or
+//! `maxweight=7.5 t`, normalized to meters and tonnes.
+//!
+//! https://wiki.openstreetmap.org/wiki/Map_features/Units
+use super::Tagged;
+use std::{error, fmt};
+
+pub static MAXHEIGHT: &'static str = "maxheight";
+pub static MAXWIDTH: &'static str = "maxwidth";
+pub static MAXLENGTH: &'static str = "maxlength";
+pub static MAXWEIGHT: &'static str = "maxweight";
+pub static MAXAXLELOAD: &'static str = "maxaxleload";
+pub static WIDTH: &'static str = "width";
+
+/// Value of a limit, such as `maxheight=none`, that doesn't restrict
+pub static NONE: &'static str = "none";
+
+const FOOT: f32 = 0.3048;
+const INCH: f32 = 0.0254;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct ParseError {
+    pub value: String,
+    pub reason: &'static str,
+}
+
+impl ParseError {
+    pub(crate) fn new(value: &str, reason: &'static str) -> Self {
+        ParseError {
+            value: value.to_owned(),
+            reason,
+        }
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "invalid value {:?}: {}", self.value, self.reason)
+    }
+}
+
+impl error::Error for ParseError {}
+
+/// Meters of a length such as `3.5`, `3.5 m`, `350 cm`, `11'6"` or `12 ft`.
+/// A limit of `none` is infinitely long.
+pub fn parse_length(value: &str) -> Result<f32, ParseError> {
+    let text = value.trim();
+    if text == NONE {
+        return Ok(f32::INFINITY);
+    }
+    if text.contains('\'') || text.ends_with('"') {
+        return parse_feet_inches(text)
+            .ok_or_else(|| ParseError::new(value, "expected feet and inches"));
+    }
+    let (number, unit) = split_unit(value)?;
+    let factor = match unit {
+        "" | "m" => 1.0,
+        "cm" => 0.01,
+        "mm" => 0.001,
+        "km" => 1000.0,
+        "ft" => FOOT,
+        "in" => INCH,
+        "mi" => 1609.344,
+        "nmi" => 1852.0,
+        _ => return Err(ParseError::new(value, "unknown length unit")),
+    };
+    Ok(number * factor)
+}
+
+/// Tonnes of a weight such as `7.5`, `7.5 t`, `7500 kg`, `10 st` (short
+/// tons), `10 lt` (long tons) or `20000 lbs`. A limit of `none` is
+/// infinitely heavy.
+pub fn parse_weight(value: &str) -> Result<f32, ParseError> {
+    if value.trim() == NONE {
+        return Ok(f32::INFINITY);
+    }
+    let (number, unit) = split_unit(value)?;
+    let factor = match unit {
+        "" | "t" => 1.0,
+        "kg" => 0.001,
+        "st" => 0.907_184_7,
+        "lt" => 1.016_046_9,
+        "lb" | "lbs" => 0.000_453_592_37,
+        _ => return Err(ParseError::new(value, "unknown weight unit")),
+    };
+    Ok(number * factor)
+}
+
+/// Meters of the element's length tag, such as `maxheight` or `width`
+pub fn length_of<T: Tagged + ?Sized>(
+    element: &T,
+    key: &str,
+) -> Option<Result<f32, ParseError>> {
+    element.get_tag(key).map(parse_length)
+}
+
+/// Tonnes of the element's weight tag, such as `maxweight`
+pub fn weight_of<T: Tagged + ?Sized>(
+    element: &T,
+    key: &str,
+) -> Option<Result<f32, ParseError>> {
+    element.get_tag(key).map(parse_weight)
+}
+
+/// Non-negative number and the unit after it, which may be separated by a
+/// space or empty
+pub(crate) fn split_unit(value: &str) -> Result<(f32, &str), ParseError> {
+    let text = value.trim();
+    let end = text
+        .find(|c: char| !(c.is_ascii_digit() || c == '.'))
+        .unwrap_or(text.len());
+    let number = text[..end]
+        .parse::<f32>()
+        .map_err(|_| ParseError::new(value, "expected a number"))?;
+
+    Ok((number, text[end..].trim()))
+}
+
+/// `11'6"`, `11'` or `6"`
+fn parse_feet_inches(text: &str) -> Option<f32> {
+    let (feet, rest) = match text.find('\'') {
+        Some(i) => (text[..i].trim().parse::<f32>().ok()?, &text[i + 1..]),
+        None => (0.0, text),
+    };
+    let rest = rest.trim();
+    let inches = if rest.is_empty() {
+        0.0
+    } else {
+        rest.strip_suffix('"')?.trim().parse::<f32>().ok()?
+    };
+    Some(feet * FOOT + inches * INCH)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn close(a: f32, b: f32) -> bool {
+        (a - b).abs() < 0.001
+    }
+
+    #[test]
+    fn lengths() {
+        assert!(close(parse_length("3.5").unwrap(), 3.5));
+        assert!(close(parse_length("350 cm").unwrap(), 3.5));
+        assert!(close(parse_length("11'6\"").unwrap(), 3.5052));
+        assert!(close(parse_length("12 ft").unwrap(), 3.6576));
+        assert_eq!(parse_length("none"), Ok(f32::INFINITY));
+        assert!(parse_length("3,5").is_err());
+        assert!(parse_length("default").is_err());
+        assert!(parse_length("3 yards").is_err());
+    }
+
+    #[test]
+    fn weights() {
+        assert!(close(parse_weight("7.5").unwrap(), 7.5));
+        assert!(close(parse_weight("7.5 t").unwrap(), 7.5));
+        assert!(close(parse_weight("7500 kg").unwrap(), 7.5));
+        assert!(close(parse_weight("10 st").unwrap(), 9.0718));
+        assert!(parse_weight("heavy").is_err());
+    }
+}
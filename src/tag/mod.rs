@@ -1,8 +1,14 @@
 pub mod access;
+pub mod area;
+pub mod highway;
+pub mod maxspeed;
+pub mod measure;
+pub mod railway;
 pub mod relation;
 pub mod restriction;
+pub mod reverse;
 pub mod surface;
-pub mod way_type;
+pub mod travel_by;
 
 use serde::Deserialize;
 
@@ -20,26 +26,21 @@ pub trait Tagged {
     fn has_tag(&self, key: &str) -> bool;
 }
 
+impl Tagged for crate::TagMap {
+    fn get_tag(&self, key: &str) -> Option<&str> {
+        self.get(key)
+    }
+
+    fn has_tag(&self, key: &str) -> bool {
+        self.contains_key(key)
+    }
+}
+
 pub static JUNCTION_TYPE: &'static str = "junction";
 pub static RAIL_TYPE: &'static str = "railway";
 pub static TYPE: &'static str = "type";
 pub static NAME: &'static str = "name";
 
-pub mod travel_by {
-    pub static BICYCLE: &'static str = "bicycle";
-    pub static BUS: &'static str = "bus";
-    pub static CAR: &'static str = "car";
-    pub static FOOT: &'static str = "foot";
-    pub static HORSE: &'static str = "horse";
-    pub static MOTOR_CAR: &'static str = "motorcar";
-    pub static MOTORCYCLE: &'static str = "motorcycle";
-    pub static MOTOR_VEHICLE: &'static str = "motor_vehicle";
-    pub static SERVICE_VEHICLE: &'static str = "psv";
-    pub static TRAM: &'static str = "tram";
-    pub static TRAIN: &'static str = "train";
-    pub static VEHICLE: &'static str = "vehicle";
-}
-
 /// Elevation change of way (especially stairs)
 pub mod incline {
     pub static KEY: &'static str = "incline";
@@ -0,0 +1,142 @@
+//! Values of the [`railway`](https://wiki.openstreetmap.org/wiki/Key:railway)
+//! key (`tag::RAIL_TYPE`) describing tracks and rail facilities.
+use super::{Tagged, RAIL_TYPE};
+use std::{convert::Infallible, fmt, str::FromStr};
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Railway {
+    /// Full sized passenger or freight trains in the standard gauge for the
+    /// country or state
+    Rail,
+    /// Higher-standard tram system, normally in its own right-of-way
+    LightRail,
+    /// City passenger rail service running mostly grade separated
+    Subway,
+    /// One or two carriage rail vehicles, usually sharing motor road
+    Tram,
+    /// Narrower than standard gauge railway
+    NarrowGauge,
+    Monorail,
+    /// Cable railway on a steep slope
+    Funicular,
+    /// Miniature railways, usually in parks
+    Miniature,
+    /// Railway running historic trains, usually a tourist attraction
+    Preserved,
+    /// Track no longer in use but not yet removed
+    Disused,
+    /// Former railway with the tracks removed
+    Abandoned,
+    Construction,
+    Platform,
+    Station,
+    Halt,
+    /// Any other value
+    Unknown(String),
+}
+
+impl Railway {
+    /// Railway value of the element's `railway` tag
+    pub fn of<T: Tagged + ?Sized>(element: &T) -> Option<Railway> {
+        element
+            .get_tag(RAIL_TYPE)
+            .map(|value| value.parse().unwrap())
+    }
+
+    pub fn as_str(&self) -> &str {
+        match self {
+            Railway::Rail => "rail",
+            Railway::LightRail => "light_rail",
+            Railway::Subway => "subway",
+            Railway::Tram => "tram",
+            Railway::NarrowGauge => "narrow_gauge",
+            Railway::Monorail => "monorail",
+            Railway::Funicular => "funicular",
+            Railway::Miniature => "miniature",
+            Railway::Preserved => "preserved",
+            Railway::Disused => "disused",
+            Railway::Abandoned => "abandoned",
+            Railway::Construction => "construction",
+            Railway::Platform => "platform",
+            Railway::Station => "station",
+            Railway::Halt => "halt",
+            Railway::Unknown(value) => value,
+        }
+    }
+
+    /// Whether trains can currently run on the way
+    pub fn is_track(&self) -> bool {
+        matches!(
+            self,
+            Railway::Rail
+                | Railway::LightRail
+                | Railway::Subway
+                | Railway::Tram
+                | Railway::NarrowGauge
+                | Railway::Monorail
+                | Railway::Funicular
+                | Railway::Miniature
+                | Railway::Preserved
+        )
+    }
+}
+
+impl FromStr for Railway {
+    type Err = Infallible;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        Ok(match value {
+            "rail" => Railway::Rail,
+            "light_rail" => Railway::LightRail,
+            "subway" => Railway::Subway,
+            "tram" => Railway::Tram,
+            "narrow_gauge" => Railway::NarrowGauge,
+            "monorail" => Railway::Monorail,
+            "funicular" => Railway::Funicular,
+            "miniature" => Railway::Miniature,
+            "preserved" => Railway::Preserved,
+            "disused" => Railway::Disused,
+            "abandoned" => Railway::Abandoned,
+            "construction" => Railway::Construction,
+            "platform" => Railway::Platform,
+            "station" => Railway::Station,
+            "halt" => Railway::Halt,
+            other => Railway::Unknown(other.to_owned()),
+        })
+    }
+}
+
+impl fmt::Display for Railway {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_and_display() {
+        for value in &["rail", "light_rail", "narrow_gauge", "turntable"] {
+            let railway: Railway = value.parse().unwrap();
+            assert_eq!(railway.to_string(), *value);
+        }
+        assert_eq!(
+            "turntable".parse::<Railway>().unwrap(),
+            Railway::Unknown("turntable".to_owned())
+        );
+        assert_eq!("subway".parse::<Railway>().unwrap(), Railway::Subway);
+    }
+
+    #[test]
+    fn only_running_lines_are_track() {
+        assert!(Railway::Rail.is_track());
+        assert!(Railway::Preserved.is_track());
+        assert!(Railway::Funicular.is_track());
+        assert!(!Railway::Disused.is_track());
+        assert!(!Railway::Abandoned.is_track());
+        assert!(!Railway::Platform.is_track());
+        assert!(!Railway::Unknown("turntable".to_owned()).is_track());
+    }
+}
@@ -22,3 +22,7 @@ pub static WATERWAY: &'static str = "waterway";
 /// Traffic enforcement devices (speed cameras, weight checks, etc.)
 /// https://wiki.openstreetmap.org/wiki/Relation:enforcement
 pub static ENFORCEMENT: &'static str = "enforcement";
+
+/// Area with holes or multiple outer rings, assembled from member ways
+/// https://wiki.openstreetmap.org/wiki/Relation:multipolygon
+pub static MULTIPOLYGON: &'static str = "multipolygon";
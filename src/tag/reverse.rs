@@ -0,0 +1,334 @@
+//! Direction-dependent tags that must change meaning when the node order of a
+//! way is reversed, so that the way still describes the same thing on the
+//! ground. The rules follow JOSM's
+//! [ReverseWayTagCorrector](https://josm.openstreetmap.de/wiki/Help/Action/Reverse):
+//!
+//! - `oneway=yes` and `oneway=-1` (including `oneway:*`) are swapped
+//! - `incline=up/down` are swapped and numeric inclines negated
+//! - `direction` values are turned around (`forward/backward`, compass points
+//!   and degrees)
+//! - `left/right` and `forward/backward` key parts and values are swapped, so
+//!   `sidewalk:left` becomes `sidewalk:right`
+//! - per-lane values such as `turn:lanes` are listed in the opposite order on
+//!   ways carrying traffic both ways
+//! - `forward/backward` roles of the way in relations are swapped
+use super::{incline, travel_by::Mode};
+use crate::{
+    access::oneway::{self, Direction},
+    relation::{role, Relation},
+    tag::access::ONE_WAY,
+    ElementID, TagMap,
+};
+use std::borrow::Cow;
+
+static FORWARD: &'static str = "forward";
+static BACKWARD: &'static str = "backward";
+static LEFT: &'static str = "left";
+static RIGHT: &'static str = "right";
+static DIRECTION: &'static str = "direction";
+
+/// Compass points paired with their opposite
+static COMPASS: [(&'static str, &'static str); 8] = [
+    ("N", "S"),
+    ("NNE", "SSW"),
+    ("NE", "SW"),
+    ("ENE", "WSW"),
+    ("E", "W"),
+    ("ESE", "WNW"),
+    ("SE", "NW"),
+    ("SSE", "NNW"),
+];
+
+/// Keys whose values are free text or describe turns relative to the
+/// direction of travel, so they never change when a way is reversed
+static UNCHANGED_VALUES: [&'static str; 5] =
+    ["name", "note", "description", "fixme", "turn"];
+
+/// Replacement of one tag by another when a way is reversed
+#[derive(Clone, Debug, PartialEq)]
+pub struct TagCorrection {
+    pub old_key: String,
+    pub old_value: String,
+    pub new_key: String,
+    pub new_value: String,
+}
+
+/// Replacement of a member role when a way is reversed
+#[derive(Clone, Debug, PartialEq)]
+pub struct RoleCorrection {
+    pub relation: ElementID,
+    /// Index of the member within the relation
+    pub position: usize,
+    pub old_role: &'static str,
+    pub new_role: &'static str,
+}
+
+/// Changes needed for the tags to keep their meaning once the way they're on
+/// is reversed
+pub fn tag_corrections(tags: &TagMap) -> Vec<TagCorrection> {
+    let both_ways = !is_oneway(tags);
+
+    tags.iter()
+        .filter_map(|(key, value)| {
+            let new_key = reverse_key(key);
+            let new_value = reverse_value(key, value).or_else(|| {
+                if both_ways {
+                    reverse_lanes(key, value).map(Cow::Owned)
+                } else {
+                    None
+                }
+            });
+
+            if new_key.is_none() && new_value.is_none() {
+                None
+            } else {
+                Some(TagCorrection {
+                    old_key: key.to_string(),
+                    old_value: value.to_string(),
+                    new_key: new_key.unwrap_or_else(|| key.to_string()),
+                    new_value: new_value.map_or_else(
+                        || value.to_string(),
+                        |value| value.into_owned(),
+                    ),
+                })
+            }
+        })
+        .collect()
+}
+
+/// Copy of the tags with direction-dependent keys and values flipped
+pub fn reverse_tags(tags: &TagMap) -> TagMap {
+    let corrections = tag_corrections(tags);
+    let mut reversed = tags.clone();
+
+    // remove every old key first so swapped pairs like `:forward` and
+    // `:backward` don't overwrite each other
+    for c in &corrections {
+        reversed.remove(&c.old_key);
+    }
+    for c in corrections {
+        reversed.insert(c.new_key, c.new_value);
+    }
+    reversed
+}
+
+/// Swap `forward` and `backward` roles of every membership of the way and
+/// return the changes made
+pub fn reverse_roles(
+    way_id: ElementID,
+    relations: &mut [Relation],
+) -> Vec<RoleCorrection> {
+    let mut corrections = Vec::new();
+
+    for relation in relations.iter_mut() {
+        for (position, member) in relation.members.iter_mut().enumerate() {
+            if !member.is_way(way_id) {
+                continue;
+            }
+            if let Some(new_role) =
+                swap(member.role, role::FORWARD, role::BACKWARD)
+            {
+                corrections.push(RoleCorrection {
+                    relation: relation.id,
+                    position,
+                    old_role: member.role,
+                    new_role,
+                });
+                member.role = new_role;
+            }
+        }
+    }
+    corrections
+}
+
+/// Key with `forward/backward` and `left/right` parts swapped, such as
+/// `lanes:forward` becoming `lanes:backward`, or `None` if the key has no
+/// direction
+pub fn reverse_key(key: &str) -> Option<String> {
+    let mut changed = false;
+    let parts: Vec<&str> = key
+        .split(':')
+        .map(|part| {
+            swap(part, FORWARD, BACKWARD)
+                .or_else(|| swap(part, LEFT, RIGHT))
+                .map_or(part, |p| {
+                    changed = true;
+                    p
+                })
+        })
+        .collect();
+
+    if changed {
+        Some(parts.join(":"))
+    } else {
+        None
+    }
+}
+
+/// Value with its direction flipped or `None` if the value isn't
+/// direction-dependent
+pub fn reverse_value(key: &str, value: &str) -> Option<Cow<'static, str>> {
+    let base = key.split(':').next().unwrap_or(key);
+
+    if base == ONE_WAY {
+        match value {
+            "yes" | "true" | "1" => Some(Cow::Borrowed("-1")),
+            "-1" => Some(Cow::Borrowed("yes")),
+            _ => None,
+        }
+    } else if base == incline::KEY {
+        swap(value, incline::UP, incline::DOWN)
+            .map(Cow::Borrowed)
+            .or_else(|| negate(value).map(Cow::Owned))
+    } else if key == DIRECTION {
+        swap(value, FORWARD, BACKWARD)
+            .or_else(|| swap(value, incline::UP, incline::DOWN))
+            .or_else(|| opposite_compass(value))
+            .map(Cow::Borrowed)
+            .or_else(|| opposite_degrees(value).map(Cow::Owned))
+    } else if UNCHANGED_VALUES.contains(&base) {
+        None
+    } else {
+        swap(value, LEFT, RIGHT)
+            .or_else(|| swap(value, FORWARD, BACKWARD))
+            .map(Cow::Borrowed)
+    }
+}
+
+/// Per-lane values in the opposite order. Lanes are listed left to right in
+/// the way direction, so `turn:lanes=left|through|right` on a two-way road
+/// becomes `right|through|left`.
+fn reverse_lanes(key: &str, value: &str) -> Option<String> {
+    if !key.ends_with(":lanes") || !value.contains('|') {
+        return None;
+    }
+    let lanes: Vec<&str> = value.split('|').rev().collect();
+
+    Some(lanes.join("|"))
+}
+
+/// Whether vehicles move in only one direction at a time
+fn is_oneway(tags: &TagMap) -> bool {
+    oneway::direction(tags, Mode::Vehicle) != Direction::Both
+}
+
+/// The other of two values if the text is one of them
+fn swap(text: &str, a: &'static str, b: &'static str) -> Option<&'static str> {
+    if text == a {
+        Some(b)
+    } else if text == b {
+        Some(a)
+    } else {
+        None
+    }
+}
+
+fn opposite_compass(value: &str) -> Option<&'static str> {
+    COMPASS.iter().find_map(|(a, b)| swap(value, a, b))
+}
+
+/// Bearing in degrees turned 180°
+fn opposite_degrees(value: &str) -> Option<String> {
+    let degrees: f32 = value.parse().ok()?;
+    let opposite = (degrees + 180.0) % 360.0;
+
+    Some(opposite.to_string())
+}
+
+/// Numeric value with its sign flipped, keeping any unit such as `%` or `°`
+fn negate(value: &str) -> Option<String> {
+    let digits = value.trim_start_matches(['-', '+']);
+    let number: String = digits
+        .chars()
+        .take_while(|c| c.is_ascii_digit() || *c == '.')
+        .collect();
+
+    if number.parse::<f32>().map_or(true, |n| n == 0.0) {
+        return None;
+    }
+
+    if value.starts_with('-') {
+        Some(digits.to_string())
+    } else {
+        Some(format!("-{}", digits))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::relation::Member;
+
+    fn tags(pairs: &[(&'static str, &'static str)]) -> TagMap {
+        pairs.iter().copied().collect()
+    }
+
+    #[test]
+    fn reverses_direction_dependent_tags() {
+        let reversed = reverse_tags(&tags(&[
+            ("highway", "secondary"),
+            ("sidewalk", "left"),
+            ("cycleway:right", "lane"),
+            ("maxspeed:forward", "50"),
+            ("maxspeed:backward", "30"),
+            ("incline", "-8%"),
+            ("turn:lanes", "left|through|right"),
+            ("name", "Left Bank Road"),
+        ]));
+
+        assert_eq!(reversed.get("sidewalk"), Some("right"));
+        assert_eq!(reversed.get("cycleway:left"), Some("lane"));
+        assert_eq!(reversed.get("maxspeed:forward"), Some("30"));
+        assert_eq!(reversed.get("maxspeed:backward"), Some("50"));
+        assert_eq!(reversed.get("incline"), Some("8%"));
+        assert_eq!(reversed.get("turn:lanes"), Some("right|through|left"));
+        assert_eq!(reversed.get("name"), Some("Left Bank Road"));
+        assert!(!reversed.contains_key("cycleway:right"));
+
+        // text built for the reversal is owned by the map and reverses back
+        let twice = reverse_tags(&reversed);
+        assert_eq!(twice.get("cycleway:right"), Some("lane"));
+        assert_eq!(twice.get("turn:lanes"), Some("left|through|right"));
+        assert_eq!(twice.get("incline"), Some("-8%"));
+    }
+
+    #[test]
+    fn keeps_lane_order_on_oneway() {
+        let reversed = reverse_tags(&tags(&[
+            ("oneway", "yes"),
+            ("turn:lanes", "left|through"),
+            ("direction", "NE"),
+        ]));
+
+        assert_eq!(reversed.get("oneway"), Some("-1"));
+        assert_eq!(reversed.get("turn:lanes"), Some("left|through"));
+        assert_eq!(reversed.get("direction"), Some("SW"));
+    }
+
+    #[test]
+    fn swaps_relation_roles() {
+        let mut relations = vec![Relation {
+            id: 9,
+            members: vec![
+                Member {
+                    id: 1,
+                    member_type: crate::item_type::WAY,
+                    role: role::FORWARD,
+                    ..Default::default()
+                },
+                Member {
+                    id: 2,
+                    member_type: crate::item_type::WAY,
+                    role: role::FORWARD,
+                    ..Default::default()
+                },
+            ],
+            ..Default::default()
+        }];
+        let corrections = reverse_roles(1, &mut relations);
+
+        assert_eq!(corrections.len(), 1);
+        assert_eq!(relations[0].members[0].role, role::BACKWARD);
+        assert_eq!(relations[0].members[1].role, role::FORWARD);
+    }
+}
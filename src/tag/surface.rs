@@ -1,5 +1,461 @@
+//! Road and path quality from the
+//! [`surface`](https://wiki.openstreetmap.org/wiki/Key:surface),
+//! [`smoothness`](https://wiki.openstreetmap.org/wiki/Key:smoothness) and
+//! [`tracktype`](https://wiki.openstreetmap.org/wiki/Key:tracktype) keys.
+//!
+//! Each value has a speed factor for each kind of traveller, from `1.0` for
+//! no slowdown to `0.0` for impassable, that routing profiles can apply to
+//! their base speed.
+use super::Tagged;
+use std::{convert::Infallible, fmt, str::FromStr};
+
 pub static KEY: &'static str = "surface";
+pub static SMOOTHNESS: &'static str = "smoothness";
+pub static TRACKTYPE: &'static str = "tracktype";
 
 pub static ASPHALT: &'static str = "asphalt";
 pub static CONCRETE: &'static str = "concrete";
 pub static PAVERS: &'static str = "paving_stones";
+
+/// Kind of traveller whose speed the surface affects
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Traveller {
+    Car = 0,
+    Bicycle = 1,
+    Wheelchair = 2,
+    Foot = 3,
+}
+
+/// Speed factors for car, bicycle, wheelchair and foot
+type Factors = [f32; 4];
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Surface {
+    /// Paved without further detail
+    Paved,
+    Asphalt,
+    /// Asphalt with stone chippings on top
+    Chipseal,
+    Concrete,
+    /// Two concrete strips for the wheels
+    ConcreteLanes,
+    ConcretePlates,
+    PavingStones,
+    /// Dressed stones with flat tops
+    Sett,
+    /// Rounded natural stones
+    UnhewnCobblestone,
+    /// Either sett or unhewn cobblestone
+    Cobblestone,
+    Bricks,
+    Metal,
+    Wood,
+    SteppingStones,
+    Rubber,
+    /// Athletics track
+    Tartan,
+    ArtificialTurf,
+    /// Unpaved without further detail
+    Unpaved,
+    /// Mix of gravel and sand packed hard
+    Compacted,
+    FineGravel,
+    Gravel,
+    Pebblestone,
+    Rock,
+    Ground,
+    Dirt,
+    Earth,
+    Grass,
+    /// Grass growing through a grid of concrete or plastic
+    GrassPaver,
+    Mud,
+    Sand,
+    Woodchips,
+    Snow,
+    Ice,
+    /// Any other value
+    Unknown(String),
+}
+
+impl Surface {
+    /// Surface of the element's `surface` tag
+    pub fn of<T: Tagged + ?Sized>(element: &T) -> Option<Surface> {
+        element.get_tag(KEY).map(|value| value.parse().unwrap())
+    }
+
+    pub fn as_str(&self) -> &str {
+        match self {
+            Surface::Paved => "paved",
+            Surface::Asphalt => "asphalt",
+            Surface::Chipseal => "chipseal",
+            Surface::Concrete => "concrete",
+            Surface::ConcreteLanes => "concrete:lanes",
+            Surface::ConcretePlates => "concrete:plates",
+            Surface::PavingStones => "paving_stones",
+            Surface::Sett => "sett",
+            Surface::UnhewnCobblestone => "unhewn_cobblestone",
+            Surface::Cobblestone => "cobblestone",
+            Surface::Bricks => "bricks",
+            Surface::Metal => "metal",
+            Surface::Wood => "wood",
+            Surface::SteppingStones => "stepping_stones",
+            Surface::Rubber => "rubber",
+            Surface::Tartan => "tartan",
+            Surface::ArtificialTurf => "artificial_turf",
+            Surface::Unpaved => "unpaved",
+            Surface::Compacted => "compacted",
+            Surface::FineGravel => "fine_gravel",
+            Surface::Gravel => "gravel",
+            Surface::Pebblestone => "pebblestone",
+            Surface::Rock => "rock",
+            Surface::Ground => "ground",
+            Surface::Dirt => "dirt",
+            Surface::Earth => "earth",
+            Surface::Grass => "grass",
+            Surface::GrassPaver => "grass_paver",
+            Surface::Mud => "mud",
+            Surface::Sand => "sand",
+            Surface::Woodchips => "woodchips",
+            Surface::Snow => "snow",
+            Surface::Ice => "ice",
+            Surface::Unknown(value) => value,
+        }
+    }
+
+    /// Whether the surface is sealed, or `None` for unknown values
+    pub fn is_paved(&self) -> Option<bool> {
+        match self {
+            Surface::Paved
+            | Surface::Asphalt
+            | Surface::Chipseal
+            | Surface::Concrete
+            | Surface::ConcreteLanes
+            | Surface::ConcretePlates
+            | Surface::PavingStones
+            | Surface::Sett
+            | Surface::UnhewnCobblestone
+            | Surface::Cobblestone
+            | Surface::Bricks
+            | Surface::Metal
+            | Surface::Wood
+            | Surface::Rubber
+            | Surface::Tartan => Some(true),
+            Surface::Unknown(_) => None,
+            _ => Some(false),
+        }
+    }
+
+    /// Speed factor for the traveller. Unknown values aren't penalized.
+    pub fn speed_factor(&self, traveller: Traveller) -> f32 {
+        let factors: Factors = match self {
+            Surface::Paved
+            | Surface::Asphalt
+            | Surface::Chipseal
+            | Surface::Concrete
+            | Surface::Unknown(_) => [1.0, 1.0, 1.0, 1.0],
+            Surface::ConcreteLanes => [0.9, 0.8, 0.6, 1.0],
+            Surface::ConcretePlates => [0.9, 0.9, 0.8, 1.0],
+            Surface::PavingStones => [0.9, 0.9, 0.9, 1.0],
+            Surface::Metal | Surface::Rubber | Surface::Tartan => {
+                [0.9, 0.9, 0.9, 1.0]
+            }
+            Surface::Bricks | Surface::Wood => [0.8, 0.8, 0.7, 1.0],
+            Surface::Sett => [0.7, 0.6, 0.5, 1.0],
+            Surface::Cobblestone => [0.5, 0.4, 0.2, 0.9],
+            Surface::UnhewnCobblestone => [0.4, 0.3, 0.1, 0.9],
+            Surface::Compacted => [0.8, 0.8, 0.7, 1.0],
+            Surface::FineGravel => [0.7, 0.7, 0.5, 1.0],
+            Surface::GrassPaver => [0.7, 0.6, 0.4, 0.9],
+            Surface::ArtificialTurf => [0.6, 0.6, 0.5, 1.0],
+            Surface::Unpaved
+            | Surface::Ground
+            | Surface::Dirt
+            | Surface::Earth => [0.6, 0.6, 0.3, 0.9],
+            Surface::Gravel | Surface::Pebblestone => [0.6, 0.5, 0.2, 0.9],
+            Surface::Grass => [0.4, 0.4, 0.2, 0.9],
+            Surface::Woodchips => [0.3, 0.4, 0.1, 0.9],
+            Surface::Rock => [0.3, 0.2, 0.0, 0.8],
+            Surface::Sand => [0.3, 0.2, 0.0, 0.7],
+            Surface::Snow | Surface::Ice => [0.3, 0.2, 0.1, 0.6],
+            Surface::Mud => [0.2, 0.2, 0.0, 0.6],
+            Surface::SteppingStones => [0.0, 0.1, 0.0, 0.8],
+        };
+        factors[traveller as usize]
+    }
+}
+
+impl FromStr for Surface {
+    type Err = Infallible;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        Ok(match value {
+            "paved" => Surface::Paved,
+            "asphalt" => Surface::Asphalt,
+            "chipseal" => Surface::Chipseal,
+            "concrete" => Surface::Concrete,
+            "concrete:lanes" => Surface::ConcreteLanes,
+            "concrete:plates" => Surface::ConcretePlates,
+            "paving_stones" => Surface::PavingStones,
+            "sett" => Surface::Sett,
+            "unhewn_cobblestone" => Surface::UnhewnCobblestone,
+            "cobblestone" => Surface::Cobblestone,
+            "bricks" => Surface::Bricks,
+            "metal" => Surface::Metal,
+            "wood" => Surface::Wood,
+            "stepping_stones" => Surface::SteppingStones,
+            "rubber" => Surface::Rubber,
+            "tartan" => Surface::Tartan,
+            "artificial_turf" => Surface::ArtificialTurf,
+            "unpaved" => Surface::Unpaved,
+            "compacted" => Surface::Compacted,
+            "fine_gravel" => Surface::FineGravel,
+            "gravel" => Surface::Gravel,
+            "pebblestone" => Surface::Pebblestone,
+            "rock" => Surface::Rock,
+            "ground" => Surface::Ground,
+            "dirt" => Surface::Dirt,
+            "earth" => Surface::Earth,
+            "grass" => Surface::Grass,
+            "grass_paver" => Surface::GrassPaver,
+            "mud" => Surface::Mud,
+            "sand" => Surface::Sand,
+            "woodchips" => Surface::Woodchips,
+            "snow" => Surface::Snow,
+            "ice" => Surface::Ice,
+            other => Surface::Unknown(other.to_owned()),
+        })
+    }
+}
+
+impl fmt::Display for Surface {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// How comfortably wheeled vehicles can travel over the surface
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Smoothness {
+    /// Usable by roller blades and racing bikes
+    Excellent,
+    /// Usable by racing bikes
+    Good,
+    /// Usable by city bikes, wheelchairs and sports cars
+    Intermediate,
+    /// Usable by trekking bikes and normal cars
+    Bad,
+    /// Usable by cars with high clearance
+    VeryBad,
+    /// Usable by off-road vehicles
+    Horrible,
+    /// Usable by tractors and specialized off-road vehicles
+    VeryHorrible,
+    /// No wheeled vehicle can pass
+    Impassable,
+    /// Any other value
+    Unknown(String),
+}
+
+impl Smoothness {
+    /// Smoothness of the element's `smoothness` tag
+    pub fn of<T: Tagged + ?Sized>(element: &T) -> Option<Smoothness> {
+        element
+            .get_tag(SMOOTHNESS)
+            .map(|value| value.parse().unwrap())
+    }
+
+    pub fn as_str(&self) -> &str {
+        match self {
+            Smoothness::Excellent => "excellent",
+            Smoothness::Good => "good",
+            Smoothness::Intermediate => "intermediate",
+            Smoothness::Bad => "bad",
+            Smoothness::VeryBad => "very_bad",
+            Smoothness::Horrible => "horrible",
+            Smoothness::VeryHorrible => "very_horrible",
+            Smoothness::Impassable => "impassable",
+            Smoothness::Unknown(value) => value,
+        }
+    }
+
+    /// Speed factor for the traveller. Unknown values aren't penalized.
+    pub fn speed_factor(&self, traveller: Traveller) -> f32 {
+        let factors: Factors = match self {
+            Smoothness::Excellent
+            | Smoothness::Good
+            | Smoothness::Unknown(_) => [1.0, 1.0, 1.0, 1.0],
+            Smoothness::Intermediate => [0.9, 0.9, 0.7, 1.0],
+            Smoothness::Bad => [0.6, 0.6, 0.2, 0.9],
+            Smoothness::VeryBad => [0.4, 0.4, 0.0, 0.8],
+            Smoothness::Horrible => [0.2, 0.2, 0.0, 0.7],
+            Smoothness::VeryHorrible => [0.1, 0.1, 0.0, 0.6],
+            Smoothness::Impassable => [0.0, 0.0, 0.0, 0.0],
+        };
+        factors[traveller as usize]
+    }
+}
+
+impl FromStr for Smoothness {
+    type Err = Infallible;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        Ok(match value {
+            "excellent" => Smoothness::Excellent,
+            "good" => Smoothness::Good,
+            "intermediate" => Smoothness::Intermediate,
+            "bad" => Smoothness::Bad,
+            "very_bad" => Smoothness::VeryBad,
+            "horrible" => Smoothness::Horrible,
+            "very_horrible" => Smoothness::VeryHorrible,
+            "impassable" => Smoothness::Impassable,
+            other => Smoothness::Unknown(other.to_owned()),
+        })
+    }
+}
+
+impl fmt::Display for Smoothness {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Firmness of a `highway=track`, from `grade1` (solid, usually paved) to
+/// `grade5` (soft, such as grass or sand)
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Tracktype {
+    Grade1,
+    /// Gravel or compacted hardcore
+    Grade2,
+    /// Even mixture of hard and soft materials
+    Grade3,
+    /// Mostly soft with some hard material mixed in
+    Grade4,
+    Grade5,
+    /// Any other value
+    Unknown(String),
+}
+
+impl Tracktype {
+    /// Track type of the element's `tracktype` tag
+    pub fn of<T: Tagged + ?Sized>(element: &T) -> Option<Tracktype> {
+        element
+            .get_tag(TRACKTYPE)
+            .map(|value| value.parse().unwrap())
+    }
+
+    pub fn as_str(&self) -> &str {
+        match self {
+            Tracktype::Grade1 => "grade1",
+            Tracktype::Grade2 => "grade2",
+            Tracktype::Grade3 => "grade3",
+            Tracktype::Grade4 => "grade4",
+            Tracktype::Grade5 => "grade5",
+            Tracktype::Unknown(value) => value,
+        }
+    }
+
+    /// Whether the track is sealed, or `None` for unknown values
+    pub fn is_paved(&self) -> Option<bool> {
+        match self {
+            Tracktype::Grade1 => Some(true),
+            Tracktype::Unknown(_) => None,
+            _ => Some(false),
+        }
+    }
+
+    /// Speed factor for the traveller. Unknown values aren't penalized.
+    pub fn speed_factor(&self, traveller: Traveller) -> f32 {
+        let factors: Factors = match self {
+            Tracktype::Grade1 | Tracktype::Unknown(_) => [1.0, 1.0, 1.0, 1.0],
+            Tracktype::Grade2 => [0.8, 0.8, 0.6, 1.0],
+            Tracktype::Grade3 => [0.6, 0.6, 0.3, 0.9],
+            Tracktype::Grade4 => [0.4, 0.4, 0.1, 0.9],
+            Tracktype::Grade5 => [0.3, 0.3, 0.0, 0.8],
+        };
+        factors[traveller as usize]
+    }
+}
+
+impl FromStr for Tracktype {
+    type Err = Infallible;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        Ok(match value {
+            "grade1" => Tracktype::Grade1,
+            "grade2" => Tracktype::Grade2,
+            "grade3" => Tracktype::Grade3,
+            "grade4" => Tracktype::Grade4,
+            "grade5" => Tracktype::Grade5,
+            other => Tracktype::Unknown(other.to_owned()),
+        })
+    }
+}
+
+impl fmt::Display for Tracktype {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Whether the element is paved according to its `surface` or, failing
+/// that, its `tracktype`
+pub fn is_paved<T: Tagged + ?Sized>(element: &T) -> Option<bool> {
+    Surface::of(element)
+        .and_then(|s| s.is_paved())
+        .or_else(|| Tracktype::of(element).and_then(|t| t.is_paved()))
+}
+
+/// Speed factor of the element for the traveller: the lowest factor of its
+/// `surface`, `smoothness` and `tracktype`, or `1.0` without any of them
+pub fn speed_factor<T: Tagged + ?Sized>(
+    element: &T,
+    traveller: Traveller,
+) -> f32 {
+    let surface = Surface::of(element).map(|s| s.speed_factor(traveller));
+    let smoothness = Smoothness::of(element).map(|s| s.speed_factor(traveller));
+    let tracktype = Tracktype::of(element).map(|t| t.speed_factor(traveller));
+
+    [surface, smoothness, tracktype]
+        .iter()
+        .flatten()
+        .fold(1.0, |lowest, factor| lowest.min(*factor))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::TagMap;
+
+    #[test]
+    fn parse_and_classify() {
+        for value in &["concrete:plates", "unhewn_cobblestone", "salt"] {
+            let surface: Surface = value.parse().unwrap();
+            assert_eq!(surface.to_string(), *value);
+        }
+        assert_eq!(Surface::Sett.is_paved(), Some(true));
+        assert_eq!(Surface::FineGravel.is_paved(), Some(false));
+        assert_eq!(Surface::Unknown("salt".to_owned()).is_paved(), None);
+
+        let mut tags = TagMap::new();
+        tags.insert(TRACKTYPE, "grade1");
+        assert_eq!(is_paved(&tags), Some(true));
+        tags.insert(KEY, "gravel");
+        assert_eq!(is_paved(&tags), Some(false));
+    }
+
+    #[test]
+    fn wheelchairs_penalized_most() {
+        let mut tags = TagMap::new();
+        tags.insert(KEY, "asphalt");
+        tags.insert(SMOOTHNESS, "bad");
+
+        assert_eq!(speed_factor(&tags, Traveller::Wheelchair), 0.2);
+        assert_eq!(speed_factor(&tags, Traveller::Bicycle), 0.6);
+        assert_eq!(speed_factor(&TagMap::new(), Traveller::Car), 1.0);
+        assert!(
+            Surface::Cobblestone.speed_factor(Traveller::Wheelchair)
+                < Surface::Cobblestone.speed_factor(Traveller::Foot)
+        );
+    }
+}
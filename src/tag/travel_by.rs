@@ -0,0 +1,135 @@
+//! Modes of transport used as keys to grant or restrict
+//! [access](https://wiki.openstreetmap.org/wiki/Key:access#Transport_mode_restrictions).
+use super::access::KEY as ACCESS;
+use std::{fmt, str::FromStr};
+
+pub static BICYCLE: &'static str = "bicycle";
+pub static BUS: &'static str = "bus";
+pub static CAR: &'static str = "car";
+pub static FOOT: &'static str = "foot";
+pub static HORSE: &'static str = "horse";
+pub static MOTOR_CAR: &'static str = "motorcar";
+pub static MOTORCYCLE: &'static str = "motorcycle";
+pub static MOTOR_VEHICLE: &'static str = "motor_vehicle";
+pub static SERVICE_VEHICLE: &'static str = "psv";
+pub static TRAM: &'static str = "tram";
+pub static TRAIN: &'static str = "train";
+pub static VEHICLE: &'static str = "vehicle";
+
+/// Transport mode within the access hierarchy. A restriction on a mode also
+/// applies to every mode beneath it unless that mode is tagged itself, so
+/// `motor_vehicle=no` restricts `motorcar` but `vehicle=no` with
+/// `bicycle=yes` still allows bicycles.
+///
+/// https://wiki.openstreetmap.org/wiki/Key:access#Land-based_transportation
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Mode {
+    /// Root of the hierarchy applying to every mode
+    Access,
+    Foot,
+    Horse,
+    /// Any vehicle, motorized or not
+    Vehicle,
+    Bicycle,
+    /// Horse-drawn vehicle
+    Carriage,
+    MotorVehicle,
+    Motorcycle,
+    /// Motorized bicycle with a speed limit
+    Moped,
+    Motorcar,
+    Motorhome,
+    /// Light commercial vehicle
+    Goods,
+    /// Heavy goods vehicle
+    Hgv,
+    Agricultural,
+    /// Public service vehicle
+    Psv,
+    Bus,
+    Taxi,
+    Emergency,
+}
+
+impl Mode {
+    /// Tag key for access restrictions of this mode
+    pub fn key(&self) -> &'static str {
+        match self {
+            Mode::Access => ACCESS,
+            Mode::Foot => FOOT,
+            Mode::Horse => HORSE,
+            Mode::Vehicle => VEHICLE,
+            Mode::Bicycle => BICYCLE,
+            Mode::Carriage => "carriage",
+            Mode::MotorVehicle => MOTOR_VEHICLE,
+            Mode::Motorcycle => MOTORCYCLE,
+            Mode::Moped => "moped",
+            Mode::Motorcar => MOTOR_CAR,
+            Mode::Motorhome => "motorhome",
+            Mode::Goods => "goods",
+            Mode::Hgv => "hgv",
+            Mode::Agricultural => "agricultural",
+            Mode::Psv => SERVICE_VEHICLE,
+            Mode::Bus => BUS,
+            Mode::Taxi => "taxi",
+            Mode::Emergency => "emergency",
+        }
+    }
+
+    /// More general mode whose restrictions apply to this one
+    pub fn parent(&self) -> Option<Mode> {
+        match self {
+            Mode::Access => None,
+            Mode::Foot | Mode::Horse | Mode::Vehicle => Some(Mode::Access),
+            Mode::Bicycle | Mode::Carriage | Mode::MotorVehicle => {
+                Some(Mode::Vehicle)
+            }
+            Mode::Motorhome => Some(Mode::Motorcar),
+            Mode::Bus | Mode::Taxi => Some(Mode::Psv),
+            _ => Some(Mode::MotorVehicle),
+        }
+    }
+
+    /// This mode followed by each more general mode up to `Access`
+    pub fn ancestors(&self) -> impl Iterator<Item = Mode> {
+        std::iter::successors(Some(*self), Mode::parent)
+    }
+}
+
+impl FromStr for Mode {
+    type Err = String;
+
+    fn from_str(key: &str) -> Result<Self, Self::Err> {
+        let modes = [
+            Mode::Access,
+            Mode::Foot,
+            Mode::Horse,
+            Mode::Vehicle,
+            Mode::Bicycle,
+            Mode::Carriage,
+            Mode::MotorVehicle,
+            Mode::Motorcycle,
+            Mode::Moped,
+            Mode::Motorcar,
+            Mode::Motorhome,
+            Mode::Goods,
+            Mode::Hgv,
+            Mode::Agricultural,
+            Mode::Psv,
+            Mode::Bus,
+            Mode::Taxi,
+            Mode::Emergency,
+        ];
+        modes
+            .iter()
+            .find(|m| m.key() == key)
+            .copied()
+            .ok_or_else(|| format!("unknown transport mode {}", key))
+    }
+}
+
+impl fmt::Display for Mode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.key())
+    }
+}
@@ -1,4 +1,9 @@
-use crate::{node::Node, tag::Tagged, ElementID, TagMap, Timestamp};
+use crate::{
+    geo::{self, BBox},
+    node::Node,
+    tag::{area::AreaRules, reverse::reverse_tags, Tagged},
+    ElementID, TagMap, Timestamp,
+};
 use serde::Deserialize;
 
 /// Collection of nodes representing a way of travel.
@@ -21,16 +26,113 @@ pub struct Way<'a> {
     pub tags: Option<TagMap>,
 }
 
+impl<'a> Way<'a> {
+    pub fn first_node(&self) -> Option<&'a Node> {
+        self.nodes.first().copied()
+    }
+
+    pub fn last_node(&self) -> Option<&'a Node> {
+        self.nodes.last().copied()
+    }
+
+    /// Whether the last node of the way is also the first
+    pub fn is_closed(&self) -> bool {
+        self.nodes.len() > 2
+            && self.nodes[0].id == self.nodes[self.nodes.len() - 1].id
+    }
+
+    /// Pairs of consecutive nodes in way order
+    pub fn segments(&self) -> impl Iterator<Item = (&'a Node, &'a Node)> + '_ {
+        self.nodes.windows(2).map(|pair| (pair[0], pair[1]))
+    }
+
+    /// Great-circle length of the way in meters
+    pub fn length_meters(&self) -> f64 {
+        self.segments()
+            .map(|(a, b)| geo::haversine(a.point(), b.point()))
+            .sum()
+    }
+
+    /// Bounding box of the way nodes or `None` if the way has no nodes
+    pub fn bbox(&self) -> Option<BBox> {
+        BBox::from_points(self.nodes.iter().map(|n| n.point()))
+    }
+
+    /// Copy of the way with its nodes in reverse order. Tags that depend on
+    /// direction, such as `oneway`, `incline` and `*:forward`, are flipped
+    /// so the reversed way has the same meaning. Roles in relations that
+    /// reference the way are updated separately with
+    /// `tag::reverse::reverse_roles`.
+    pub fn reversed(&self) -> Way<'a> {
+        Way {
+            nodes: self.nodes.iter().rev().copied().collect(),
+            id: self.id,
+            name: self.name.clone(),
+            timestamp: self.timestamp.clone(),
+            tags: self.tags.as_ref().map(reverse_tags),
+        }
+    }
+
+    /// Whether the way is closed and tagged as an area according to the
+    /// standard area rules
+    pub fn is_area(&self) -> bool {
+        self.is_area_with(AreaRules::standard())
+    }
+
+    /// Whether the way is closed and tagged as an area according to the given
+    /// rules
+    pub fn is_area_with(&self, rules: &AreaRules) -> bool {
+        self.is_closed() && rules.is_area(self)
+    }
+}
+
 impl<'a> Tagged for Way<'a> {
     fn get_tag(&self, key: &str) -> Option<&str> {
-        self.tags
-            .as_ref()
-            .and_then(|tags| tags.get(key).map(|value| *value))
+        self.tags.as_ref().and_then(|tags| tags.get(key))
     }
 
     fn has_tag(&self, key: &str) -> bool {
         self.tags
             .as_ref()
-            .map_or(false, |tags| tags.contains_key(key))
+            .is_some_and(|tags| tags.contains_key(key))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reversed_way_flips_direction_tags() {
+        let nodes: Vec<Node> = (0..3)
+            .map(|i| Node {
+                id: i,
+                lat: 43.6,
+                lon: -116.2 + i as f32 * 0.001,
+                ..Default::default()
+            })
+            .collect();
+        let mut tags = TagMap::new();
+        tags.insert("oneway", "yes");
+        tags.insert("incline", "up");
+        tags.insert("maxspeed:forward", "50");
+        tags.insert("highway", "residential");
+
+        let way = Way {
+            nodes: nodes.iter().collect(),
+            tags: Some(tags),
+            ..Default::default()
+        };
+        let reversed = way.reversed();
+
+        assert!(!way.is_closed());
+        assert_eq!(reversed.first_node().unwrap().id, 2);
+        assert_eq!(reversed.get_tag("oneway"), Some("-1"));
+        assert_eq!(reversed.get_tag("incline"), Some("down"));
+        assert_eq!(reversed.get_tag("maxspeed:backward"), Some("50"));
+        assert_eq!(reversed.get_tag("highway"), Some("residential"));
+        assert_eq!(reversed.segments().count(), 2);
+        assert!((way.length_meters() - 161.0).abs() < 1.0);
+        assert!(way.bbox().unwrap().contains(nodes[1].point()));
     }
 }